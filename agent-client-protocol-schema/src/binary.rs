@@ -0,0 +1,453 @@
+//! Decoded binary payloads of image, audio and blob content.
+
+use std::{fmt, ops::Deref, sync::Arc};
+
+use bytes::Bytes;
+
+use base64::{
+    Engine as _,
+    alphabet::STANDARD,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+use derive_more::Display;
+
+/// Standard base64 that also accepts payloads missing their `=` padding.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// The decoded bytes of a base64 payload.
+///
+/// Clones share one buffer, so a multi-megabyte image can be handed to
+/// several consumers without copying. Decoded payloads, `Vec<u8>`,
+/// `Arc<[u8]>` and [`Bytes`] are taken over without copying, and the data
+/// converts back into [`Bytes`] without copying as well.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct BinaryData(Bytes);
+
+impl BinaryData {
+    /// Wraps bytes; owned buffers are taken over rather than copied.
+    #[must_use]
+    pub fn new(bytes: impl Into<Self>) -> Self {
+        bytes.into()
+    }
+
+    /// Decodes standard base64, with or without padding.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError::InvalidBase64`] when `data` is not base64.
+    pub fn decode(data: &str) -> Result<Self, BinaryDataError> {
+        BASE64
+            .decode(data)
+            .map(Self::from)
+            .map_err(|error| BinaryDataError::InvalidBase64(error.to_string()))
+    }
+
+    /// Encodes the bytes as padded standard base64.
+    #[must_use]
+    pub fn encode(&self) -> String {
+        BASE64.encode(&self.0)
+    }
+
+    /// The decoded bytes.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The media type identified by the payload's leading magic bytes, if recognized.
+    ///
+    /// This is a best guess: some signatures are short or shared by several
+    /// formats, so a payload may be reported as a type it is not.
+    #[must_use]
+    pub fn sniff_media_type(&self) -> Option<&'static str> {
+        sniff(&self.0).map(|sniffed| sniffed.media_type)
+    }
+
+    /// Checks the payload against a declared media type.
+    ///
+    /// Only signatures that unambiguously identify one format are checked.
+    /// Payloads whose format is not recognized with certainty, and generic
+    /// declared types such as `application/octet-stream`, are accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError::MediaTypeMismatch`] when the payload is
+    /// certainly of another type.
+    pub fn check_media_type(&self, declared: &str) -> Result<(), BinaryDataError> {
+        match sniff(&self.0) {
+            Some(Sniffed {
+                media_type: detected,
+                certain: true,
+            }) if !media_types_match(declared, detected) => {
+                Err(BinaryDataError::MediaTypeMismatch {
+                    declared: declared.to_string(),
+                    detected,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Decodes `data` and checks it against `declared`, when known.
+pub(crate) fn decode_checked(
+    data: &str,
+    declared: Option<&str>,
+) -> Result<BinaryData, BinaryDataError> {
+    let bytes = BinaryData::decode(data)?;
+    if let Some(declared) = declared {
+        bytes.check_media_type(declared)?;
+    }
+    Ok(bytes)
+}
+
+/// The number of bytes `data` decodes to, computed without decoding it.
+pub(crate) fn decoded_len(data: &str) -> usize {
+    let data = data.trim_end_matches('=');
+    data.len() / 4 * 3 + (data.len() % 4).saturating_sub(1)
+}
+
+impl Deref for BinaryData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for BinaryData {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Bytes> for BinaryData {
+    fn from(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Arc<[u8]>> for BinaryData {
+    fn from(bytes: Arc<[u8]>) -> Self {
+        Self(Bytes::from_owner(bytes))
+    }
+}
+
+impl From<Vec<u8>> for BinaryData {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Bytes::from(bytes))
+    }
+}
+
+impl From<&[u8]> for BinaryData {
+    fn from(bytes: &[u8]) -> Self {
+        Self(Bytes::copy_from_slice(bytes))
+    }
+}
+
+impl From<BinaryData> for Bytes {
+    fn from(data: BinaryData) -> Self {
+        data.0
+    }
+}
+
+/// Copies the bytes into a new shared buffer.
+impl From<BinaryData> for Arc<[u8]> {
+    fn from(data: BinaryData) -> Self {
+        Self::from(data.as_bytes())
+    }
+}
+
+impl fmt::Debug for BinaryData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinaryData")
+            .field("len", &self.0.len())
+            .field("media_type", &self.sniff_media_type())
+            .finish()
+    }
+}
+
+/// A binary payload that cannot be used.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum BinaryDataError {
+    /// The payload is not valid base64.
+    #[display("invalid base64 payload: {_0}")]
+    InvalidBase64(String),
+    /// The payload's contents do not match its declared media type.
+    #[display("payload declared as {declared} looks like {detected}")]
+    MediaTypeMismatch {
+        /// The media type the payload was declared with.
+        declared: String,
+        /// The media type identified from the payload's magic bytes.
+        detected: &'static str,
+    },
+}
+
+impl std::error::Error for BinaryDataError {}
+
+/// A media type identified from a payload's leading bytes.
+struct Sniffed {
+    media_type: &'static str,
+    /// Whether the signature identifies the format unambiguously. Short
+    /// signatures and ones shared by related formats only give a best guess.
+    certain: bool,
+}
+
+/// Identifies common image, audio and document formats by their leading bytes.
+fn sniff(bytes: &[u8]) -> Option<Sniffed> {
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+    let certain = |media_type| Sniffed {
+        media_type,
+        certain: true,
+    };
+    let guess = |media_type| Sniffed {
+        media_type,
+        certain: false,
+    };
+    Some(match bytes {
+        [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => certain("image/png"),
+        [0xff, 0xd8, 0xff, ..] => certain("image/jpeg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => certain("image/gif"),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'E',
+            b'B',
+            b'P',
+            ..,
+        ] => certain("image/webp"),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'A',
+            b'V',
+            b'E',
+            ..,
+        ] => certain("audio/wav"),
+        [b'%', b'P', b'D', b'F', b'-', ..] => certain("application/pdf"),
+        [b'O', b'g', b'g', b'S', ..] => certain("audio/ogg"),
+        [b'f', b'L', b'a', b'C', ..] => certain("audio/flac"),
+        _ if at(4, b"ftypavif") => certain("image/avif"),
+        _ if at(4, b"ftypM4A ") => certain("audio/mp4"),
+        _ if is_bmp(bytes) => guess("image/bmp"),
+        _ if is_ico(bytes) => guess("image/x-icon"),
+        // Camera raw formats are TIFF containers too.
+        [b'I', b'I', 0x2a, 0x00, ..] | [b'M', b'M', 0x00, 0x2a, ..] => guess("image/tiff"),
+        // ID3 tags also prefix AAC streams, and bare frame syncs are two bytes.
+        [b'I', b'D', b'3', ..] | [0xff, 0xfb | 0xf3 | 0xf2, ..] => guess("audio/mpeg"),
+        [0xff, 0xf1 | 0xf9, ..] => guess("audio/aac"),
+        // EBML is shared with Matroska.
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => guess("video/webm"),
+        _ if at(4, b"ftypheic") || at(4, b"ftypheix") => guess("image/heic"),
+        _ if at(4, b"ftypisom") || at(4, b"ftypmp41") || at(4, b"ftypmp42") => guess("video/mp4"),
+        _ => return None,
+    })
+}
+
+/// A `BM` signature followed by zeroed reserved fields and a known DIB header size.
+fn is_bmp(bytes: &[u8]) -> bool {
+    let [
+        b'B',
+        b'M',
+        _,
+        _,
+        _,
+        _,
+        0,
+        0,
+        0,
+        0,
+        _,
+        _,
+        _,
+        _,
+        header,
+        0,
+        0,
+        0,
+        ..,
+    ] = bytes
+    else {
+        return false;
+    };
+    matches!(header, 12 | 40 | 52 | 56 | 64 | 108 | 124)
+}
+
+/// An icon directory with at least one entry whose reserved byte is zero.
+fn is_ico(bytes: &[u8]) -> bool {
+    let [0, 0, 1, 0, count_lo, count_hi, _, _, _, 0, ..] = bytes else {
+        return false;
+    };
+    bytes.len() >= 22 && (*count_lo, *count_hi) != (0, 0)
+}
+
+fn canonical_media_type(media_type: &str) -> String {
+    let essence = media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match essence.as_str() {
+        "image/jpg" | "image/pjpeg" => "image/jpeg".to_string(),
+        "audio/mp3" | "audio/mpeg3" | "audio/x-mpeg" => "audio/mpeg".to_string(),
+        "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => "audio/wav".to_string(),
+        "audio/x-flac" => "audio/flac".to_string(),
+        "audio/x-aac" => "audio/aac".to_string(),
+        "audio/m4a" | "audio/x-m4a" => "audio/mp4".to_string(),
+        "image/vnd.microsoft.icon" => "image/x-icon".to_string(),
+        _ => essence,
+    }
+}
+
+/// Whether a payload detected as `detected` may be declared as `declared`.
+///
+/// Container formats such as Ogg, `WebM` and MP4 carry audio or video, so their
+/// `audio/`, `video/` and `application/` variants are interchangeable, and the
+/// types of the codecs they carry, such as `audio/opus` in Ogg or `audio/aac`
+/// in MP4, are accepted as well.
+fn media_types_match(declared: &str, detected: &str) -> bool {
+    let declared = canonical_media_type(declared);
+    if declared == detected || declared == "application/octet-stream" {
+        return true;
+    }
+    let subtype = |media_type: &str| {
+        media_type
+            .split_once('/')
+            .map(|(_, subtype)| subtype.to_string())
+    };
+    let codecs: &[&str] = match subtype(detected).as_deref() {
+        Some("ogg") => &[
+            "audio/opus",
+            "audio/vorbis",
+            "audio/speex",
+            "audio/flac",
+            "video/theora",
+        ],
+        Some("webm") => &["audio/opus", "audio/vorbis"],
+        Some("mp4") => &["audio/aac", "audio/alac"],
+        _ => return false,
+    };
+    subtype(&declared) == subtype(detected) || codecs.contains(&declared.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn round_trips_base64_with_or_without_padding() {
+        let data = BinaryData::from(PNG);
+        let encoded = data.encode();
+        assert_eq!(encoded, "iVBORw0KGgoAAAANSUhEUg==");
+        assert_eq!(BinaryData::decode(&encoded).unwrap(), data);
+        assert_eq!(
+            BinaryData::decode(encoded.trim_end_matches('=')).unwrap(),
+            data
+        );
+        assert!(matches!(
+            BinaryData::decode("not base64!"),
+            Err(BinaryDataError::InvalidBase64(_))
+        ));
+
+        let shared = data.clone();
+        assert!(std::ptr::eq(shared.as_bytes(), data.as_bytes()));
+        assert_eq!(decoded_len(&encoded), PNG.len());
+        assert_eq!(decoded_len(encoded.trim_end_matches('=')), PNG.len());
+    }
+
+    #[test]
+    fn checks_declared_media_types() {
+        let png = BinaryData::from(PNG);
+        assert_eq!(png.sniff_media_type(), Some("image/png"));
+        assert_eq!(png.check_media_type("image/png; charset=binary"), Ok(()));
+        assert_eq!(png.check_media_type("application/octet-stream"), Ok(()));
+        assert_eq!(
+            png.check_media_type("image/jpeg"),
+            Err(BinaryDataError::MediaTypeMismatch {
+                declared: "image/jpeg".to_string(),
+                detected: "image/png",
+            })
+        );
+
+        let mp3 = BinaryData::from(&b"ID3\x04\0\0\0\0\0\0"[..]);
+        assert_eq!(mp3.check_media_type("audio/mp3"), Ok(()));
+        let ogg = BinaryData::from(&b"OggS\0\x02"[..]);
+        assert_eq!(ogg.check_media_type("application/ogg"), Ok(()));
+        let unknown = BinaryData::from(&b"plain text"[..]);
+        assert_eq!(unknown.check_media_type("image/png"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_only_certain_contradictions() {
+        // An MP4 with an unlisted brand, e.g. a QuickTime movie.
+        let quicktime = BinaryData::from(&b"\0\0\0\x14ftypqt  \0\0\0\0"[..]);
+        assert_eq!(quicktime.sniff_media_type(), None);
+        assert_eq!(quicktime.check_media_type("video/quicktime"), Ok(()));
+
+        // Text that happens to start with `BM`, and a payload starting with
+        // the ICO header bytes, are not mistaken for images.
+        let text = BinaryData::from(&b"BMW owners manual, 2nd edition"[..]);
+        assert_eq!(text.sniff_media_type(), None);
+        assert_eq!(text.check_media_type("text/plain"), Ok(()));
+        let not_icon = BinaryData::from(&b"\0\0\x01\0\0\0"[..]);
+        assert_eq!(not_icon.sniff_media_type(), None);
+
+        // Codecs may be declared in place of their container.
+        let opus = BinaryData::from(&b"OggS\0\x02\0\0\0\0\0\0\0\0OpusHead"[..]);
+        assert_eq!(opus.sniff_media_type(), Some("audio/ogg"));
+        assert_eq!(opus.check_media_type("audio/opus"), Ok(()));
+        assert_eq!(opus.check_media_type("audio/vorbis"), Ok(()));
+        let m4a = BinaryData::from(&b"\0\0\0\x20ftypM4A \0\0\0\0"[..]);
+        assert_eq!(m4a.sniff_media_type(), Some("audio/mp4"));
+        assert_eq!(m4a.check_media_type("audio/aac"), Ok(()));
+        assert!(m4a.check_media_type("audio/opus").is_err());
+
+        // Guesses are reported but never rejected.
+        let mp3 = BinaryData::from(&b"ID3\x04\0\0\0\0\0\0"[..]);
+        assert_eq!(mp3.sniff_media_type(), Some("audio/mpeg"));
+        assert_eq!(mp3.check_media_type("audio/aac"), Ok(()));
+        let mut bmp = b"BM\x46\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0".to_vec();
+        bmp.resize(64, 0);
+        let bmp = BinaryData::from(bmp);
+        assert_eq!(bmp.sniff_media_type(), Some("image/bmp"));
+        assert_eq!(bmp.check_media_type("image/x-ms-bmp"), Ok(()));
+    }
+
+    #[test]
+    fn takes_over_buffers_without_copying() {
+        let vec = PNG.to_vec();
+        let ptr = vec.as_ptr();
+        let data = BinaryData::from(vec);
+        assert_eq!(data.as_bytes().as_ptr(), ptr);
+
+        let bytes = Bytes::from(data.clone());
+        assert_eq!(bytes.as_ptr(), ptr);
+        assert_eq!(BinaryData::from(bytes).as_bytes().as_ptr(), ptr);
+
+        let shared: Arc<[u8]> = Arc::from(PNG);
+        let ptr = shared.as_ptr();
+        assert_eq!(BinaryData::from(shared).as_bytes().as_ptr(), ptr);
+    }
+}
@@ -0,0 +1,311 @@
+//! Reading local files as prompt context, shared by the `v1` and `v2` builders.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use crate::{BinaryData, Timestamp, Uri};
+
+/// Files larger than this are truncated (text) or refused (binary) unless configured otherwise.
+pub(crate) const DEFAULT_MAX_BYTES: usize = 256 * 1024;
+
+/// How many leading bytes are checked for NUL when deciding between text and binary.
+const BINARY_PROBE_LEN: usize = 8 * 1024;
+
+/// What protocol content says about a file besides its contents.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FileInfo {
+    pub(crate) uri: String,
+    pub(crate) name: String,
+    pub(crate) mime_type: Option<String>,
+    pub(crate) last_modified: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FileContents {
+    Text(String),
+    Blob(BinaryData),
+}
+
+/// Reads `path`, keeping only `lines` (1-based, inclusive) of a text file.
+///
+/// At most `max_bytes` plus one byte of the file, or of the selected lines,
+/// is read. Text is cut at the last line break within `max_bytes`; binary
+/// files over `max_bytes` fail with [`io::ErrorKind::FileTooLarge`], as do
+/// selections of binary files with [`io::ErrorKind::InvalidData`] and empty
+/// or 0-based selections with [`io::ErrorKind::InvalidInput`].
+pub(crate) fn read(
+    path: &Path,
+    lines: Option<&RangeInclusive<usize>>,
+    max_bytes: usize,
+) -> io::Result<(FileInfo, FileContents)> {
+    let path = std::path::absolute(path)?;
+    let uri = file_uri(&path, lines)?;
+    let mut file = File::open(&path)?;
+    let metadata = file.metadata()?;
+    let last_modified = metadata.modified().ok().map(Timestamp::from);
+
+    let mut head = Vec::new();
+    (&mut file)
+        .take(BINARY_PROBE_LEN as u64)
+        .read_to_end(&mut head)?;
+    let binary = head.contains(&0) || utf8_prefix(&head).is_none();
+    let max_len = u64::try_from(max_bytes).unwrap_or(u64::MAX);
+    let reader = io::Cursor::new(head).chain(file);
+
+    let contents = match lines {
+        Some(_) if binary => return Err(not_text()),
+        Some(lines) => FileContents::Text(read_lines(BufReader::new(reader), lines, max_bytes)?),
+        None if binary && metadata.len() > max_len => return Err(too_large(max_bytes)),
+        None => {
+            let mut bytes = Vec::new();
+            reader
+                .take(max_len.saturating_add(1))
+                .read_to_end(&mut bytes)?;
+            let text = if bytes.len() > max_bytes {
+                utf8_prefix(&bytes)
+            } else {
+                std::str::from_utf8(&bytes).ok()
+            };
+            match text {
+                Some(text) if !binary => FileContents::Text(truncate(text.to_string(), max_bytes)),
+                _ => blob(bytes, max_bytes)?,
+            }
+        }
+    };
+
+    let mime_type = guess_mime_type(&path)
+        .or_else(|| match &contents {
+            FileContents::Text(_) => Some("text/plain"),
+            FileContents::Blob(blob) => blob.sniff_media_type(),
+        })
+        .map(str::to_string);
+    let info = FileInfo {
+        uri,
+        name: file_name(&path),
+        mime_type,
+        last_modified,
+    };
+    Ok((info, contents))
+}
+
+/// Describes `path` and its size in bytes without reading its contents.
+pub(crate) fn stat(
+    path: &Path,
+    lines: Option<&RangeInclusive<usize>>,
+) -> io::Result<(FileInfo, u64)> {
+    let path = std::path::absolute(path)?;
+    let uri = file_uri(&path, lines)?;
+    let metadata = fs::metadata(&path)?;
+    let info = FileInfo {
+        uri,
+        name: file_name(&path),
+        mime_type: guess_mime_type(&path).map(str::to_string),
+        last_modified: metadata.modified().ok().map(Timestamp::from),
+    };
+    Ok((info, metadata.len()))
+}
+
+fn blob(bytes: Vec<u8>, max_bytes: usize) -> io::Result<FileContents> {
+    if bytes.len() > max_bytes {
+        return Err(too_large(max_bytes));
+    }
+    Ok(FileContents::Blob(bytes.into()))
+}
+
+fn too_large(max_bytes: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::FileTooLarge,
+        format!("binary file exceeds {max_bytes} bytes"),
+    )
+}
+
+fn not_text() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "cannot select lines of a binary file",
+    )
+}
+
+/// The longest valid UTF-8 prefix of `bytes`, dropping a character cut off at
+/// the end, or `None` if `bytes` is not UTF-8.
+fn utf8_prefix(bytes: &[u8]) -> Option<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        Err(error) if error.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..error.valid_up_to()]).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Reads `lines` of `reader`, keeping at most `max_bytes` plus one byte of
+/// them in memory and skipping earlier lines without buffering them.
+fn read_lines(
+    mut reader: impl BufRead,
+    lines: &RangeInclusive<usize>,
+    max_bytes: usize,
+) -> io::Result<String> {
+    for _ in 1..*lines.start() {
+        if !skip_line(&mut reader)? {
+            return Ok(String::new());
+        }
+    }
+    let mut text = Vec::new();
+    for _ in lines.clone() {
+        let limit = u64::try_from(max_bytes.saturating_add(1) - text.len()).unwrap_or(u64::MAX);
+        let read = (&mut reader).take(limit).read_until(b'\n', &mut text)?;
+        if read == 0 || text.len() > max_bytes {
+            break;
+        }
+    }
+    let text = if text.len() > max_bytes {
+        utf8_prefix(&text)
+    } else {
+        std::str::from_utf8(&text).ok()
+    };
+    Ok(truncate(text.ok_or_else(not_text)?.to_string(), max_bytes))
+}
+
+/// Consumes one line of `reader`, returning `false` at the end of input.
+fn skip_line(reader: &mut impl BufRead) -> io::Result<bool> {
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+        if let Some(newline) = buffer.iter().position(|&byte| byte == b'\n') {
+            reader.consume(newline + 1);
+            return Ok(true);
+        }
+        let len = buffer.len();
+        reader.consume(len);
+    }
+}
+
+fn truncate(mut text: String, max_bytes: usize) -> String {
+    if text.len() > max_bytes {
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = text[..end].rfind('\n').map_or(end, |newline| newline + 1);
+        text.truncate(end);
+    }
+    text
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The `file://` URI of an absolute path, with an `#L<start>:<end>`
+/// fragment for a selection.
+fn file_uri(path: &Path, lines: Option<&RangeInclusive<usize>>) -> io::Result<String> {
+    if lines.is_some_and(|lines| *lines.start() == 0 || lines.is_empty()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "line selections are 1-based and must not be empty",
+        ));
+    }
+    let uri = Uri::from_file_path(path)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    Ok(match lines {
+        Some(lines) => format!("{uri}#L{}:{}", lines.start(), lines.end()),
+        None => uri.into(),
+    })
+}
+
+/// The media type conventionally used for a file extension.
+fn guess_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "ts" | "tsx" | "mts" => "text/x-typescript",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "go" => "text/x-go",
+        "java" => "text/x-java",
+        "c" | "h" => "text/x-c",
+        "cc" | "cpp" | "cxx" | "hpp" => "text/x-c++",
+        "rb" => "text/x-ruby",
+        "sh" | "bash" | "zsh" => "text/x-shellscript",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "zip" => "application/zip",
+        "wasm" => "application/wasm",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn selections_add_a_line_fragment() {
+        assert_eq!(
+            file_uri(Path::new("/a/ü.rs"), Some(&(3..=7))).unwrap(),
+            "file:///a/%C3%BC.rs#L3:7"
+        );
+        for (start, end) in [(0, 2), (5, 4)] {
+            let error = file_uri(Path::new("/a.rs"), Some(&(start..=end))).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn selections_and_truncation_keep_whole_lines() {
+        let text = "one\ntwo\nthree\nfour\n";
+        let select = |lines, max_bytes| read_lines(text.as_bytes(), &lines, max_bytes).unwrap();
+        assert_eq!(select(2..=3, 100), "two\nthree\n");
+        assert_eq!(select(4..=9, 100), "four\n");
+        assert_eq!(select(7..=9, 100), "");
+        assert_eq!(select(1..=4, 10), "one\ntwo\n");
+        assert_eq!(truncate(text.to_string(), 10), "one\ntwo\n");
+        assert_eq!(truncate("héllo".to_string(), 2), "h");
+    }
+
+    #[test]
+    fn reads_stop_after_the_limit() {
+        let dir = std::env::temp_dir().join(format!("acp-file-context-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Invalid UTF-8 past the probe is only noticed if it is read.
+        let path = dir.join("long.txt");
+        let mut contents = "line\n".repeat(BINARY_PROBE_LEN).into_bytes();
+        contents.push(0xff);
+        fs::write(&path, &contents).unwrap();
+        let (_, text) = read(&path, None, 12).unwrap();
+        assert_eq!(text, FileContents::Text("line\nline\n".to_string()));
+        let (_, blob) = read(&path, None, contents.len()).unwrap();
+        assert!(matches!(blob, FileContents::Blob(blob) if blob.len() == contents.len()));
+
+        let (_, lines) = read(&path, Some(&(2..=usize::MAX)), 7).unwrap();
+        assert_eq!(lines, FileContents::Text("line\n".to_string()));
+        let error = read(&path, Some(&(1..=usize::MAX)), contents.len()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
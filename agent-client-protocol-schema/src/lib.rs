@@ -25,6 +25,14 @@
 //! - JSON-RPC envelope and routing types: [`v1::JsonRpcMessage`],
 //!   [`rpc::JsonRpcBatch`], [`v1::Request`], [`v1::Response`],
 //!   [`v1::Notification`], [`v1::RequestId`], [`v1::Error`].
+//! - Decoded image, audio and blob payloads checked against their declared
+//!   media type: [`BinaryData`].
+//! - RFC 3339 timestamps that keep their wire text and order by instant:
+//!   [`Timestamp`].
+//! - URIs with `file://` path conversion and session root checks: [`Uri`].
+//! - Prompt size estimates with a pluggable [`Tokenizer`].
+//! - Log-safe copies of protocol messages with secrets masked:
+//!   [`redact::Redactor`].
 //! - Aggregated routing enums: [`v1::AgentRequest`], [`v1::AgentResponse`],
 //!   [`v1::AgentNotification`], and the matching client-side trio used by SDK
 //!   crates to dispatch incoming JSON-RPC messages.
@@ -36,6 +44,9 @@
 //! Consumers that only need serialization can disable default features to omit
 //! the dependency and those trait implementations.
 //!
+//! The `time` and `chrono` features add conversions between [`Timestamp`] and
+//! those crates' date-time types.
+//!
 //! ## Versioning
 //!
 //! Stable protocol types are exposed through explicit version modules. For
@@ -45,15 +56,29 @@
 //! For the complete protocol specification and documentation, visit
 //! <https://agentclientprotocol.com>.
 
+mod binary;
+mod file_context;
+mod markdown;
+mod mcp_launch;
+pub mod redact;
+mod rfc3339;
 pub mod rpc;
 mod serde_util;
+mod session_catalog;
+mod timestamp;
+mod tokens;
+mod uri;
 pub mod v1;
 #[cfg(feature = "unstable_protocol_v2")]
 pub mod v2;
 mod version;
 
+pub use binary::{BinaryData, BinaryDataError};
 pub(crate) use serde_util::SkipListener;
 pub use serde_util::{IntoMaybeUndefined, IntoOption, MaybeUndefined};
+pub use timestamp::{LenientTimestamp, Timestamp, TimestampError};
+pub use tokens::{ContentSize, HeuristicTokenizer, Tokenizer};
+pub use uri::{Uri, UriError};
 pub use version::*;
 
 #[cfg(test)]
@@ -0,0 +1,202 @@
+//! Markdown building blocks shared by the `v1` and `v2` renderers.
+
+use std::fmt::Write as _;
+
+/// Number of unchanged lines kept around a change in a rendered diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Wraps `body` in a fenced code block whose fence is longer than any run of
+/// backticks inside it.
+pub(crate) fn fenced(info: &str, body: &str) -> String {
+    let longest_run = body
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let newline = if body.ends_with('\n') || body.is_empty() {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{fence}{info}\n{body}{newline}{fence}")
+}
+
+/// Renders a line-based unified diff between two texts.
+///
+/// Only the region between the common prefix and suffix is treated as
+/// changed, which is what agents produce for single edits.
+pub(crate) fn unified_diff(path: &str, old_text: Option<&str>, new_text: &str) -> String {
+    let old_lines: Vec<&str> = old_text
+        .map(|text| text.lines().collect())
+        .unwrap_or_default();
+    let new_lines: Vec<&str> = new_text.lines().collect();
+
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let mut out = String::new();
+    if old_text.is_some() {
+        let _ = writeln!(out, "--- {path}");
+    } else {
+        out.push_str("--- /dev/null\n");
+    }
+    let _ = writeln!(out, "+++ {path}");
+
+    for line in &old_lines[prefix.saturating_sub(DIFF_CONTEXT)..prefix] {
+        let _ = writeln!(out, " {line}");
+    }
+    for line in &old_lines[prefix..old_lines.len() - suffix] {
+        let _ = writeln!(out, "-{line}");
+    }
+    for line in &new_lines[prefix..new_lines.len() - suffix] {
+        let _ = writeln!(out, "+{line}");
+    }
+    let trailing = &new_lines[new_lines.len() - suffix..];
+    for line in &trailing[..trailing.len().min(DIFF_CONTEXT)] {
+        let _ = writeln!(out, " {line}");
+    }
+    out
+}
+
+/// Wraps `body` in a `<details>` element that renders collapsed.
+///
+/// `<` and `>` in `summary` and `body` are escaped, so neither can close the
+/// element early or inject HTML.
+pub(crate) fn collapsed(summary: &str, body: &str) -> String {
+    format!(
+        "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+        escape_html(summary),
+        escape_html(body)
+    )
+}
+
+/// Replaces `&`, `<` and `>` with HTML entities.
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Makes `text` safe to place on one line of Markdown, such as a list item,
+/// heading or link text.
+///
+/// Line breaks are collapsed into spaces, and backslashes, brackets and angle
+/// brackets are escaped so the text cannot end a link or open raw HTML.
+/// Emphasis and code spans are kept.
+pub(crate) fn inline(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if !escaped.is_empty() {
+            escaped.push(' ');
+        }
+        for c in line.chars() {
+            if matches!(c, '\\' | '[' | ']' | '<' | '>') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Renders a link to `uri` labelled with `text`.
+///
+/// The destination is wrapped in `<…>`, so spaces and parentheses in the URI
+/// do not end it.
+pub(crate) fn link(text: &str, uri: &str) -> String {
+    let uri = uri
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+        .replace('\n', "%0A")
+        .replace('\r', "%0D");
+    format!("[{}](<{uri}>)", inline(text))
+}
+
+/// Wraps `text` in a code span delimited by more backticks than it contains.
+pub(crate) fn code(text: &str) -> String {
+    let text = text.replace(['\n', '\r'], " ");
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let ticks = "`".repeat(longest_run + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{ticks} {text} {ticks}")
+    } else {
+        format!("{ticks}{text}{ticks}")
+    }
+}
+
+/// Prefixes every line of `body` with `> `.
+pub(crate) fn quoted(body: &str) -> String {
+    body.lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Picks a code block language from the extension of a path or URI.
+pub(crate) fn language_hint(path: &str) -> &str {
+    path.rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fence_outgrows_backticks_in_body() {
+        assert_eq!(fenced("rs", "let x = 1;"), "```rs\nlet x = 1;\n```");
+        assert_eq!(fenced("", "````\n"), "`````\n````\n`````");
+    }
+
+    #[test]
+    fn agent_text_cannot_break_out_of_its_markup() {
+        assert_eq!(
+            link("see [docs]", "https://x.test/a (b)"),
+            "[see \\[docs\\]](<https://x.test/a (b)>)"
+        );
+        assert_eq!(
+            link("x", "https://x.test/>\n"),
+            "[x](<https://x.test/%3E%0A>)"
+        );
+        assert_eq!(inline("one\n\n- [ ] two <b>"), "one - \\[ \\] two \\<b\\>");
+        assert_eq!(code("a`b"), "``a`b``");
+        assert_eq!(code("`x"), "`` `x ``");
+        assert_eq!(
+            collapsed("<b>", "</details>\n<script>"),
+            "<details>\n<summary>&lt;b&gt;</summary>\n\n\
+             &lt;/details&gt;\n&lt;script&gt;\n\n</details>"
+        );
+    }
+
+    #[test]
+    fn unified_diff_keeps_context_around_change() {
+        let diff = unified_diff("a.txt", Some("1\n2\n3\n4\n5\n"), "1\n2\n3\nfour\n5\n");
+        assert_eq!(diff, "--- a.txt\n+++ a.txt\n 1\n 2\n 3\n-4\n+four\n 5\n");
+        assert_eq!(
+            unified_diff("new.txt", None, "hello\n"),
+            "--- /dev/null\n+++ new.txt\n+hello\n"
+        );
+    }
+}
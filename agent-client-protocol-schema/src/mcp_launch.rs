@@ -0,0 +1,65 @@
+//! Launch-spec helpers shared by the `v1` and `v2` MCP launch modules.
+
+use crate::redact::{REDACTED, is_secret_name};
+
+/// Replaces the values of secret-looking entries with [`REDACTED`].
+pub(crate) fn redact_pairs(pairs: &[(String, String)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_secret_name(name) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` references in `input`.
+///
+/// An unterminated `${` is kept literally. Returns the name of the first
+/// variable that is neither set nor defaulted.
+pub(crate) fn expand_vars(
+    input: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let Some(end) = reference.find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let (variable, default) = match reference[..end].split_once(":-") {
+            Some((variable, default)) => (variable, Some(default)),
+            None => (&reference[..end], None),
+        };
+        let value = lookup(variable)
+            .or_else(|| default.map(str::to_string))
+            .ok_or_else(|| variable.to_string())?;
+        out.push_str(&value);
+        rest = &reference[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables_expand_with_defaults() {
+        let lookup = |name: &str| (name == "HOME").then(|| "/home/me".to_string());
+        assert_eq!(
+            expand_vars("${HOME}/bin:${PATH:-/usr/bin}", &lookup).unwrap(),
+            "/home/me/bin:/usr/bin"
+        );
+        assert_eq!(expand_vars("cost: ${", &lookup).unwrap(), "cost: ${");
+        assert_eq!(expand_vars("${TOKEN}", &lookup), Err("TOKEN".to_string()));
+    }
+}
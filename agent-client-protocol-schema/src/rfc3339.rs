@@ -0,0 +1,202 @@
+//! Minimal RFC 3339 timestamp parsing and formatting.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// Parses an RFC 3339 timestamp into nanoseconds since the Unix epoch.
+pub(crate) fn parse_unix_nanos(input: &str) -> Option<i128> {
+    let bytes = input.as_bytes();
+    if bytes.len() < 20 || !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = input.get(range)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    if bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut rest = &input[19..];
+    let mut nanos: i128 = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        for (index, digit) in fraction[..digits].bytes().enumerate() {
+            if index < 9 {
+                nanos += i128::from(digit - b'0') * 10_i128.pow(8 - u32::try_from(index).ok()?);
+            }
+        }
+        rest = &fraction[digits..];
+    }
+
+    let offset_seconds = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let hours = number(input.len() - 5..input.len() - 3)?;
+            let minutes = number(input.len() - 2..input.len())?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second
+        - offset_seconds;
+    Some(i128::from(seconds) * NANOS_PER_SECOND + nanos)
+}
+
+/// Formats nanoseconds since the Unix epoch as a UTC RFC 3339 timestamp,
+/// omitting trailing zeros from the fractional seconds.
+pub(crate) fn format_unix_nanos(unix_nanos: i128) -> String {
+    let seconds = unix_nanos.div_euclid(NANOS_PER_SECOND);
+    let nanos = unix_nanos.rem_euclid(NANOS_PER_SECOND);
+    let days = i64::try_from(seconds.div_euclid(86_400)).unwrap_or(i64::MAX);
+    let second_of_day = seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let mut formatted = format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60
+    );
+    if nanos != 0 {
+        let fraction = format!("{nanos:09}");
+        formatted.push('.');
+        formatted.push_str(fraction.trim_end_matches('0'));
+    }
+    formatted.push('Z');
+    formatted
+}
+
+/// Converts a time to nanoseconds since the Unix epoch.
+pub(crate) fn to_unix_nanos(time: SystemTime) -> i128 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => i128::try_from(after.as_nanos()).unwrap_or(i128::MAX),
+        Err(before) => -i128::try_from(before.duration().as_nanos()).unwrap_or(i128::MAX),
+    }
+}
+
+/// Converts nanoseconds since the Unix epoch to a time, if representable.
+pub(crate) fn from_unix_nanos(unix_nanos: i128) -> Option<SystemTime> {
+    let magnitude = unix_nanos.unsigned_abs();
+    let duration = Duration::new(
+        u64::try_from(magnitude / 1_000_000_000).ok()?,
+        u32::try_from(magnitude % 1_000_000_000).ok()?,
+    );
+    if unix_nanos >= 0 {
+        UNIX_EPOCH.checked_add(duration)
+    } else {
+        UNIX_EPOCH.checked_sub(duration)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date for a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_offsets_and_fractions() {
+        let utc = parse_unix_nanos("2025-01-02T03:04:05Z").unwrap();
+        assert_eq!(utc, 1_735_787_045 * NANOS_PER_SECOND);
+        assert_eq!(
+            parse_unix_nanos("2025-01-02T04:04:05.5+01:00").unwrap(),
+            utc + NANOS_PER_SECOND / 2
+        );
+        assert_eq!(
+            parse_unix_nanos("1969-12-31t23:59:59z").unwrap(),
+            -NANOS_PER_SECOND
+        );
+        assert_eq!(
+            format_unix_nanos(utc + 120_000_000),
+            "2025-01-02T03:04:05.12Z"
+        );
+        assert_eq!(
+            format_unix_nanos(to_unix_nanos(
+                from_unix_nanos(parse_unix_nanos("2024-02-29T12:00:00-05:30").unwrap()).unwrap()
+            )),
+            "2024-02-29T17:30:00Z"
+        );
+        assert_eq!(
+            format_unix_nanos(-NANOS_PER_SECOND / 2),
+            "1969-12-31T23:59:59.5Z"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for input in [
+            "2025-01-02",
+            "2025-01-02T03:04:05",
+            "2025-02-30T00:00:00Z",
+            "2025-01-02T24:00:00Z",
+            "2025-01-02T03:04:05.Z",
+            "2025-01-02T03:04:05+0100",
+            "yesterday at noon",
+        ] {
+            assert_eq!(parse_unix_nanos(input), None, "{input}");
+        }
+    }
+}
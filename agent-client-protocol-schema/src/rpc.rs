@@ -3,10 +3,18 @@
 //! These types model the JSON-RPC 2.0 request, response, notification, and
 //! batch envelopes that wrap ACP method-specific payloads.
 
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicI64, Ordering as AtomicOrdering},
+    },
+    time::{Duration, Instant},
+};
 
 use derive_more::{Display, From};
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use serde_with::skip_serializing_none;
 
 /// JSON RPC Request Id
@@ -97,6 +105,14 @@ impl<R, E> Response<R, E> {
             },
         }
     }
+
+    /// The id of the request this response answers.
+    #[must_use]
+    pub fn id(&self) -> &RequestId {
+        match self {
+            Self::Result { id, .. } | Self::Error { id, .. } => id,
+        }
+    }
 }
 
 /// A JSON-RPC notification object.
@@ -231,6 +247,445 @@ where
     }
 }
 
+/// A request or notification: a message that invokes a method on the peer.
+///
+/// Requests carry an `id` and expect a response; notifications do not.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[allow(
+    clippy::exhaustive_enums,
+    reason = "This comes from the JSON-RPC specification itself"
+)]
+#[serde(untagged)]
+pub enum Call<Params> {
+    /// A request, answered with a response carrying the same id.
+    Request(Request<Params>),
+    /// A notification, never answered.
+    Notification(Notification<Params>),
+}
+
+impl<Params> Call<Params> {
+    /// The method to invoke.
+    #[must_use]
+    pub fn method(&self) -> &str {
+        match self {
+            Self::Request(request) => &request.method,
+            Self::Notification(notification) => &notification.method,
+        }
+    }
+
+    /// The id of a request; notifications have none.
+    #[must_use]
+    pub fn id(&self) -> Option<&RequestId> {
+        match self {
+            Self::Request(request) => Some(&request.id),
+            Self::Notification(_) => None,
+        }
+    }
+}
+
+/// Error returned when a JSON-RPC batch contains two requests with the same id.
+///
+/// Their responses could not be told apart, so the whole batch is rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[display("JSON-RPC batch contains more than one request with id {_0}")]
+#[non_exhaustive]
+pub struct DuplicateRequestId(pub RequestId);
+
+impl std::error::Error for DuplicateRequestId {}
+
+impl<Params> JsonRpcBatch<Call<Params>> {
+    /// The ids of the requests in this batch, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DuplicateRequestId`] for the first id used by two requests.
+    pub fn request_ids(&self) -> Result<Vec<RequestId>, DuplicateRequestId> {
+        let mut seen = HashSet::new();
+        self.0
+            .iter()
+            .filter_map(|message| message.inner().id())
+            .map(|id| {
+                if seen.insert(id) {
+                    Ok(id.clone())
+                } else {
+                    Err(DuplicateRequestId(id.clone()))
+                }
+            })
+            .collect()
+    }
+
+    /// Splits this batch into its requests and its notifications.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DuplicateRequestId`] for the first id used by two requests.
+    pub fn split(self) -> Result<SplitBatch<Params>, DuplicateRequestId> {
+        self.request_ids()?;
+        let mut split = SplitBatch {
+            requests: Vec::new(),
+            notifications: Vec::new(),
+        };
+        for message in self.0 {
+            match message.into_inner() {
+                Call::Request(request) => split.requests.push(request),
+                Call::Notification(notification) => split.notifications.push(notification),
+            }
+        }
+        Ok(split)
+    }
+}
+
+/// A received batch split by [`JsonRpcBatch::split`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SplitBatch<Params> {
+    /// The requests, in their original order; each must be answered.
+    pub requests: Vec<Request<Params>>,
+    /// The notifications, in their original order.
+    pub notifications: Vec<Notification<Params>>,
+}
+
+impl<R, E> JsonRpcBatch<Response<R, E>> {
+    /// Assembles the reply to a received batch from the responses to its
+    /// requests.
+    ///
+    /// Returns `None` when there are no responses: a batch of only
+    /// notifications must not be answered at all, not even with `[]`.
+    #[must_use]
+    pub fn reply(responses: impl IntoIterator<Item = Response<R, E>>) -> Option<Self> {
+        Self::new(responses.into_iter().map(JsonRpcMessage::wrap).collect()).ok()
+    }
+
+    /// Matches the responses in this batch to the ids of the requests sent
+    /// in a batch.
+    ///
+    /// Peers may answer a batch in any order, so responses are matched by id.
+    #[must_use]
+    pub fn correlate(self, sent: &[RequestId]) -> CorrelatedBatch<R, E> {
+        let mut outstanding: HashSet<&RequestId> = sent.iter().collect();
+        let mut answered = HashMap::new();
+        let mut correlated = CorrelatedBatch {
+            responses: Vec::new(),
+            missing: Vec::new(),
+            duplicates: Vec::new(),
+            unexpected: Vec::new(),
+        };
+        for response in self.0.into_iter().map(JsonRpcMessage::into_inner) {
+            if outstanding.remove(response.id()) {
+                answered.insert(response.id().clone(), response);
+            } else if answered.contains_key(response.id()) {
+                correlated.duplicates.push(response);
+            } else {
+                correlated.unexpected.push(response);
+            }
+        }
+        for id in sent {
+            match answered.remove(id) {
+                Some(response) => correlated.responses.push(response),
+                None if !correlated.missing.contains(id) => correlated.missing.push(id.clone()),
+                None => {}
+            }
+        }
+        correlated
+    }
+}
+
+/// Responses to a sent batch, matched to its requests by
+/// [`JsonRpcBatch::correlate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CorrelatedBatch<R, E> {
+    /// One response per answered request, in the order the requests were sent.
+    pub responses: Vec<Response<R, E>>,
+    /// Ids of sent requests that received no response.
+    pub missing: Vec<RequestId>,
+    /// Further responses to requests that were already answered.
+    pub duplicates: Vec<Response<R, E>>,
+    /// Responses whose id matches no sent request, such as `null` ids
+    /// reported for unparseable requests.
+    pub unexpected: Vec<Response<R, E>>,
+}
+
+impl<R, E> CorrelatedBatch<R, E> {
+    /// Returns whether every sent request was answered exactly once.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.duplicates.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+/// Response payloads whose shape depends on the method of the request they answer.
+///
+/// Several ACP responses are empty objects, so an untagged response enum
+/// cannot tell them apart on its own; decoding with the request's method can.
+pub trait MethodResponse: Sized {
+    /// Decodes the `result` of a response to a `method` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from deserializing `result` as the method's response type.
+    fn decode_result(method: &str, result: &RawValue) -> serde_json::Result<Self>;
+}
+
+/// Allocates ids for outgoing requests: increasing numbers starting at 0.
+///
+/// Ids are allocated atomically, so one generator can be shared between threads.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RequestIdGenerator {
+    next: AtomicI64,
+}
+
+impl RequestIdGenerator {
+    /// Creates a generator whose first id is `0`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a generator whose first id is `first`.
+    #[must_use]
+    pub fn starting_at(first: i64) -> Self {
+        Self {
+            next: AtomicI64::new(first),
+        }
+    }
+
+    /// Returns a fresh id.
+    pub fn next_id(&self) -> RequestId {
+        RequestId::Number(self.next.fetch_add(1, AtomicOrdering::Relaxed))
+    }
+}
+
+/// A flag shared between the code handling a request and the code that
+/// cancels it.
+///
+/// Clones observe the same flag, so a handler can poll
+/// [`Self::is_cancelled`] while another thread cancels the request.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, returning `true` if it was not cancelled before.
+    #[expect(
+        clippy::must_use_candidate,
+        reason = "callers usually cancel without caring whether someone else did first"
+    )]
+    pub fn cancel(&self) -> bool {
+        !self.0.swap(true, AtomicOrdering::Relaxed)
+    }
+
+    /// Returns whether the token has been cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// An outgoing request that has not been answered yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PendingRequest {
+    /// The id the request was sent with.
+    pub id: RequestId,
+    /// The method the request was sent with.
+    pub method: Arc<str>,
+    /// When the request was registered.
+    pub sent_at: Instant,
+    /// When the request times out, if ever.
+    pub deadline: Option<Instant>,
+    /// Whether `$/cancel_request` has been sent for the request.
+    ///
+    /// The peer still answers cancelled requests, with a result or a
+    /// `RequestCancelled` error, so they stay pending until then.
+    pub cancelled: bool,
+}
+
+impl PendingRequest {
+    /// Returns whether the request's deadline has passed at `now`.
+    #[must_use]
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.deadline.is_some_and(|deadline| deadline <= now)
+    }
+}
+
+/// Tracks outgoing requests until their responses arrive.
+///
+/// Each request is registered with the method it was sent with, so its
+/// response can be decoded as that method's response type with
+/// [`Self::resolve`].
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct PendingRequests {
+    ids: RequestIdGenerator,
+    pending: HashMap<RequestId, PendingRequest>,
+    timeout: Option<Duration>,
+}
+
+impl PendingRequests {
+    /// Creates an empty table whose requests never time out.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Times out requests registered from now on after `timeout`.
+    #[must_use]
+    pub fn timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
+    /// Allocates an id for a `method` request and records it as pending.
+    pub fn register(&mut self, method: impl Into<Arc<str>>) -> RequestId {
+        self.register_with_timeout(method, self.timeout)
+    }
+
+    /// Like [`Self::register`], with a timeout specific to this request.
+    pub fn register_with_timeout(
+        &mut self,
+        method: impl Into<Arc<str>>,
+        timeout: Option<Duration>,
+    ) -> RequestId {
+        let id = self.ids.next_id();
+        let sent_at = Instant::now();
+        self.pending.insert(
+            id.clone(),
+            PendingRequest {
+                id: id.clone(),
+                method: method.into(),
+                sent_at,
+                deadline: timeout.and_then(|timeout| sent_at.checked_add(timeout)),
+                cancelled: false,
+            },
+        );
+        id
+    }
+
+    /// Builds a `method` request with a fresh id and records it as pending.
+    pub fn request<Params>(
+        &mut self,
+        method: impl Into<Arc<str>>,
+        params: Option<Params>,
+    ) -> Request<Params> {
+        let method = method.into();
+        Request {
+            id: self.register(method.clone()),
+            method,
+            params,
+        }
+    }
+
+    /// The pending request with `id`, if any.
+    #[must_use]
+    pub fn get(&self, id: &RequestId) -> Option<&PendingRequest> {
+        self.pending.get(id)
+    }
+
+    /// The number of pending requests.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns whether no request is pending.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Marks the request with `id` as cancelled.
+    ///
+    /// Returns `true` when a `$/cancel_request` notification should be sent:
+    /// the request is pending and was not cancelled before.
+    pub fn cancel(&mut self, id: &RequestId) -> bool {
+        self.pending
+            .get_mut(id)
+            .is_some_and(|pending| !std::mem::replace(&mut pending.cancelled, true))
+    }
+
+    /// Stops tracking the request with `id` and returns it, if it was pending.
+    pub fn complete(&mut self, id: &RequestId) -> Option<PendingRequest> {
+        self.pending.remove(id)
+    }
+
+    /// Stops tracking every request whose deadline has passed at `now` and
+    /// returns them, oldest first.
+    pub fn expire(&mut self, now: Instant) -> Vec<PendingRequest> {
+        let mut expired: Vec<_> = self
+            .pending
+            .extract_if(|_, pending| pending.is_expired(now))
+            .map(|(_, pending)| pending)
+            .collect();
+        expired.sort_by_key(|pending| pending.sent_at);
+        expired
+    }
+
+    /// Matches `response` to its pending request and decodes its result as
+    /// the response to that request's method.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ResolveError::Unknown`] when no request with the response's
+    /// id is pending, and [`ResolveError::Decode`] when the result does not
+    /// match the method's response type. The request is no longer pending
+    /// in the latter case.
+    pub fn resolve<R, E>(
+        &mut self,
+        response: Response<Box<RawValue>, E>,
+    ) -> Result<(PendingRequest, Response<R, E>), ResolveError>
+    where
+        R: MethodResponse,
+    {
+        let (id, result) = match response {
+            Response::Result { id, result } => (id, Ok(result)),
+            Response::Error { id, error } => (id, Err(error)),
+        };
+        let pending = self
+            .complete(&id)
+            .ok_or_else(|| ResolveError::Unknown(id.clone()))?;
+        let result = match result {
+            Ok(result) => Ok(R::decode_result(&pending.method, &result).map_err(|error| {
+                ResolveError::Decode {
+                    id: id.clone(),
+                    method: pending.method.to_string(),
+                    error: error.to_string(),
+                }
+            })?),
+            Err(error) => Err(error),
+        };
+        Ok((pending, Response::new(id, result)))
+    }
+}
+
+/// A response that [`PendingRequests::resolve`] cannot match or decode.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum ResolveError {
+    /// No request with this id is pending.
+    #[display("no pending request with id {_0}")]
+    Unknown(RequestId),
+    /// The result is not a valid response to the request's method.
+    #[display("invalid {method} response for request {id}: {error}")]
+    Decode {
+        /// The id of the request.
+        id: RequestId,
+        /// The method the request was sent with.
+        method: String,
+        /// The deserialization error.
+        error: String,
+    },
+}
+
+impl std::error::Error for ResolveError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -327,6 +782,84 @@ mod tests {
         assert_eq!(deserialized.as_slice()[0].inner().method.as_ref(), "cancel");
     }
 
+    fn call(id: Option<i64>, method: &str) -> JsonRpcMessage<Call<Value>> {
+        JsonRpcMessage::wrap(match id {
+            Some(id) => Call::Request(Request {
+                id: id.into(),
+                method: method.into(),
+                params: None,
+            }),
+            None => Call::Notification(Notification {
+                method: method.into(),
+                params: None,
+            }),
+        })
+    }
+
+    #[test]
+    fn batches_split_into_requests_and_notifications() {
+        let batch: JsonRpcBatch<Call<Value>> = serde_json::from_value(json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "session/prompt", "params": {}},
+            {"jsonrpc": "2.0", "method": "session/cancel", "params": {}},
+            {"jsonrpc": "2.0", "id": "b", "method": "session/list"},
+        ]))
+        .unwrap();
+        assert_eq!(
+            batch.request_ids().unwrap(),
+            [1.into(), "b".to_string().into()]
+        );
+        let split = batch.split().unwrap();
+        assert_eq!(split.requests.len(), 2);
+        assert_eq!(&*split.notifications[0].method, "session/cancel");
+
+        let duplicate = JsonRpcBatch::new(vec![
+            call(Some(7), "a"),
+            call(None, "n"),
+            call(Some(7), "b"),
+        ])
+        .unwrap();
+        assert_eq!(duplicate.split(), Err(DuplicateRequestId(7.into())));
+    }
+
+    #[test]
+    fn notification_only_batches_get_no_reply() {
+        assert_eq!(JsonRpcBatch::<Response<Value, ()>>::reply([]), None);
+        let reply = JsonRpcBatch::reply([Response::<_, ()>::new(1, Ok(json!({})))]).unwrap();
+        assert_eq!(
+            serde_json::to_value(reply).unwrap(),
+            json!([{"jsonrpc": "2.0", "id": 1, "result": {}}])
+        );
+    }
+
+    #[test]
+    fn batch_responses_correlate_by_id() {
+        let sent = [1.into(), 2.into(), 3.into()];
+        let batch = JsonRpcBatch::new(
+            [
+                Response::new(3, Ok("three")),
+                Response::new(1, Err(())),
+                Response::new(3, Ok("again")),
+                Response::new(RequestId::Null, Err(())),
+            ]
+            .into_iter()
+            .map(JsonRpcMessage::wrap)
+            .collect(),
+        )
+        .unwrap();
+        let correlated = batch.correlate(&sent);
+        assert_eq!(
+            correlated.responses,
+            [Response::new(1, Err(())), Response::new(3, Ok("three"))]
+        );
+        assert_eq!(correlated.missing, [2.into()]);
+        assert_eq!(correlated.duplicates, [Response::new(3, Ok("again"))]);
+        assert_eq!(
+            correlated.unexpected,
+            [Response::new(RequestId::Null, Err(()))]
+        );
+        assert!(!correlated.is_complete());
+    }
+
     #[test]
     fn notification_wire_format() {
         // Test client -> agent notification wire format
@@ -389,4 +922,66 @@ mod tests {
             })
         );
     }
+
+    #[derive(Debug, PartialEq)]
+    enum TestResponse {
+        Empty,
+        Count(u32),
+    }
+
+    impl MethodResponse for TestResponse {
+        fn decode_result(method: &str, result: &RawValue) -> serde_json::Result<Self> {
+            match method {
+                "count" => serde_json::from_str(result.get()).map(Self::Count),
+                _ => Ok(Self::Empty),
+            }
+        }
+    }
+
+    fn raw(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn pending_requests_decode_responses_by_method() {
+        let mut pending = PendingRequests::new();
+        let ping = pending.register("ping");
+        let count = pending.request("count", Some(json!({})));
+        assert_eq!((ping.clone(), count.id.clone()), (0.into(), 1.into()));
+        assert_eq!(pending.len(), 2);
+
+        let (request, response) = pending
+            .resolve::<TestResponse, ()>(Response::new(count.id.clone(), Ok(raw("3"))))
+            .unwrap();
+        assert_eq!(&*request.method, "count");
+        assert_eq!(response, Response::new(1, Ok(TestResponse::Count(3))));
+
+        assert_eq!(
+            pending.resolve::<TestResponse, ()>(Response::new(count.id, Ok(raw("3")))),
+            Err(ResolveError::Unknown(1.into()))
+        );
+        let (_, response) = pending
+            .resolve::<TestResponse, &str>(Response::new(ping, Err("boom")))
+            .unwrap();
+        assert_eq!(response, Response::new(0, Err("boom")));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn pending_requests_expire_and_cancel_once() {
+        let mut pending = PendingRequests::new().timeout(Duration::from_secs(30));
+        let slow = pending.register("slow");
+        let patient = pending.register_with_timeout("patient", None);
+
+        assert!(pending.cancel(&slow));
+        assert!(!pending.cancel(&slow));
+        assert!(!pending.cancel(&RequestId::Str("unknown".into())));
+        assert!(pending.get(&slow).unwrap().cancelled);
+
+        assert!(pending.expire(Instant::now()).is_empty());
+        let expired = pending.expire(Instant::now() + Duration::from_secs(31));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, slow);
+        assert!(pending.get(&patient).is_some());
+    }
 }
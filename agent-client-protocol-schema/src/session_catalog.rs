@@ -0,0 +1,133 @@
+//! Cursor pagination shared by the `v1` and `v2` session catalogs.
+
+use std::cmp::Reverse;
+
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, KeyInit as _, Mac as _};
+use sha2::Sha256;
+
+use crate::Timestamp;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Where a page ended: the sort key of the last session returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CursorPosition {
+    /// `updatedAt` in nanoseconds since the Unix epoch, if known.
+    pub(crate) updated: Option<i128>,
+    /// Session id, breaking ties between equal timestamps.
+    pub(crate) session_id: String,
+}
+
+impl CursorPosition {
+    pub(crate) fn new(updated_at: Option<&Timestamp>, session_id: &str) -> Self {
+        Self {
+            updated: updated_at.and_then(Timestamp::unix_nanos),
+            session_id: session_id.to_string(),
+        }
+    }
+
+    /// Most recently updated first, sessions without a timestamp last, then by id.
+    fn sort_key(&self) -> (bool, Reverse<Option<i128>>, &str) {
+        (
+            self.updated.is_none(),
+            Reverse(self.updated),
+            &self.session_id,
+        )
+    }
+}
+
+/// Sorts `items`, drops everything up to and including `after`, and returns
+/// at most `page_size` items plus the position to resume from when more remain.
+pub(crate) fn paginate<T>(
+    mut items: Vec<T>,
+    position: impl Fn(&T) -> CursorPosition,
+    after: Option<&CursorPosition>,
+    page_size: usize,
+) -> (Vec<T>, Option<CursorPosition>) {
+    let mut keyed: Vec<_> = items
+        .drain(..)
+        .map(|item| (position(&item), item))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| a.sort_key().cmp(&b.sort_key()));
+    let start = after.map_or(0, |after| {
+        keyed.partition_point(|(key, _)| key.sort_key() <= after.sort_key())
+    });
+    let mut page: Vec<_> = keyed.into_iter().skip(start).take(page_size + 1).collect();
+    let next = if page.len() > page_size {
+        page.truncate(page_size);
+        page.last().map(|(key, _)| key.clone())
+    } else {
+        None
+    };
+    (page.into_iter().map(|(_, item)| item).collect(), next)
+}
+
+/// Encodes a position as an opaque cursor bound to `secret` and the `cwd` filter.
+pub(crate) fn encode_cursor(secret: &[u8], filter: &str, position: &CursorPosition) -> String {
+    let updated = position
+        .updated
+        .map(|updated| updated.to_string())
+        .unwrap_or_default();
+    let payload = format!("{updated}\n{}", position.session_id);
+    let tag = mac(secret, filter, &payload).finalize().into_bytes();
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(&payload),
+        URL_SAFE_NO_PAD.encode(tag)
+    )
+}
+
+/// Decodes a cursor produced by [`encode_cursor`] with the same secret and filter.
+pub(crate) fn decode_cursor(secret: &[u8], filter: &str, cursor: &str) -> Option<CursorPosition> {
+    let (encoded, tag) = cursor.rsplit_once('.')?;
+    let payload = String::from_utf8(URL_SAFE_NO_PAD.decode(encoded).ok()?).ok()?;
+    let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+    // `verify_slice` compares in constant time.
+    mac(secret, filter, &payload).verify_slice(&tag).ok()?;
+    let (updated, session_id) = payload.split_once('\n')?;
+    Some(CursorPosition {
+        updated: if updated.is_empty() {
+            None
+        } else {
+            Some(updated.parse().ok()?)
+        },
+        session_id: session_id.to_string(),
+    })
+}
+
+/// HMAC-SHA256 over the length-prefixed `filter` followed by `payload`.
+fn mac(secret: &[u8], filter: &str, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(filter.len() as u64).to_be_bytes());
+    mac.update(filter.as_bytes());
+    mac.update(payload.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_are_bound_to_secret_and_filter() {
+        let position = CursorPosition::new(
+            Timestamp::parse("2025-01-02T03:04:05Z").ok().as_ref(),
+            "sess-1",
+        );
+        let cursor = encode_cursor(b"secret", "/repo", &position);
+        assert_eq!(decode_cursor(b"secret", "/repo", &cursor), Some(position));
+        assert_eq!(decode_cursor(b"other", "/repo", &cursor), None);
+        assert_eq!(decode_cursor(b"secret", "/elsewhere", &cursor), None);
+
+        let forged = format!(
+            "{}{}",
+            URL_SAFE_NO_PAD.encode("\nsess-9"),
+            &cursor[cursor.find('.').unwrap()..]
+        );
+        assert_eq!(decode_cursor(b"secret", "/repo", &forged), None);
+
+        let truncated = &cursor[..cursor.len() - 1];
+        assert_eq!(decode_cursor(b"secret", "/repo", truncated), None);
+    }
+}
@@ -0,0 +1,361 @@
+//! RFC 3339 timestamps carried by protocol time fields.
+
+use std::{cmp::Ordering, fmt, str::FromStr, sync::Arc, time::SystemTime};
+
+use derive_more::Display;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::rfc3339;
+
+/// An RFC 3339 timestamp such as `2025-01-12T15:00:58Z`.
+///
+/// The original string is kept and serialized unchanged, so a timestamp with
+/// an offset or extra precision round-trips exactly. Timestamps order by the
+/// instant they denote, then by their text; see [`Self::parse_lenient`] for
+/// how unparseable timestamps are ordered.
+///
+/// Deserializing a [`Timestamp`] rejects strings that are not valid RFC 3339.
+/// Use [`LenientTimestamp`] with `serde_with` to keep them instead.
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+#[non_exhaustive]
+pub struct Timestamp {
+    unix_nanos: Option<i128>,
+    raw: Arc<str>,
+}
+
+impl Timestamp {
+    /// Parses an RFC 3339 timestamp.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TimestampError::Invalid`] when `input` is not valid RFC 3339.
+    pub fn parse(input: impl Into<Arc<str>>) -> Result<Self, TimestampError> {
+        let timestamp = Self::parse_lenient(input);
+        if timestamp.is_valid() {
+            Ok(timestamp)
+        } else {
+            Err(TimestampError::Invalid(timestamp.raw.to_string()))
+        }
+    }
+
+    /// Wraps `input` whether or not it is valid RFC 3339.
+    ///
+    /// Invalid timestamps keep their text but denote no instant, and order
+    /// before every valid timestamp.
+    #[must_use]
+    pub fn parse_lenient(input: impl Into<Arc<str>>) -> Self {
+        let raw = input.into();
+        Self {
+            unix_nanos: rfc3339::parse_unix_nanos(&raw),
+            raw,
+        }
+    }
+
+    /// The current time.
+    #[must_use]
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    /// Formats nanoseconds since the Unix epoch as a UTC timestamp.
+    #[must_use]
+    pub fn from_unix_nanos(unix_nanos: i128) -> Self {
+        Self {
+            unix_nanos: Some(unix_nanos),
+            raw: rfc3339::format_unix_nanos(unix_nanos).into(),
+        }
+    }
+
+    /// Returns whether this is valid RFC 3339.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.unix_nanos.is_some()
+    }
+
+    /// Nanoseconds since the Unix epoch, or `None` when invalid.
+    #[must_use]
+    pub fn unix_nanos(&self) -> Option<i128> {
+        self.unix_nanos
+    }
+
+    /// The instant this denotes, or `None` when invalid or out of range.
+    #[must_use]
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        rfc3339::from_unix_nanos(self.unix_nanos?)
+    }
+
+    /// The timestamp exactly as written.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Self::from_unix_nanos(rfc3339::to_unix_nanos(time))
+    }
+}
+
+impl TryFrom<String> for Timestamp {
+    type Error = TimestampError;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        Self::parse(input)
+    }
+}
+
+impl TryFrom<&str> for Timestamp {
+    type Error = TimestampError;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        Self::parse(input)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::parse(input)
+    }
+}
+
+impl From<Timestamp> for String {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.raw.to_string()
+    }
+}
+
+impl AsRef<str> for Timestamp {
+    fn as_ref(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl fmt::Debug for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Timestamp").field(&self.raw).finish()
+    }
+}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.unix_nanos, &self.raw).cmp(&(other.unix_nanos, &other.raw))
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Timestamp {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Timestamp".into()
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "An RFC 3339 timestamp such as `2025-01-12T15:00:58Z`.",
+            "type": "string",
+            "format": "date-time",
+        })
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(time: time::OffsetDateTime) -> Self {
+        Self::from_unix_nanos(time.unix_timestamp_nanos())
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<&Timestamp> for time::OffsetDateTime {
+    type Error = TimestampError;
+
+    fn try_from(timestamp: &Timestamp) -> Result<Self, Self::Error> {
+        let unix_nanos = timestamp
+            .unix_nanos
+            .ok_or_else(|| TimestampError::Invalid(timestamp.raw.to_string()))?;
+        Self::from_unix_timestamp_nanos(unix_nanos)
+            .map_err(|_| TimestampError::OutOfRange(timestamp.raw.to_string()))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Timestamp {
+    fn from(time: chrono::DateTime<Tz>) -> Self {
+        Self::from_unix_nanos(
+            i128::from(time.timestamp()) * 1_000_000_000
+                + i128::from(time.timestamp_subsec_nanos()),
+        )
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<&Timestamp> for chrono::DateTime<chrono::Utc> {
+    type Error = TimestampError;
+
+    fn try_from(timestamp: &Timestamp) -> Result<Self, Self::Error> {
+        let unix_nanos = timestamp
+            .unix_nanos
+            .ok_or_else(|| TimestampError::Invalid(timestamp.raw.to_string()))?;
+        i64::try_from(unix_nanos.div_euclid(1_000_000_000))
+            .ok()
+            .zip(u32::try_from(unix_nanos.rem_euclid(1_000_000_000)).ok())
+            .and_then(|(seconds, nanos)| Self::from_timestamp(seconds, nanos))
+            .ok_or_else(|| TimestampError::OutOfRange(timestamp.raw.to_string()))
+    }
+}
+
+/// A `serde_with` adapter that deserializes any string as a [`Timestamp`],
+/// keeping ones that are not valid RFC 3339 instead of rejecting them.
+///
+/// ```
+/// use agent_client_protocol_schema::{LenientTimestamp, Timestamp};
+/// use serde::Deserialize;
+/// use serde_with::serde_as;
+///
+/// #[serde_as]
+/// #[derive(Deserialize)]
+/// struct Entry {
+///     #[serde_as(as = "LenientTimestamp")]
+///     seen_at: Timestamp,
+/// }
+///
+/// let entry: Entry = serde_json::from_str(r#"{"seen_at": "yesterday"}"#).unwrap();
+/// assert!(!entry.seen_at.is_valid());
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct LenientTimestamp;
+
+impl<'de> serde_with::DeserializeAs<'de, Timestamp> for LenientTimestamp {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        String::deserialize(deserializer).map(Timestamp::parse_lenient)
+    }
+}
+
+impl serde_with::SerializeAs<Timestamp> for LenientTimestamp {
+    fn serialize_as<S: Serializer>(source: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&source.raw)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl serde_with::schemars_1::JsonSchemaAs<Timestamp> for LenientTimestamp {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        <Timestamp as schemars::JsonSchema>::schema_name()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        <Timestamp as schemars::JsonSchema>::json_schema(generator)
+    }
+}
+
+/// A string that cannot be used as a [`Timestamp`].
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum TimestampError {
+    /// The string is not valid RFC 3339.
+    #[display("invalid RFC 3339 timestamp: {_0}")]
+    Invalid(String),
+    /// The timestamp is valid but outside the range of the target type.
+    #[display("timestamp out of range: {_0}")]
+    OutOfRange(String),
+}
+
+impl std::error::Error for TimestampError {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_text_and_orders_by_instant() {
+        let offset: Timestamp =
+            serde_json::from_value(json!("2025-01-02T04:04:05.50+01:00")).unwrap();
+        let utc = Timestamp::parse("2025-01-02T03:04:05Z").unwrap();
+        assert_eq!(
+            serde_json::to_value(&offset).unwrap(),
+            json!("2025-01-02T04:04:05.50+01:00")
+        );
+        assert!(utc < offset);
+        assert_eq!(
+            offset.to_system_time(),
+            Some(UNIX_EPOCH + Duration::from_millis(1_735_787_045_500))
+        );
+        assert_eq!(
+            Timestamp::from(UNIX_EPOCH + Duration::from_millis(1_735_787_045_500)).as_str(),
+            "2025-01-02T03:04:05.5Z"
+        );
+
+        let mut sorted = vec![
+            offset.clone(),
+            Timestamp::parse_lenient("soon"),
+            utc.clone(),
+        ];
+        sorted.sort();
+        assert_eq!(sorted, [Timestamp::parse_lenient("soon"), utc, offset]);
+    }
+
+    #[test]
+    fn strict_deserialization_rejects_what_lenient_keeps() {
+        #[serde_with::serde_as]
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Entry {
+            #[serde_as(as = "LenientTimestamp")]
+            at: Timestamp,
+        }
+
+        assert!(serde_json::from_value::<Timestamp>(json!("2025-01-02 03:04")).is_err());
+        assert_eq!(
+            "last tuesday".parse::<Timestamp>(),
+            Err(TimestampError::Invalid("last tuesday".to_string()))
+        );
+
+        let entry: Entry = serde_json::from_value(json!({"at": "2025-01-02 03:04"})).unwrap();
+        assert!(!entry.at.is_valid());
+        assert_eq!(entry.at.to_system_time(), None);
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({"at": "2025-01-02 03:04"})
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn converts_to_and_from_time() {
+        let timestamp = Timestamp::parse("2025-01-02T04:04:05+01:00").unwrap();
+        let time = time::OffsetDateTime::try_from(&timestamp).unwrap();
+        assert_eq!(time.unix_timestamp(), 1_735_787_045);
+        assert_eq!(Timestamp::from(time).as_str(), "2025-01-02T03:04:05Z");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn converts_to_and_from_chrono() {
+        let timestamp = Timestamp::parse("2025-01-02T04:04:05+01:00").unwrap();
+        let time = chrono::DateTime::<chrono::Utc>::try_from(&timestamp).unwrap();
+        assert_eq!(time.timestamp(), 1_735_787_045);
+        assert_eq!(Timestamp::from(time).as_str(), "2025-01-02T03:04:05Z");
+        assert!(
+            chrono::DateTime::<chrono::Utc>::try_from(&Timestamp::parse_lenient("never")).is_err()
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Rough size and token estimates of prompt content.
+
+use std::{iter::Sum, ops::Add};
+
+/// Counts the tokens a model spends on prompt content.
+///
+/// Only [`Self::count_text`] is required; implement [`Self::count_binary`]
+/// as well when the model's image or audio costs are known.
+pub trait Tokenizer {
+    /// Tokens spent on `text`.
+    fn count_text(&self, text: &str) -> u64;
+
+    /// Tokens spent on a decoded image, audio or blob payload of `bytes` bytes.
+    ///
+    /// Defaults to a flat 1,600 tokens for images, about what a one-megapixel
+    /// image costs, and one token per three bytes otherwise.
+    fn count_binary(&self, mime_type: &str, bytes: u64) -> u64 {
+        if mime_type.starts_with("image/") {
+            1_600
+        } else {
+            bytes.div_ceil(3)
+        }
+    }
+}
+
+impl<T: Tokenizer + ?Sized> Tokenizer for &T {
+    fn count_text(&self, text: &str) -> u64 {
+        (**self).count_text(text)
+    }
+
+    fn count_binary(&self, mime_type: &str, bytes: u64) -> u64 {
+        (**self).count_binary(mime_type, bytes)
+    }
+}
+
+/// A model-independent [`Tokenizer`]: one token per four ASCII characters and
+/// one per other character, which over-counts English slightly and CJK text
+/// about right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct HeuristicTokenizer;
+
+impl HeuristicTokenizer {
+    /// Builds the heuristic tokenizer.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_text(&self, text: &str) -> u64 {
+        let (ascii, other) = text.chars().fold((0u64, 0u64), |(ascii, other), c| {
+            if c.is_ascii() {
+                (ascii + 1, other)
+            } else {
+                (ascii, other + 1)
+            }
+        });
+        ascii.div_ceil(4) + other
+    }
+}
+
+/// The estimated size of prompt content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct ContentSize {
+    /// Bytes of text and base64 payloads as sent on the wire.
+    pub bytes: u64,
+    /// Tokens the model is estimated to spend on the content.
+    pub tokens: u64,
+}
+
+impl ContentSize {
+    /// Builds a size from its byte and token counts.
+    #[must_use]
+    pub fn new(bytes: u64, tokens: u64) -> Self {
+        Self { bytes, tokens }
+    }
+}
+
+impl Add for ContentSize {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            bytes: self.bytes + other.bytes,
+            tokens: self.tokens + other.tokens,
+        }
+    }
+}
+
+impl Sum for ContentSize {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// The length of `text` in bytes, as a `u64`.
+pub(crate) fn len(text: &str) -> u64 {
+    u64::try_from(text.len()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heuristic_counts_ascii_and_other_text_differently() {
+        let tokenizer = HeuristicTokenizer::new();
+        assert_eq!(tokenizer.count_text(""), 0);
+        assert_eq!(tokenizer.count_text("hello world"), 3);
+        assert_eq!(tokenizer.count_text("日本語"), 3);
+        assert_eq!(tokenizer.count_binary("image/png", 2_000_000), 1_600);
+        assert_eq!(tokenizer.count_binary("audio/wav", 3_000), 1_000);
+    }
+}
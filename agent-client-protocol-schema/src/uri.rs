@@ -0,0 +1,442 @@
+//! URIs identifying resources, icons and images.
+
+use std::{
+    borrow::Cow,
+    fmt::Write as _,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use derive_more::{Display, From};
+use serde::{Deserialize, Serialize};
+
+use crate::IntoOption;
+
+/// A URI such as `file:///home/user/main.rs` or `https://example.com/logo.png`.
+///
+/// Like other protocol strings, any text is accepted when deserializing and
+/// kept unchanged; use [`Self::parse`] to check that it is a URI. Agents may
+/// use their own schemes, so only `file:` URIs can be converted to paths.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Display, From,
+)]
+#[serde(transparent)]
+#[from(Arc<str>, String, &str, Box<str>, Cow<'_, str>)]
+#[non_exhaustive]
+pub struct Uri(Arc<str>);
+
+impl Uri {
+    /// Wraps a protocol string as a typed [`Uri`] without checking it.
+    #[must_use]
+    pub fn new(uri: impl Into<Self>) -> Self {
+        uri.into()
+    }
+
+    /// Checks that `input` is an absolute URI: a scheme followed by text
+    /// without whitespace, control characters or malformed `%` escapes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UriError::Invalid`] otherwise.
+    pub fn parse(input: impl Into<Self>) -> Result<Self, UriError> {
+        let uri = input.into();
+        let valid = uri.scheme().is_some()
+            && !uri.0.chars().any(|c| c.is_whitespace() || c.is_control())
+            && percent_decode(&uri.0).is_some();
+        if valid {
+            Ok(uri)
+        } else {
+            Err(UriError::Invalid(uri.0.to_string()))
+        }
+    }
+
+    /// The `file://` URI of an absolute path, percent-encoding everything
+    /// that may not appear in a URI path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UriError::NotAbsolute`] for relative paths.
+    pub fn from_file_path(path: impl AsRef<Path>) -> Result<Self, UriError> {
+        let path = path.as_ref();
+        if !path.is_absolute() {
+            return Err(UriError::NotAbsolute(path.to_path_buf()));
+        }
+        let path = path.to_string_lossy();
+        let path = if cfg!(windows) {
+            Cow::Owned(path.replace('\\', "/"))
+        } else {
+            path
+        };
+        let mut uri = String::from("file://");
+        if !path.starts_with('/') {
+            uri.push('/');
+        }
+        for byte in path.bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&byte) {
+                uri.push(char::from(byte));
+            } else {
+                let _ = write!(uri, "%{byte:02X}");
+            }
+        }
+        Ok(Self(uri.into()))
+    }
+
+    /// The URI text.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The scheme, such as `file` or `https`, if the URI has a valid one.
+    #[must_use]
+    pub fn scheme(&self) -> Option<&str> {
+        let (scheme, _) = self.0.split_once(':')?;
+        let mut chars = scheme.chars();
+        (chars.next()?.is_ascii_alphabetic()
+            && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+        .then_some(scheme)
+    }
+
+    /// Returns whether this is a `file:` URI.
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        self.scheme()
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file"))
+    }
+
+    /// The local path of a `file:` URI, percent-decoded.
+    ///
+    /// Only URIs without a host or with host `localhost` name local files;
+    /// any query or fragment, such as a `#L10:20` line selection, is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UriError::NotFile`] for other schemes and
+    /// [`UriError::Invalid`] for malformed URIs and remote hosts.
+    pub fn to_file_path(&self) -> Result<PathBuf, UriError> {
+        let invalid = || UriError::Invalid(self.0.to_string());
+        if !self.is_file() {
+            return Err(UriError::NotFile(self.0.to_string()));
+        }
+        let rest = &self.0["file:".len()..];
+        let rest = rest.split(['?', '#']).next().unwrap_or_default();
+        let path = match rest.strip_prefix("//") {
+            Some(authority_and_path) => {
+                let (host, path) = authority_and_path
+                    .find('/')
+                    .map_or((authority_and_path, ""), |slash| {
+                        authority_and_path.split_at(slash)
+                    });
+                if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                    return Err(invalid());
+                }
+                path
+            }
+            None => rest,
+        };
+        let segments_have_slashes = path
+            .split('/')
+            .any(|segment| percent_decode(segment).is_none_or(|bytes| bytes.contains(&b'/')));
+        if segments_have_slashes {
+            return Err(invalid());
+        }
+        let bytes = percent_decode(path).ok_or_else(invalid)?;
+        if bytes.contains(&0) {
+            return Err(invalid());
+        }
+        let path = bytes_to_path(bytes).ok_or_else(invalid)?;
+        if path.is_absolute() {
+            Ok(path)
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// The local path of a `file:` URI, if it lies within one of `roots`.
+    ///
+    /// `.` and `..` segments are removed first, and the returned path is the
+    /// one without them. Symbolic links in the part of the path and of each
+    /// root that exists on disk are then resolved, so a link inside a root
+    /// pointing outside of it is rejected, even when the file it names does
+    /// not exist yet. Links created after the check are not accounted for.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Self::to_file_path`], and
+    /// [`UriError::OutsideRoots`] when the path is not under any root or
+    /// passes through a link that cannot be resolved.
+    pub fn to_file_path_within<I, P>(&self, roots: I) -> Result<PathBuf, UriError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let path = normalize(&self.to_file_path()?);
+        let Some(resolved) = resolve_links(&path) else {
+            return Err(UriError::OutsideRoots(path));
+        };
+        let within = roots.into_iter().any(|root| {
+            let root = normalize(root.as_ref());
+            resolved.starts_with(resolve_links(&root).unwrap_or(root))
+        });
+        if within {
+            Ok(path)
+        } else {
+            Err(UriError::OutsideRoots(path))
+        }
+    }
+}
+
+impl AsRef<str> for Uri {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&String> for Uri {
+    fn from(uri: &String) -> Self {
+        Self(uri.as_str().into())
+    }
+}
+
+impl From<Uri> for String {
+    fn from(uri: Uri) -> Self {
+        uri.0.to_string()
+    }
+}
+
+macro_rules! impl_uri_option_conversion {
+    ($source:ty) => {
+        impl IntoOption<Uri> for $source {
+            fn into_option(self) -> Option<Uri> {
+                Some(self.into())
+            }
+        }
+    };
+}
+
+impl_uri_option_conversion!(Arc<str>);
+impl_uri_option_conversion!(String);
+impl_uri_option_conversion!(&str);
+impl_uri_option_conversion!(&String);
+impl_uri_option_conversion!(Box<str>);
+impl_uri_option_conversion!(Cow<'_, str>);
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Uri {
+    fn schema_name() -> Cow<'static, str> {
+        "Uri".into()
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "A URI such as `file:///home/user/main.rs` or `https://example.com/logo.png`.",
+            "type": "string",
+            "format": "uri",
+        })
+    }
+}
+
+/// A URI that cannot be used as requested.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum UriError {
+    /// The string is not a well-formed URI, or names a file on another host.
+    #[display("invalid URI: {_0}")]
+    Invalid(String),
+    /// The URI does not use the `file:` scheme.
+    #[display("not a file URI: {_0}")]
+    NotFile(String),
+    /// A relative path cannot be expressed as a `file:` URI.
+    #[display("path is not absolute: {}", _0.display())]
+    NotAbsolute(PathBuf),
+    /// The path lies outside every allowed root.
+    #[display("path is outside the session roots: {}", _0.display())]
+    OutsideRoots(PathBuf),
+}
+
+impl std::error::Error for UriError {}
+
+fn percent_decode(input: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut rest = input.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(unix)]
+#[expect(
+    clippy::unnecessary_wraps,
+    reason = "non-UTF-8 paths only fail on other platforms"
+)]
+fn bytes_to_path(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    Some(OsString::from_vec(bytes).into())
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> Option<PathBuf> {
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/dir` names the drive path `C:/dir`.
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_string(),
+        _ => path,
+    };
+    Some(path.into())
+}
+
+/// Resolves `.` and `..` lexically.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// A normalized path with symbolic links resolved in its longest prefix that
+/// exists, or `None` if that prefix ends in a link that cannot be resolved.
+pub(crate) fn resolve_links(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Some(
+                missing
+                    .iter()
+                    .rev()
+                    .fold(canonical, |path, name| path.join(name)),
+            );
+        }
+        if existing.symlink_metadata().is_ok() {
+            return None;
+        }
+        let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+            return Some(path.to_path_buf());
+        };
+        missing.push(name);
+        existing = parent;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_schemes_and_rejects_malformed_uris() {
+        let uri = Uri::parse("https://example.com/logo.png").unwrap();
+        assert_eq!(uri.scheme(), Some("https"));
+        assert!(!uri.is_file());
+        assert!(Uri::parse("zed-agent:thread/42").is_ok());
+        for invalid in ["src/main.rs", "file:///a b", "file:///%zz", "1x:y"] {
+            assert_eq!(
+                Uri::parse(invalid),
+                Err(UriError::Invalid(invalid.to_string()))
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_paths_round_trip_through_percent_encoding() {
+        let path = Path::new("/repo/My Notes/#1 100%ü.md");
+        let uri = Uri::from_file_path(path).unwrap();
+        assert_eq!(
+            uri.as_str(),
+            "file:///repo/My%20Notes/%231%20100%25%C3%BC.md"
+        );
+        assert_eq!(uri.to_file_path().unwrap(), path);
+
+        let selection = Uri::new("file://localhost/repo/main.rs#L2:4");
+        assert_eq!(
+            selection.to_file_path().unwrap(),
+            Path::new("/repo/main.rs")
+        );
+        assert!(Uri::new("file://server/share/a").to_file_path().is_err());
+        assert!(Uri::new("file:///a%2Fb").to_file_path().is_err());
+        assert_eq!(
+            Uri::new("https://example.com/a").to_file_path(),
+            Err(UriError::NotFile("https://example.com/a".to_string()))
+        );
+        assert_eq!(
+            Uri::from_file_path("main.rs"),
+            Err(UriError::NotAbsolute("main.rs".into()))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_must_stay_within_roots() {
+        let roots = ["/repo", "/lib/"];
+        assert_eq!(
+            Uri::new("file:///repo/src/../main.rs")
+                .to_file_path_within(roots)
+                .unwrap(),
+            Path::new("/repo/main.rs")
+        );
+        assert!(
+            Uri::new("file:///lib/x.rs")
+                .to_file_path_within(roots)
+                .is_ok()
+        );
+        assert_eq!(
+            Uri::new("file:///repo/../etc/passwd").to_file_path_within(roots),
+            Err(UriError::OutsideRoots("/etc/passwd".into()))
+        );
+        assert!(
+            Uri::new("file:///repository/a")
+                .to_file_path_within(roots)
+                .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symbolic_links_cannot_escape_roots() {
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join(format!("acp-uri-{}", std::process::id()));
+        let (root, outside) = (dir.join("root"), dir.join("outside"));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret"), "").unwrap();
+        symlink(&outside, root.join("escape")).unwrap();
+        symlink(outside.join("missing"), root.join("dangling")).unwrap();
+        symlink(&root, dir.join("alias")).unwrap();
+
+        let within = |path: PathBuf| {
+            Uri::from_file_path(&path)
+                .unwrap()
+                .to_file_path_within([&root])
+        };
+        assert_eq!(
+            within(root.join("src/new.rs")).unwrap(),
+            root.join("src/new.rs")
+        );
+        for escaping in ["escape/secret", "escape/new", "dangling"] {
+            let path = root.join(escaping);
+            assert_eq!(within(path.clone()), Err(UriError::OutsideRoots(path)));
+        }
+
+        let path = root.join("main.rs");
+        let uri = Uri::from_file_path(&path).unwrap();
+        assert_eq!(uri.to_file_path_within([dir.join("alias")]), Ok(path));
+    }
+}
@@ -11,7 +11,7 @@ use derive_more::{Display, From};
 use serde::{Deserialize, Serialize};
 use serde_with::{DefaultOnError, VecSkipError, serde_as, skip_serializing_none};
 
-use crate::{IntoOption, ProtocolVersion, SkipListener};
+use crate::{IntoOption, ProtocolVersion, SkipListener, Timestamp};
 
 use super::{
     ClientCapabilities, ContentBlock, ExtNotification, ExtRequest, ExtResponse, Meta, SessionId,
@@ -1814,6 +1814,14 @@ impl SessionInfo {
         self
     }
 
+    /// Parses `updated_at` as an RFC 3339 [`Timestamp`].
+    ///
+    /// Returns `None` when the field is absent or is not valid RFC 3339.
+    #[must_use]
+    pub fn updated_at_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse(self.updated_at.as_deref()?).ok()
+    }
+
     /// The _meta property is reserved by ACP to allow clients and agents to attach additional
     /// metadata to their interactions. Implementations MUST NOT make assumptions about values at
     /// these keys.
@@ -13,7 +13,7 @@ use super::{
     CompleteElicitationNotification, CreateElicitationRequest, CreateElicitationResponse,
     ElicitationCapabilities,
 };
-use crate::{IntoMaybeUndefined, IntoOption, MaybeUndefined, SkipListener};
+use crate::{IntoMaybeUndefined, IntoOption, MaybeUndefined, SkipListener, Timestamp};
 
 use super::{
     ContentBlock, EnvVariable, ExtNotification, ExtRequest, ExtResponse, Meta, Plan,
@@ -585,6 +585,14 @@ impl SessionInfoUpdate {
         self
     }
 
+    /// Parses `updated_at` as an RFC 3339 [`Timestamp`].
+    ///
+    /// Returns `None` unless the field is set to a valid RFC 3339 string.
+    #[must_use]
+    pub fn updated_at_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse(self.updated_at.value()?.as_str()).ok()
+    }
+
     /// The _meta property is reserved by ACP to allow clients and agents to attach additional
     /// metadata to their interactions. Implementations MUST NOT make assumptions about values at
     /// these keys.
@@ -638,6 +646,12 @@ impl UsageUpdate {
         }
     }
 
+    /// Tokens left in the context window.
+    #[must_use]
+    pub fn remaining(&self) -> u64 {
+        self.size.saturating_sub(self.used)
+    }
+
     /// Cumulative session cost (optional).
     #[must_use]
     pub fn cost(mut self, cost: impl IntoOption<Cost>) -> Self {
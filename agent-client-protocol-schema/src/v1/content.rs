@@ -9,10 +9,12 @@
 //!
 //! See: [Content](https://agentclientprotocol.com/protocol/content)
 
+use std::path::{Path, PathBuf};
+
 use serde::{Deserialize, Serialize};
 use serde_with::{DefaultOnError, VecSkipError, serde_as, skip_serializing_none};
 
-use crate::{IntoOption, SkipListener};
+use crate::{BinaryData, BinaryDataError, IntoOption, SkipListener, Timestamp, Uri, UriError};
 
 use super::Meta;
 
@@ -170,6 +172,22 @@ impl ImageContent {
         }
     }
 
+    /// Builds [`ImageContent`] by base64-encoding `data`.
+    #[must_use]
+    pub fn from_bytes(data: impl Into<BinaryData>, mime_type: impl Into<String>) -> Self {
+        Self::new(data.into().encode(), mime_type)
+    }
+
+    /// Decodes `data`, checking it against `mime_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError`] when `data` is not base64 or is
+    /// recognizably not a `mime_type` payload.
+    pub fn decode_data(&self) -> Result<BinaryData, BinaryDataError> {
+        crate::binary::decode_checked(&self.data, Some(&self.mime_type))
+    }
+
     /// Sets or clears the optional `annotations` field.
     #[must_use]
     pub fn annotations(mut self, annotations: impl IntoOption<Annotations>) -> Self {
@@ -237,6 +255,22 @@ impl AudioContent {
         }
     }
 
+    /// Builds [`AudioContent`] by base64-encoding `data`.
+    #[must_use]
+    pub fn from_bytes(data: impl Into<BinaryData>, mime_type: impl Into<String>) -> Self {
+        Self::new(data.into().encode(), mime_type)
+    }
+
+    /// Decodes `data`, checking it against `mime_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError`] when `data` is not base64 or is
+    /// recognizably not a `mime_type` payload.
+    pub fn decode_data(&self) -> Result<BinaryData, BinaryDataError> {
+        crate::binary::decode_checked(&self.data, Some(&self.mime_type))
+    }
+
     /// Sets or clears the optional `annotations` field.
     #[must_use]
     pub fn annotations(mut self, annotations: impl IntoOption<Annotations>) -> Self {
@@ -425,6 +459,22 @@ impl BlobResourceContents {
         }
     }
 
+    /// Builds [`BlobResourceContents`] by base64-encoding `blob`.
+    #[must_use]
+    pub fn from_bytes(blob: impl Into<BinaryData>, uri: impl Into<String>) -> Self {
+        Self::new(blob.into().encode(), uri)
+    }
+
+    /// Decodes `blob`, checking it against `mime_type` when set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError`] when `blob` is not base64 or is
+    /// recognizably not a `mime_type` payload.
+    pub fn decode_blob(&self) -> Result<BinaryData, BinaryDataError> {
+        crate::binary::decode_checked(&self.blob, self.mime_type.as_deref())
+    }
+
     /// Sets or clears the optional `mimeType` field.
     #[must_use]
     pub fn mime_type(mut self, mime_type: impl IntoOption<String>) -> Self {
@@ -509,6 +559,21 @@ impl ResourceLink {
         }
     }
 
+    /// The local path this link points to, if it is a `file:` URI within
+    /// one of `roots`, usually the session's `cwd` and additional directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UriError`] when the link is not a local file URI or points
+    /// outside every root.
+    pub fn local_path<I, P>(&self, roots: I) -> Result<PathBuf, UriError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        Uri::new(&self.uri).to_file_path_within(roots)
+    }
+
     /// Sets or clears the optional `annotations` field.
     #[must_use]
     pub fn annotations(mut self, annotations: impl IntoOption<Annotations>) -> Self {
@@ -612,6 +677,14 @@ impl Annotations {
         self
     }
 
+    /// Parses `last_modified` as an RFC 3339 [`Timestamp`].
+    ///
+    /// Returns `None` when the field is absent or is not valid RFC 3339.
+    #[must_use]
+    pub fn last_modified_timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse(self.last_modified.as_deref()?).ok()
+    }
+
     /// Sets or clears the optional `priority` field.
     #[must_use]
     pub fn priority(mut self, priority: impl IntoOption<f64>) -> Self {
@@ -647,6 +720,31 @@ pub enum Role {
 mod tests {
     use super::*;
 
+    #[test]
+    fn binary_payloads_encode_and_check_media_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let image = ImageContent::from_bytes(png.clone(), "image/png");
+        assert_eq!(image.data, "iVBORw0KGgoAAAANSUhEUg==");
+        assert_eq!(image.decode_data().unwrap().as_bytes(), png);
+
+        let mislabeled = ImageContent::new(image.data.clone(), "image/jpeg");
+        assert!(matches!(
+            mislabeled.decode_data(),
+            Err(BinaryDataError::MediaTypeMismatch {
+                detected: "image/png",
+                ..
+            })
+        ));
+        assert!(matches!(
+            AudioContent::new("%%%", "audio/wav").decode_data(),
+            Err(BinaryDataError::InvalidBase64(_))
+        ));
+
+        let blob = BlobResourceContents::from_bytes(png.clone(), "file:///logo.png");
+        assert_eq!(blob.decode_blob().unwrap().as_bytes(), png);
+        assert!(blob.mime_type("audio/mpeg").decode_blob().is_err());
+    }
+
     #[test]
     fn test_text_content_roundtrip() {
         let content = TextContent::new("hello world");
@@ -727,4 +825,22 @@ mod tests {
         assert!(!json.as_object().unwrap().contains_key("annotations"));
         assert!(!json.as_object().unwrap().contains_key("meta"));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn resource_links_resolve_only_within_roots() {
+        let link = ResourceLink::new("main.rs", "file:///repo/src/main.rs#L1:3");
+        assert_eq!(
+            link.local_path(["/repo"]).unwrap(),
+            PathBuf::from("/repo/src/main.rs")
+        );
+        assert!(matches!(
+            link.local_path(["/other"]),
+            Err(UriError::OutsideRoots(_))
+        ));
+        assert!(matches!(
+            ResourceLink::new("docs", "https://example.com/docs").local_path(["/repo"]),
+            Err(UriError::NotFile(_))
+        ));
+    }
 }
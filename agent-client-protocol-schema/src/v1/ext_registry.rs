@@ -0,0 +1,376 @@
+//! Typed extension methods and their dispatch.
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{
+    Error, ExtMethodError, ExtNotification, ExtRequest, ExtResponse, RawValue,
+    ext::check_method_name,
+};
+
+/// An extension request method with typed parameters and response.
+///
+/// ```
+/// # use agent_client_protocol_schema::v1::ExtMethod;
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Serialize, Deserialize)]
+/// struct IndexParams { path: String }
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct IndexResponse { files: u32 }
+///
+/// struct Index;
+///
+/// impl ExtMethod for Index {
+///     const METHOD: &'static str = "_zed/index";
+///     type Params = IndexParams;
+///     type Response = IndexResponse;
+/// }
+/// ```
+pub trait ExtMethod {
+    /// The wire method name, such as `_zed/index`. Must start with `_`.
+    const METHOD: &'static str;
+    /// The request parameters.
+    type Params: Serialize + DeserializeOwned;
+    /// The successful response.
+    type Response: Serialize + DeserializeOwned;
+}
+
+/// An extension notification method with typed parameters.
+pub trait ExtNotificationMethod {
+    /// The wire method name, such as `_zed/indexed`. Must start with `_`.
+    const METHOD: &'static str;
+    /// The notification parameters.
+    type Params: Serialize + DeserializeOwned;
+}
+
+impl ExtRequest {
+    /// Builds an `M` request from its typed parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::InvalidName`] if `M::METHOD` does not start
+    /// with `_`, and [`ExtMethodError::Encode`] if `params` cannot be serialized.
+    pub fn encode<M: ExtMethod>(params: &M::Params) -> Result<Self, ExtMethodError> {
+        Self::try_new(M::METHOD, to_raw(M::METHOD, params)?)
+    }
+
+    /// Decodes the parameters of an `M` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `method_not_found` error for requests of another method and
+    /// an `invalid_params` error if the parameters do not match `M::Params`.
+    pub fn decode<M: ExtMethod>(&self) -> Result<M::Params, Error> {
+        if &*self.method != M::METHOD {
+            return Err(Error::method_not_found().data(self.method.to_string()));
+        }
+        Ok(serde_json::from_str(self.params.get())?)
+    }
+}
+
+impl ExtResponse {
+    /// Builds the response to an `M` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::Encode`] if `response` cannot be serialized.
+    pub fn encode<M: ExtMethod>(response: &M::Response) -> Result<Self, ExtMethodError> {
+        Ok(Self::new(to_raw(M::METHOD, response)?))
+    }
+
+    /// Decodes the response to an `M` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from deserializing the response as `M::Response`.
+    pub fn decode<M: ExtMethod>(&self) -> serde_json::Result<M::Response> {
+        serde_json::from_str(self.0.get())
+    }
+}
+
+impl ExtNotification {
+    /// Builds an `N` notification from its typed parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::InvalidName`] if `N::METHOD` does not start
+    /// with `_`, and [`ExtMethodError::Encode`] if `params` cannot be serialized.
+    pub fn encode<N: ExtNotificationMethod>(params: &N::Params) -> Result<Self, ExtMethodError> {
+        Self::try_new(N::METHOD, to_raw(N::METHOD, params)?)
+    }
+
+    /// Decodes the parameters of an `N` notification.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `method_not_found` error for notifications of another method
+    /// and an `invalid_params` error if the parameters do not match `N::Params`.
+    pub fn decode<N: ExtNotificationMethod>(&self) -> Result<N::Params, Error> {
+        if &*self.method != N::METHOD {
+            return Err(Error::method_not_found().data(self.method.to_string()));
+        }
+        Ok(serde_json::from_str(self.params.get())?)
+    }
+}
+
+type RequestHandler = Box<dyn Fn(&ExtRequest) -> Result<ExtResponse, Error> + Send + Sync>;
+type NotificationHandler = Box<dyn Fn(&ExtNotification) -> Result<(), Error> + Send + Sync>;
+
+/// Routes [`ExtRequest`]s and [`ExtNotification`]s to typed handlers.
+///
+/// Requests for unregistered methods are answered with `method_not_found`,
+/// and parameters that do not match the registered type with
+/// `invalid_params`. Notifications for unregistered methods are ignored, as
+/// the protocol requires.
+#[derive(Default)]
+#[non_exhaustive]
+pub struct ExtMethodRegistry {
+    requests: HashMap<Arc<str>, RequestHandler>,
+    notifications: HashMap<Arc<str>, NotificationHandler>,
+}
+
+impl ExtMethodRegistry {
+    /// Builds an empty [`ExtMethodRegistry`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for `M` requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::InvalidName`] if `M::METHOD` does not start
+    /// with `_`, and [`ExtMethodError::AlreadyRegistered`] if it already has
+    /// a handler.
+    pub fn register<M: ExtMethod>(
+        &mut self,
+        handler: impl Fn(M::Params) -> Result<M::Response, Error> + Send + Sync + 'static,
+    ) -> Result<(), ExtMethodError> {
+        check_method_name(M::METHOD)?;
+        if self.requests.contains_key(M::METHOD) {
+            return Err(ExtMethodError::AlreadyRegistered(M::METHOD.to_string()));
+        }
+        self.requests.insert(
+            M::METHOD.into(),
+            Box::new(move |request| {
+                let response = handler(request.decode::<M>()?)?;
+                ExtResponse::encode::<M>(&response).map_err(Error::into_internal_error)
+            }),
+        );
+        Ok(())
+    }
+
+    /// Registers `handler` for `N` notifications.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::InvalidName`] if `N::METHOD` does not start
+    /// with `_`, and [`ExtMethodError::AlreadyRegistered`] if it already has
+    /// a handler.
+    pub fn register_notification<N: ExtNotificationMethod>(
+        &mut self,
+        handler: impl Fn(N::Params) + Send + Sync + 'static,
+    ) -> Result<(), ExtMethodError> {
+        check_method_name(N::METHOD)?;
+        if self.notifications.contains_key(N::METHOD) {
+            return Err(ExtMethodError::AlreadyRegistered(N::METHOD.to_string()));
+        }
+        self.notifications.insert(
+            N::METHOD.into(),
+            Box::new(move |notification| {
+                handler(notification.decode::<N>()?);
+                Ok(())
+            }),
+        );
+        Ok(())
+    }
+
+    /// Returns whether a request or notification handler is registered for `method`.
+    #[must_use]
+    pub fn contains(&self, method: &str) -> bool {
+        self.requests.contains_key(method) || self.notifications.contains_key(method)
+    }
+
+    /// Answers `request` with its registered handler.
+    ///
+    /// # Errors
+    ///
+    /// Returns `method_not_found` for unregistered methods, `invalid_params`
+    /// for parameters of the wrong shape, and the handler's own errors.
+    pub fn handle(&self, request: &ExtRequest) -> Result<ExtResponse, Error> {
+        let handler = self
+            .requests
+            .get(&request.method)
+            .ok_or_else(|| Error::method_not_found().data(request.method.to_string()))?;
+        handler(request)
+    }
+
+    /// Passes `notification` to its registered handler.
+    ///
+    /// Returns `false` for unregistered methods, which should be ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `invalid_params` for parameters of the wrong shape.
+    pub fn notify(&self, notification: &ExtNotification) -> Result<bool, Error> {
+        match self.notifications.get(&notification.method) {
+            Some(handler) => handler(notification).map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
+
+impl fmt::Debug for ExtMethodRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut requests: Vec<_> = self.requests.keys().collect();
+        let mut notifications: Vec<_> = self.notifications.keys().collect();
+        requests.sort();
+        notifications.sort();
+        f.debug_struct("ExtMethodRegistry")
+            .field("requests", &requests)
+            .field("notifications", &notifications)
+            .finish()
+    }
+}
+
+fn to_raw(method: &str, value: &impl Serialize) -> Result<Arc<RawValue>, ExtMethodError> {
+    serde_json::value::to_raw_value(value)
+        .map(Arc::from)
+        .map_err(|error| ExtMethodError::Encode {
+            method: method.to_string(),
+            error: error.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::v1::ErrorCode;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct IndexParams {
+        path: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct IndexResponse {
+        files: u32,
+    }
+
+    struct Index;
+
+    impl ExtMethod for Index {
+        const METHOD: &'static str = "_zed/index";
+        type Params = IndexParams;
+        type Response = IndexResponse;
+    }
+
+    struct Indexed;
+
+    impl ExtNotificationMethod for Indexed {
+        const METHOD: &'static str = "_zed/indexed";
+        type Params = IndexResponse;
+    }
+
+    struct Unprefixed;
+
+    impl ExtMethod for Unprefixed {
+        const METHOD: &'static str = "zed/index";
+        type Params = IndexParams;
+        type Response = IndexResponse;
+    }
+
+    fn raw_request(method: &str, params: &serde_json::Value) -> ExtRequest {
+        ExtRequest::new(
+            method,
+            serde_json::value::to_raw_value(params).unwrap().into(),
+        )
+    }
+
+    #[test]
+    fn extension_names_must_start_with_an_underscore() {
+        let params: Arc<RawValue> = serde_json::value::to_raw_value(&json!({})).unwrap().into();
+        assert!(ExtRequest::try_new("_zed/index", params.clone()).is_ok());
+        assert_eq!(
+            ExtNotification::try_new("session/update", params).unwrap_err(),
+            ExtMethodError::InvalidName("session/update".to_string())
+        );
+
+        let params = IndexParams {
+            path: "/repo".into(),
+        };
+        assert!(matches!(
+            ExtRequest::encode::<Unprefixed>(&params),
+            Err(ExtMethodError::InvalidName(_))
+        ));
+        let mut registry = ExtMethodRegistry::new();
+        assert!(
+            registry
+                .register::<Unprefixed>(|_| Ok(IndexResponse { files: 0 }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn registry_dispatches_typed_requests() {
+        let mut registry = ExtMethodRegistry::new();
+        registry
+            .register::<Index>(|params| {
+                Ok(IndexResponse {
+                    files: u32::try_from(params.path.len()).unwrap(),
+                })
+            })
+            .unwrap();
+        assert_eq!(
+            registry.register::<Index>(|_| Err(Error::internal_error())),
+            Err(ExtMethodError::AlreadyRegistered("_zed/index".to_string()))
+        );
+
+        let request = ExtRequest::encode::<Index>(&IndexParams {
+            path: "/repo".into(),
+        })
+        .unwrap();
+        let response = registry.handle(&request).unwrap();
+        assert_eq!(
+            response.decode::<Index>().unwrap(),
+            IndexResponse { files: 5 }
+        );
+
+        let error = registry
+            .handle(&raw_request("_zed/unknown", &json!({})))
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::MethodNotFound);
+        let error = registry
+            .handle(&raw_request("_zed/index", &json!({"path": 1})))
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+    }
+
+    #[test]
+    fn registry_ignores_unknown_notifications() {
+        let seen = Arc::new(AtomicU32::new(0));
+        let mut registry = ExtMethodRegistry::new();
+        let counter = seen.clone();
+        registry
+            .register_notification::<Indexed>(move |params| {
+                counter.fetch_add(params.files, Ordering::Relaxed);
+            })
+            .unwrap();
+        assert!(registry.contains("_zed/indexed"));
+
+        let notification = ExtNotification::encode::<Indexed>(&IndexResponse { files: 3 }).unwrap();
+        assert_eq!(registry.notify(&notification), Ok(true));
+        assert_eq!(seen.load(Ordering::Relaxed), 3);
+
+        let unknown = ExtNotification::new("_other/event", notification.params.clone());
+        assert_eq!(registry.notify(&unknown), Ok(false));
+    }
+}
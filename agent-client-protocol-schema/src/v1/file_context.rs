@@ -0,0 +1,172 @@
+//! Embedding local files and editor selections as prompt context.
+
+use std::{io, ops::RangeInclusive, path::PathBuf};
+
+use super::{
+    Annotations, BlobResourceContents, EmbeddedResource, EmbeddedResourceResource, ResourceLink,
+    Role, TextResourceContents,
+};
+use crate::file_context::{DEFAULT_MAX_BYTES, FileContents, FileInfo, read, stat};
+
+/// Builds [`EmbeddedResource`]s and [`ResourceLink`]s from files on disk.
+///
+/// URIs are percent-encoded `file://` URIs of the absolute path, with an
+/// `#L<start>:<end>` fragment for selections. The media type is guessed from
+/// the extension, falling back to `text/plain` for text and the detected
+/// format for binary files. Files whose embedded part is valid UTF-8 without
+/// NUL bytes are embedded as text, everything else as a base64 blob. Only
+/// as much of the file as [`Self::max_bytes`] allows is read.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FileContext {
+    path: PathBuf,
+    lines: Option<RangeInclusive<usize>>,
+    max_bytes: usize,
+    priority: Option<f64>,
+    audience: Option<Vec<Role>>,
+}
+
+impl FileContext {
+    /// Context for the whole file at `path`, relative to the working directory if not absolute.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lines: None,
+            max_bytes: DEFAULT_MAX_BYTES,
+            priority: None,
+            audience: None,
+        }
+    }
+
+    /// Context for a selection of 1-based, inclusive line numbers of a text file.
+    ///
+    /// Selections starting at line 0 or ending before they start are
+    /// refused when the context is embedded or linked.
+    #[must_use]
+    pub fn selection(path: impl Into<PathBuf>, lines: RangeInclusive<usize>) -> Self {
+        Self {
+            lines: Some(lines),
+            ..Self::new(path)
+        }
+    }
+
+    /// Largest payload to embed, 256 KiB by default.
+    ///
+    /// Longer text is cut after the last whole line that fits; larger binary
+    /// files are refused.
+    #[must_use]
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Importance of the context, from 0 (least) to 1 (most).
+    #[must_use]
+    pub fn priority(mut self, priority: f64) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Who the context is intended for.
+    #[must_use]
+    pub fn audience(mut self, audience: Vec<Role>) -> Self {
+        self.audience = Some(audience);
+        self
+    }
+
+    /// Reads the file into an embedded resource.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the file, [`io::ErrorKind::FileTooLarge`]
+    /// for binary files over [`Self::max_bytes`],
+    /// [`io::ErrorKind::InvalidData`] for selections of binary files, and
+    /// [`io::ErrorKind::InvalidInput`] for invalid selections.
+    pub fn embed(&self) -> io::Result<EmbeddedResource> {
+        let (info, contents) = read(&self.path, self.lines.as_ref(), self.max_bytes)?;
+        let annotations = self.annotations(&info);
+        let resource = match contents {
+            FileContents::Text(text) => EmbeddedResourceResource::TextResourceContents(
+                TextResourceContents::new(text, info.uri).mime_type(info.mime_type),
+            ),
+            FileContents::Blob(blob) => EmbeddedResourceResource::BlobResourceContents(
+                BlobResourceContents::from_bytes(blob, info.uri).mime_type(info.mime_type),
+            ),
+        };
+        Ok(EmbeddedResource::new(resource).annotations(annotations))
+    }
+
+    /// Links to the file without reading its contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the file's metadata, and
+    /// [`io::ErrorKind::InvalidInput`] for invalid selections.
+    pub fn link(&self) -> io::Result<ResourceLink> {
+        let (info, size) = stat(&self.path, self.lines.as_ref())?;
+        let annotations = self.annotations(&info);
+        Ok(ResourceLink::new(info.name, info.uri)
+            .mime_type(info.mime_type)
+            .size(i64::try_from(size).ok())
+            .annotations(annotations))
+    }
+
+    fn annotations(&self, info: &FileInfo) -> Annotations {
+        Annotations::new()
+            .last_modified(info.last_modified.as_ref().map(ToString::to_string))
+            .priority(self.priority)
+            .audience(self.audience.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn scratch(name: &str, contents: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("acp-v1-file-context-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn selections_embed_text_with_a_line_fragment() {
+        let path = scratch("main.rs", b"fn main() {\n    run();\n}\n");
+        let resource = FileContext::selection(&path, 2..=2)
+            .priority(0.8)
+            .embed()
+            .unwrap();
+        let EmbeddedResourceResource::TextResourceContents(text) = resource.resource else {
+            panic!("expected text contents");
+        };
+        assert_eq!(text.text, "    run();\n");
+        assert!(text.uri.starts_with("file:///") && text.uri.ends_with("/main.rs#L2:2"));
+        assert_eq!(text.mime_type.as_deref(), Some("text/x-rust"));
+
+        let annotations = resource.annotations.unwrap();
+        assert_eq!(annotations.priority, Some(0.8));
+        assert!(annotations.last_modified_timestamp().unwrap().is_valid());
+    }
+
+    #[test]
+    fn binary_files_become_blobs_and_respect_the_limit() {
+        let path = scratch("logo", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+        let resource = FileContext::new(&path).embed().unwrap();
+        let EmbeddedResourceResource::BlobResourceContents(blob) = resource.resource else {
+            panic!("expected blob contents");
+        };
+        assert_eq!(blob.mime_type.as_deref(), Some("image/png"));
+        assert_eq!(blob.decode_blob().unwrap().len(), 16);
+
+        let error = FileContext::new(&path).max_bytes(8).embed().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::FileTooLarge);
+
+        let link = FileContext::new(&path).link().unwrap();
+        assert_eq!((link.name.as_str(), link.size), ("logo", Some(16)));
+    }
+}
@@ -0,0 +1,479 @@
+//! Rendering session content to Markdown.
+//!
+//! [`MarkdownRenderer`] turns individual plans, tool calls and content blocks
+//! into Markdown, and [`MarkdownTranscript`] accumulates the
+//! [`SessionUpdate`]s of a turn into a summary suitable for posting to chat
+//! or code review tools.
+
+use std::collections::HashMap;
+
+use super::{
+    ContentBlock, Diff, EmbeddedResourceResource, PlanChange, PlanContent, PlanEntry,
+    PlanEntryStatus, PlanKey, PlanStore, SessionUpdate, Terminal, TerminalId, ToolCall,
+    ToolCallContent, ToolCallId, ToolCallStatus,
+};
+use crate::markdown::{
+    code, collapsed, escape_html, fenced, inline, language_hint, link, quoted, unified_diff,
+};
+
+/// How agent thoughts appear in a rendered transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ThoughtStyle {
+    /// Inside a collapsed `<details>` element.
+    #[default]
+    Collapsed,
+    /// As a block quote.
+    Quoted,
+    /// Left out entirely.
+    Hidden,
+}
+
+/// Renders protocol types to Markdown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MarkdownRenderer {
+    /// How agent thoughts are rendered.
+    pub thoughts: ThoughtStyle,
+    terminal_output: HashMap<TerminalId, String>,
+}
+
+impl MarkdownRenderer {
+    /// Builds a [`MarkdownRenderer`] with collapsed thoughts.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how agent thoughts are rendered.
+    #[must_use]
+    pub fn thoughts(mut self, thoughts: ThoughtStyle) -> Self {
+        self.thoughts = thoughts;
+        self
+    }
+
+    /// Records the output of a terminal, such as from a `terminal/output`
+    /// response, so [`Terminal`] content can be rendered as a code block.
+    pub fn set_terminal_output(
+        &mut self,
+        terminal_id: impl Into<TerminalId>,
+        output: impl Into<String>,
+    ) {
+        self.terminal_output
+            .insert(terminal_id.into(), output.into());
+    }
+
+    /// Renders plan entries as a checkbox list.
+    #[must_use]
+    pub fn plan_entries(&self, entries: &[PlanEntry]) -> String {
+        entries
+            .iter()
+            .map(|entry| {
+                let content = inline(&entry.content);
+                match entry.status {
+                    PlanEntryStatus::Completed => format!("- [x] {content}"),
+                    PlanEntryStatus::InProgress => format!("- [ ] {content} _(in progress)_"),
+                    PlanEntryStatus::Pending => format!("- [ ] {content}"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Renders a tool call as its status icon and title followed by its content.
+    #[must_use]
+    pub fn tool_call(&self, tool_call: &ToolCall) -> String {
+        let mut sections = vec![format!(
+            "{} **{}**",
+            status_icon(tool_call.status),
+            inline(&tool_call.title)
+        )];
+        sections.extend(
+            tool_call
+                .content
+                .iter()
+                .map(|content| self.tool_call_content(content)),
+        );
+        sections.join("\n\n")
+    }
+
+    /// Renders one item of tool call content.
+    #[must_use]
+    pub fn tool_call_content(&self, content: &ToolCallContent) -> String {
+        match content {
+            ToolCallContent::Content(content) => self.content_block(&content.content),
+            ToolCallContent::Diff(diff) => self.diff(diff),
+            ToolCallContent::Terminal(terminal) => self.terminal(terminal),
+        }
+    }
+
+    /// Renders a file modification as a fenced `diff` block.
+    #[must_use]
+    pub fn diff(&self, diff: &Diff) -> String {
+        fenced(
+            "diff",
+            &unified_diff(
+                &diff.path.display().to_string(),
+                diff.old_text.as_deref(),
+                &diff.new_text,
+            ),
+        )
+    }
+
+    /// Renders terminal output as a code block, or a placeholder if no output
+    /// was recorded with [`MarkdownRenderer::set_terminal_output`].
+    #[must_use]
+    pub fn terminal(&self, terminal: &Terminal) -> String {
+        match self.terminal_output.get(&terminal.terminal_id) {
+            Some(output) => fenced("console", output),
+            None => format!("_[terminal {}]_", code(&terminal.terminal_id.to_string())),
+        }
+    }
+
+    /// Renders a content block. Binary content is replaced by a placeholder.
+    #[must_use]
+    pub fn content_block(&self, block: &ContentBlock) -> String {
+        match block {
+            ContentBlock::Text(text) => text.text.clone(),
+            ContentBlock::Image(image) => {
+                let label = format!("image: {}", image.mime_type);
+                match &image.uri {
+                    Some(uri) => format!("_{}_", link(&label, uri)),
+                    None => format!("_[{}]_", inline(&label)),
+                }
+            }
+            ContentBlock::Audio(audio) => format!("_[audio: {}]_", inline(&audio.mime_type)),
+            ContentBlock::ResourceLink(resource) => link(
+                resource.title.as_deref().unwrap_or(&resource.name),
+                &resource.uri,
+            ),
+            ContentBlock::Resource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(contents) => format!(
+                    "{}\n\n{}",
+                    code(&contents.uri),
+                    fenced(language_hint(&contents.uri), &contents.text)
+                ),
+                EmbeddedResourceResource::BlobResourceContents(contents) => {
+                    format!("_[binary resource: {}]_", inline(&contents.uri))
+                }
+            },
+        }
+    }
+
+    /// Renders content blocks separated by blank lines.
+    #[must_use]
+    pub fn content_blocks(&self, blocks: &[ContentBlock]) -> String {
+        blocks
+            .iter()
+            .map(|block| self.content_block(block))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Renders an agent thought according to [`MarkdownRenderer::thoughts`].
+    ///
+    /// HTML in the thought is escaped rather than rendered. Returns `None` if
+    /// thoughts are hidden.
+    #[must_use]
+    pub fn thought(&self, blocks: &[ContentBlock]) -> Option<String> {
+        let body = self.content_blocks(blocks);
+        match self.thoughts {
+            ThoughtStyle::Collapsed => Some(collapsed("Thinking", &body)),
+            ThoughtStyle::Quoted => Some(quoted(&escape_html(&body))),
+            ThoughtStyle::Hidden => None,
+        }
+    }
+
+    fn plan_content(&self, content: &PlanContent) -> String {
+        match content {
+            PlanContent::Entries(entries) => self.plan_entries(entries),
+            #[cfg(feature = "unstable_plan_operations")]
+            PlanContent::File(uri) => link("Plan", uri),
+            #[cfg(feature = "unstable_plan_operations")]
+            PlanContent::Markdown(markdown) => markdown.clone(),
+        }
+    }
+}
+
+fn status_icon(status: ToolCallStatus) -> &'static str {
+    match status {
+        ToolCallStatus::Pending => "⏳",
+        ToolCallStatus::InProgress => "🔄",
+        ToolCallStatus::Completed => "✅",
+        ToolCallStatus::Failed => "❌",
+    }
+}
+
+/// Accumulates session updates into a Markdown transcript.
+///
+/// Consecutive message chunks are merged, tool calls are shown in their
+/// latest state at the position they were first reported, and plans are
+/// shown with their latest entries.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MarkdownTranscript {
+    renderer: MarkdownRenderer,
+    items: Vec<TranscriptItem>,
+    plans: PlanStore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TranscriptItem {
+    Message(Speaker, Vec<ContentBlock>),
+    ToolCall(Box<ToolCall>),
+    Plan(PlanKey),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Speaker {
+    User,
+    Agent,
+    Thought,
+}
+
+impl MarkdownTranscript {
+    /// Builds an empty transcript rendered with `renderer`.
+    #[must_use]
+    pub fn new(renderer: MarkdownRenderer) -> Self {
+        Self {
+            renderer,
+            ..Self::default()
+        }
+    }
+
+    /// The renderer, for recording terminal output.
+    pub fn renderer_mut(&mut self) -> &mut MarkdownRenderer {
+        &mut self.renderer
+    }
+
+    /// Adds a session update to the transcript. Updates without renderable
+    /// content are ignored.
+    pub fn push(&mut self, update: &SessionUpdate) {
+        match update {
+            SessionUpdate::UserMessageChunk(chunk) => {
+                self.push_chunk(Speaker::User, &chunk.content);
+            }
+            SessionUpdate::AgentMessageChunk(chunk) => {
+                self.push_chunk(Speaker::Agent, &chunk.content);
+            }
+            SessionUpdate::AgentThoughtChunk(chunk) => {
+                self.push_chunk(Speaker::Thought, &chunk.content);
+            }
+            SessionUpdate::ToolCall(tool_call) => {
+                match self.find_tool_call(&tool_call.tool_call_id) {
+                    Some(existing) => *existing = tool_call.clone(),
+                    None => self
+                        .items
+                        .push(TranscriptItem::ToolCall(Box::new(tool_call.clone()))),
+                }
+            }
+            SessionUpdate::ToolCallUpdate(update) => {
+                if let Some(existing) = self.find_tool_call(&update.tool_call_id) {
+                    existing.update(update.fields.clone());
+                } else if let Ok(tool_call) = ToolCall::try_from(update.clone()) {
+                    self.items
+                        .push(TranscriptItem::ToolCall(Box::new(tool_call)));
+                }
+            }
+            _ => match self.plans.apply(update) {
+                Some(PlanChange::Updated { key, .. })
+                    if !self.items.contains(&TranscriptItem::Plan(key.clone())) =>
+                {
+                    self.items.push(TranscriptItem::Plan(key));
+                }
+                Some(PlanChange::Removed { key, .. }) => {
+                    self.items
+                        .retain(|item| item != &TranscriptItem::Plan(key.clone()));
+                }
+                _ => {}
+            },
+        }
+    }
+
+    /// Renders the transcript.
+    #[must_use]
+    pub fn render(&self) -> String {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                TranscriptItem::Message(Speaker::User, blocks) => Some(format!(
+                    "**User**\n\n{}",
+                    self.renderer.content_blocks(blocks)
+                )),
+                TranscriptItem::Message(Speaker::Agent, blocks) => Some(format!(
+                    "**Agent**\n\n{}",
+                    self.renderer.content_blocks(blocks)
+                )),
+                TranscriptItem::Message(Speaker::Thought, blocks) => self.renderer.thought(blocks),
+                TranscriptItem::ToolCall(tool_call) => Some(self.renderer.tool_call(tool_call)),
+                TranscriptItem::Plan(key) => self
+                    .plans
+                    .get(key)
+                    .map(|content| format!("**Plan**\n\n{}", self.renderer.plan_content(content))),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn push_chunk(&mut self, speaker: Speaker, block: &ContentBlock) {
+        if let Some(TranscriptItem::Message(last_speaker, blocks)) = self.items.last_mut()
+            && *last_speaker == speaker
+        {
+            if let (Some(ContentBlock::Text(last)), ContentBlock::Text(text)) =
+                (blocks.last_mut(), block)
+            {
+                last.text.push_str(&text.text);
+            } else {
+                blocks.push(block.clone());
+            }
+            return;
+        }
+        self.items
+            .push(TranscriptItem::Message(speaker, vec![block.clone()]));
+    }
+
+    fn find_tool_call(&mut self, tool_call_id: &ToolCallId) -> Option<&mut ToolCall> {
+        self.items.iter_mut().find_map(|item| match item {
+            TranscriptItem::ToolCall(tool_call) if &tool_call.tool_call_id == tool_call_id => {
+                Some(&mut **tool_call)
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::{
+        ContentChunk, EmbeddedResource, ImageContent, Plan, PlanEntryPriority, ResourceLink,
+        TextResourceContents, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
+    };
+
+    fn text_chunk(text: &str) -> ContentChunk {
+        ContentChunk::new(text.into())
+    }
+
+    #[test]
+    fn agent_text_is_escaped() {
+        let renderer = MarkdownRenderer::new();
+        let link = ContentBlock::ResourceLink(
+            ResourceLink::new("a", "file:///a b (1).md").title("see ](x) <b>"),
+        );
+        assert_eq!(
+            renderer.content_block(&link),
+            "[see \\](x) \\<b\\>](<file:///a b (1).md>)"
+        );
+
+        let thought = renderer
+            .thought(&["</details>\n<img src=x>".into()])
+            .unwrap();
+        assert_eq!(
+            thought,
+            "<details>\n<summary>Thinking</summary>\n\n\
+             &lt;/details&gt;\n&lt;img src=x&gt;\n\n</details>"
+        );
+
+        let entries = [PlanEntry::new(
+            "Step\n- [x] forged",
+            PlanEntryPriority::High,
+            PlanEntryStatus::Pending,
+        )];
+        assert_eq!(
+            renderer.plan_entries(&entries),
+            "- [ ] Step - \\[x\\] forged"
+        );
+    }
+
+    #[test]
+    fn renders_content_blocks() {
+        let renderer = MarkdownRenderer::new();
+        assert_eq!(
+            renderer.content_block(&ContentBlock::Image(ImageContent::new("AAAA", "image/png"))),
+            "_[image: image/png]_"
+        );
+        let resource = ContentBlock::Resource(EmbeddedResource::new(
+            EmbeddedResourceResource::TextResourceContents(TextResourceContents::new(
+                "fn main() {}",
+                "file:///repo/main.rs",
+            )),
+        ));
+        assert_eq!(
+            renderer.content_block(&resource),
+            "`file:///repo/main.rs`\n\n```rs\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn renders_tool_call_with_diff_and_terminal() {
+        let mut renderer = MarkdownRenderer::new();
+        renderer.set_terminal_output("term-1", "ok\n");
+        let tool_call = ToolCall::new("call-1", "Edit config")
+            .kind(ToolKind::Edit)
+            .status(ToolCallStatus::Completed)
+            .content(vec![
+                ToolCallContent::Diff(Diff::new("/repo/a.toml", "x = 2").old_text("x = 1")),
+                ToolCallContent::Terminal(Terminal::new("term-1")),
+                ToolCallContent::Terminal(Terminal::new("term-2")),
+            ]);
+        assert_eq!(
+            renderer.tool_call(&tool_call),
+            "✅ **Edit config**\n\n\
+             ```diff\n--- /repo/a.toml\n+++ /repo/a.toml\n-x = 1\n+x = 2\n```\n\n\
+             ```console\nok\n```\n\n\
+             _[terminal `term-2`]_"
+        );
+    }
+
+    #[test]
+    fn transcript_merges_chunks_and_tracks_updates() {
+        let mut transcript =
+            MarkdownTranscript::new(MarkdownRenderer::new().thoughts(ThoughtStyle::Quoted));
+        transcript.push(&SessionUpdate::UserMessageChunk(text_chunk("Fix the bug")));
+        transcript.push(&SessionUpdate::AgentThoughtChunk(text_chunk("Looking ")));
+        transcript.push(&SessionUpdate::AgentThoughtChunk(text_chunk("around")));
+        transcript.push(&SessionUpdate::Plan(Plan::new(vec![
+            PlanEntry::new(
+                "Find bug",
+                PlanEntryPriority::High,
+                PlanEntryStatus::Pending,
+            ),
+            PlanEntry::new("Fix bug", PlanEntryPriority::High, PlanEntryStatus::Pending),
+        ])));
+        transcript.push(&SessionUpdate::ToolCall(ToolCall::new(
+            "call-1",
+            "Read file",
+        )));
+        transcript.push(&SessionUpdate::ToolCallUpdate(ToolCallUpdate::new(
+            "call-1",
+            ToolCallUpdateFields::new().status(ToolCallStatus::Failed),
+        )));
+        transcript.push(&SessionUpdate::Plan(Plan::new(vec![
+            PlanEntry::new(
+                "Find bug",
+                PlanEntryPriority::High,
+                PlanEntryStatus::Completed,
+            ),
+            PlanEntry::new(
+                "Fix bug",
+                PlanEntryPriority::High,
+                PlanEntryStatus::InProgress,
+            ),
+        ])));
+        transcript.push(&SessionUpdate::AgentMessageChunk(text_chunk("Done.")));
+
+        assert_eq!(
+            transcript.render(),
+            "**User**\n\nFix the bug\n\n\
+             > Looking around\n\n\
+             **Plan**\n\n- [x] Find bug\n- [ ] Fix bug _(in progress)_\n\n\
+             ❌ **Read file**\n\n\
+             **Agent**\n\nDone."
+        );
+
+        let mut hidden =
+            MarkdownTranscript::new(MarkdownRenderer::new().thoughts(ThoughtStyle::Hidden));
+        hidden.push(&SessionUpdate::AgentThoughtChunk(text_chunk("secret")));
+        assert_eq!(hidden.render(), "");
+    }
+}
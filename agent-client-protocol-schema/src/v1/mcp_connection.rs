@@ -0,0 +1,694 @@
+//! Multiplexing MCP-over-ACP connections onto plain MCP JSON-RPC.
+//!
+//! Either side of an ACP connection can provide MCP servers through the ACP
+//! transport. [`McpConnectionTable`] tracks the connections opened with
+//! `mcp/connect`, translates `mcp/message` traffic into the JSON-RPC messages
+//! an MCP implementation speaks ([`McpMessage`]) and back, and remaps request
+//! ids so that inner MCP ids and outer ACP ids never collide. The `requestId`
+//! of notifications such as `notifications/cancelled` is remapped through the
+//! same table, so a cancellation reaches the request it names.
+
+use std::collections::{HashMap, HashSet};
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ConnectMcpRequest, ConnectMcpResponse, DisconnectMcpRequest, Error, McpConnectionId,
+    McpServerAcpId, MessageMcpNotification, MessageMcpRequest, MessageMcpResponse,
+};
+use crate::rpc::{Notification, Request, RequestId, Response};
+
+/// Parameters of an MCP request or notification.
+pub type McpParams = serde_json::Map<String, serde_json::Value>;
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// A JSON-RPC message exchanged with an MCP implementation.
+///
+/// Wrap in [`crate::rpc::JsonRpcMessage`] to get the `"jsonrpc": "2.0"` wire format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+#[non_exhaustive]
+pub enum McpMessage {
+    /// An MCP request.
+    Request(Request<McpParams>),
+    /// An MCP notification.
+    Notification(Notification<McpParams>),
+    /// A response to an MCP request.
+    Response(Response<serde_json::Value, Error>),
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Open MCP-over-ACP connections and the requests in flight on them.
+///
+/// Traffic is named by direction: `forward_*` methods take ACP messages
+/// received from the peer and produce [`McpMessage`]s for the local MCP
+/// implementation, and `relay_*` methods take [`McpMessage`]s from the local
+/// MCP implementation and produce ACP messages for the peer.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct McpConnectionTable {
+    servers: HashSet<McpServerAcpId>,
+    connections: HashMap<McpConnectionId, McpConnection>,
+    /// Outer ACP ids of relayed requests, mapped to their connection and inner id.
+    relayed: HashMap<RequestId, (McpConnectionId, RequestId)>,
+    next_connection: u64,
+}
+
+#[derive(Debug)]
+struct McpConnection {
+    server_id: McpServerAcpId,
+    /// Inner MCP ids of forwarded requests, mapped to their outer ACP id.
+    forwarded: HashMap<RequestId, RequestId>,
+    next_request: i64,
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// A connection removed from a [`McpConnectionTable`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClosedMcpConnection {
+    /// The server the connection was opened to.
+    pub server_id: McpServerAcpId,
+    /// Outer ACP ids of forwarded requests that never received a response.
+    ///
+    /// The peer is still waiting for these and should be sent an error.
+    pub unanswered: Vec<RequestId>,
+    /// Inner MCP ids of relayed requests the peer never answered.
+    ///
+    /// The local MCP implementation is still waiting for these and should be
+    /// sent an error.
+    pub unanswered_relayed: Vec<RequestId>,
+}
+
+impl McpConnectionTable {
+    /// Builds an empty [`McpConnectionTable`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares an MCP server provided by this side, so `mcp/connect`
+    /// requests for it are accepted.
+    pub fn serve(&mut self, server_id: impl Into<McpServerAcpId>) {
+        self.servers.insert(server_id.into());
+    }
+
+    /// Accepts an `mcp/connect` request from the peer for a served MCP server.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpConnectionError::UnknownServer`] if the server was not
+    /// declared with [`McpConnectionTable::serve`].
+    pub fn accept(
+        &mut self,
+        request: &ConnectMcpRequest,
+    ) -> Result<ConnectMcpResponse, McpConnectionError> {
+        if !self.servers.contains(&request.server_id) {
+            return Err(McpConnectionError::UnknownServer(request.server_id.clone()));
+        }
+        self.next_connection += 1;
+        let connection_id = McpConnectionId::new(format!("mcp-{}", self.next_connection));
+        self.insert(request.server_id.clone(), connection_id.clone());
+        Ok(ConnectMcpResponse::new(connection_id))
+    }
+
+    /// Records a connection the peer accepted in response to our `mcp/connect`.
+    pub fn opened(&mut self, server_id: impl Into<McpServerAcpId>, response: &ConnectMcpResponse) {
+        self.insert(server_id.into(), response.connection_id.clone());
+    }
+
+    /// Closes a connection, such as when handling `mcp/disconnect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpConnectionError::UnknownConnection`] if the connection is
+    /// unknown or already closed.
+    pub fn close(
+        &mut self,
+        connection_id: &McpConnectionId,
+    ) -> Result<ClosedMcpConnection, McpConnectionError> {
+        let connection = self
+            .connections
+            .remove(connection_id)
+            .ok_or_else(|| McpConnectionError::UnknownConnection(connection_id.clone()))?;
+        let mut unanswered_relayed = Vec::new();
+        self.relayed.retain(|_, (relayed_connection, inner_id)| {
+            let keep = relayed_connection != connection_id;
+            if !keep {
+                unanswered_relayed.push(inner_id.clone());
+            }
+            keep
+        });
+        Ok(ClosedMcpConnection {
+            server_id: connection.server_id,
+            unanswered: connection.forwarded.into_values().collect(),
+            unanswered_relayed,
+        })
+    }
+
+    /// Handles an `mcp/disconnect` request from the peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is unknown or already closed.
+    pub fn disconnect(
+        &mut self,
+        request: &DisconnectMcpRequest,
+    ) -> Result<ClosedMcpConnection, McpConnectionError> {
+        self.close(&request.connection_id)
+    }
+
+    /// Returns `true` if the connection is open.
+    #[must_use]
+    pub fn is_open(&self, connection_id: &McpConnectionId) -> bool {
+        self.connections.contains_key(connection_id)
+    }
+
+    /// The server an open connection belongs to.
+    #[must_use]
+    pub fn server_id(&self, connection_id: &McpConnectionId) -> Option<&McpServerAcpId> {
+        self.connections
+            .get(connection_id)
+            .map(|connection| &connection.server_id)
+    }
+
+    /// Translates an `mcp/message` request received with ACP id `outer_id`
+    /// into an MCP request with a fresh id for the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is unknown or closed.
+    pub fn forward_request(
+        &mut self,
+        outer_id: RequestId,
+        request: MessageMcpRequest,
+    ) -> Result<McpMessage, McpConnectionError> {
+        let connection = self.connection(&request.connection_id)?;
+        let inner_id = RequestId::Number(connection.next_request);
+        connection.next_request += 1;
+        connection.forwarded.insert(inner_id.clone(), outer_id);
+        Ok(McpMessage::Request(Request {
+            id: inner_id,
+            method: request.method.into(),
+            params: request.params,
+        }))
+    }
+
+    /// Translates an `mcp/message` notification into an MCP notification.
+    ///
+    /// A `requestId` parameter names a forwarded request by its ACP id and is
+    /// replaced with the inner id that request was given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is unknown or closed, or
+    /// [`McpConnectionError::UnknownRequest`] if `requestId` does not name a
+    /// request still in flight on the connection. Such notifications should be
+    /// dropped.
+    pub fn forward_notification(
+        &mut self,
+        notification: MessageMcpNotification,
+    ) -> Result<McpMessage, McpConnectionError> {
+        let connection = self.connection(&notification.connection_id)?;
+        let params = remap_request_id(notification.params, |outer_id| {
+            connection
+                .forwarded
+                .iter()
+                .find(|(_, forwarded)| **forwarded == outer_id)
+                .map(|(inner_id, _)| inner_id.clone())
+        })?;
+        Ok(McpMessage::Notification(Notification {
+            method: notification.method.into(),
+            params,
+        }))
+    }
+
+    /// Translates the peer's response to a relayed request back into an MCP
+    /// response carrying the original inner id.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpConnectionError::UnexpectedResponse`] if no relayed
+    /// request is waiting for `response`, including when its connection has
+    /// since been closed.
+    pub fn forward_response(
+        &mut self,
+        response: Response<MessageMcpResponse, Error>,
+    ) -> Result<(McpConnectionId, McpMessage), McpConnectionError> {
+        let outer_id = response_id(&response);
+        let (connection_id, inner_id) = self
+            .relayed
+            .remove(outer_id)
+            .ok_or_else(|| McpConnectionError::UnexpectedResponse(outer_id.clone()))?;
+        let result = match response {
+            Response::Result { result, .. } => {
+                serde_json::from_str(result.0.get()).map_err(Error::into_internal_error)
+            }
+            Response::Error { error, .. } => Err(error),
+        };
+        let response = Response::new(inner_id, result);
+        Ok((connection_id, McpMessage::Response(response)))
+    }
+
+    /// Translates a request from the local MCP implementation into an
+    /// `mcp/message` request to be sent with ACP id `outer_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is unknown or closed.
+    pub fn relay_request(
+        &mut self,
+        connection_id: &McpConnectionId,
+        outer_id: RequestId,
+        request: Request<McpParams>,
+    ) -> Result<MessageMcpRequest, McpConnectionError> {
+        self.connection(connection_id)?;
+        self.relayed
+            .insert(outer_id, (connection_id.clone(), request.id));
+        Ok(
+            MessageMcpRequest::new(connection_id.clone(), request.method.to_string())
+                .params(request.params),
+        )
+    }
+
+    /// Translates a notification from the local MCP implementation into an
+    /// `mcp/message` notification.
+    ///
+    /// A `requestId` parameter names a relayed request by its inner id and is
+    /// replaced with the ACP id it was relayed with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is unknown or closed, or
+    /// [`McpConnectionError::UnknownRequest`] if `requestId` does not name a
+    /// request still in flight on the connection. Such notifications should be
+    /// dropped.
+    pub fn relay_notification(
+        &mut self,
+        connection_id: &McpConnectionId,
+        notification: Notification<McpParams>,
+    ) -> Result<MessageMcpNotification, McpConnectionError> {
+        self.connection(connection_id)?;
+        let params = remap_request_id(notification.params, |inner_id| {
+            self.relayed
+                .iter()
+                .find(|(_, relayed)| relayed.0 == *connection_id && relayed.1 == inner_id)
+                .map(|(outer_id, _)| outer_id.clone())
+        })?;
+        Ok(
+            MessageMcpNotification::new(connection_id.clone(), notification.method.to_string())
+                .params(params),
+        )
+    }
+
+    /// Translates a response from the local MCP implementation into the
+    /// response to the forwarded `mcp/message` request, carrying its ACP id.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is unknown or closed, or if no
+    /// forwarded request is waiting for `response`.
+    pub fn relay_response(
+        &mut self,
+        connection_id: &McpConnectionId,
+        response: Response<serde_json::Value, Error>,
+    ) -> Result<Response<MessageMcpResponse, Error>, McpConnectionError> {
+        let connection = self.connection(connection_id)?;
+        let inner_id = response_id(&response);
+        let outer_id = connection
+            .forwarded
+            .remove(inner_id)
+            .ok_or_else(|| McpConnectionError::UnexpectedResponse(inner_id.clone()))?;
+        let result = match response {
+            Response::Result { result, .. } => serde_json::value::to_raw_value(&result)
+                .map(|result| MessageMcpResponse::new(result.into()))
+                .map_err(Error::into_internal_error),
+            Response::Error { error, .. } => Err(error),
+        };
+        Ok(Response::new(outer_id, result))
+    }
+
+    fn insert(&mut self, server_id: McpServerAcpId, connection_id: McpConnectionId) {
+        self.connections.insert(
+            connection_id,
+            McpConnection {
+                server_id,
+                forwarded: HashMap::new(),
+                next_request: 0,
+            },
+        );
+    }
+
+    fn connection(
+        &mut self,
+        connection_id: &McpConnectionId,
+    ) -> Result<&mut McpConnection, McpConnectionError> {
+        self.connections
+            .get_mut(connection_id)
+            .ok_or_else(|| McpConnectionError::UnknownConnection(connection_id.clone()))
+    }
+}
+
+fn response_id<R, E>(response: &Response<R, E>) -> &RequestId {
+    match response {
+        Response::Result { id, .. } | Response::Error { id, .. } => id,
+    }
+}
+
+/// Replaces the `requestId` parameter of a notification, if present, with the
+/// id `map` translates it to.
+fn remap_request_id(
+    params: Option<McpParams>,
+    map: impl FnOnce(RequestId) -> Option<RequestId>,
+) -> Result<Option<McpParams>, McpConnectionError> {
+    let Some(mut params) = params else {
+        return Ok(None);
+    };
+    if let Some(value) = params.get_mut("requestId") {
+        let id: RequestId = serde_json::from_value(value.clone())
+            .map_err(|_| McpConnectionError::InvalidRequestId(value.clone()))?;
+        let mapped = map(id.clone()).ok_or(McpConnectionError::UnknownRequest(id))?;
+        *value = match mapped {
+            RequestId::Null => serde_json::Value::Null,
+            RequestId::Number(number) => number.into(),
+            RequestId::Str(string) => string.into(),
+        };
+    }
+    Ok(Some(params))
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// A message that cannot be routed through a [`McpConnectionTable`].
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum McpConnectionError {
+    /// `mcp/connect` named a server this side does not provide.
+    #[display("unknown MCP server: {_0}")]
+    UnknownServer(McpServerAcpId),
+    /// The connection id was never opened, or has since been closed.
+    #[display("unknown MCP connection: {_0}")]
+    UnknownConnection(McpConnectionId),
+    /// No request is waiting for a response with this id.
+    #[display("no pending MCP request with id {_0}")]
+    UnexpectedResponse(RequestId),
+    /// A notification's `requestId` names no request in flight on the connection.
+    #[display("no MCP request in flight with id {_0}")]
+    UnknownRequest(RequestId),
+    /// A notification's `requestId` is not a JSON-RPC request id.
+    #[display("invalid MCP request id: {_0}")]
+    InvalidRequestId(serde_json::Value),
+}
+
+impl std::error::Error for McpConnectionError {}
+
+impl From<McpConnectionError> for Error {
+    fn from(error: McpConnectionError) -> Self {
+        Error::invalid_params().data(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::rpc::JsonRpcMessage;
+
+    /// A minimal MCP server exposing a single `echo` tool.
+    fn stub_server(message: &str) -> Option<String> {
+        let message: JsonRpcMessage<McpMessage> = serde_json::from_str(message).unwrap();
+        let McpMessage::Request(request) = message.into_inner() else {
+            return None;
+        };
+        let result = match &*request.method {
+            "tools/list" => Ok(json!({ "tools": [{ "name": "echo" }] })),
+            "tools/call" => Ok(json!({
+                "content": [{ "type": "text", "text": request.params.unwrap()["arguments"]["text"] }]
+            })),
+            _ => Err(Error::method_not_found()),
+        };
+        let response = Response::new(request.id, result);
+        Some(serde_json::to_string(&JsonRpcMessage::wrap(McpMessage::Response(response))).unwrap())
+    }
+
+    /// Sends an ACP `mcp/message` request through the table to the stub server.
+    fn round_trip(
+        table: &mut McpConnectionTable,
+        outer_id: i64,
+        request: MessageMcpRequest,
+    ) -> Response<MessageMcpResponse, Error> {
+        let connection_id = request.connection_id.clone();
+        let inner = table.forward_request(outer_id.into(), request).unwrap();
+        let reply =
+            stub_server(&serde_json::to_string(&JsonRpcMessage::wrap(inner)).unwrap()).unwrap();
+        let reply: JsonRpcMessage<McpMessage> = serde_json::from_str(&reply).unwrap();
+        let McpMessage::Response(response) = reply.into_inner() else {
+            panic!("expected response");
+        };
+        table.relay_response(&connection_id, response).unwrap()
+    }
+
+    #[test]
+    fn forwards_requests_to_stub_server_and_remaps_ids() {
+        let mut table = McpConnectionTable::new();
+        table.serve("tools");
+        let connection_id = table
+            .accept(&ConnectMcpRequest::new("tools"))
+            .unwrap()
+            .connection_id;
+
+        let Response::Result { id, result } = round_trip(
+            &mut table,
+            41,
+            MessageMcpRequest::new(connection_id.clone(), "tools/list"),
+        ) else {
+            panic!("expected result");
+        };
+        assert_eq!(id, RequestId::Number(41));
+        assert_eq!(result.0.get(), r#"{"tools":[{"name":"echo"}]}"#);
+
+        let params = json!({ "name": "echo", "arguments": { "text": "hi" } });
+        let Response::Result { id, result } = round_trip(
+            &mut table,
+            42,
+            MessageMcpRequest::new(connection_id.clone(), "tools/call")
+                .params(params.as_object().cloned()),
+        ) else {
+            panic!("expected result");
+        };
+        assert_eq!(id, RequestId::Number(42));
+        assert_eq!(
+            result.0.get(),
+            r#"{"content":[{"type":"text","text":"hi"}]}"#
+        );
+
+        let Response::Error { id, error } = round_trip(
+            &mut table,
+            43,
+            MessageMcpRequest::new(connection_id, "prompts/list"),
+        ) else {
+            panic!("expected error");
+        };
+        assert_eq!(id, RequestId::Number(43));
+        assert_eq!(error, Error::method_not_found());
+    }
+
+    #[test]
+    fn relays_server_requests_to_peer() {
+        let mut table = McpConnectionTable::new();
+        table.opened("tools", &ConnectMcpResponse::new("conn"));
+        let connection_id = McpConnectionId::new("conn");
+
+        let request = table
+            .relay_request(
+                &connection_id,
+                RequestId::Str("acp-7".into()),
+                Request {
+                    id: RequestId::Number(0),
+                    method: "roots/list".into(),
+                    params: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(request.method, "roots/list");
+
+        let result = serde_json::value::to_raw_value(&json!({ "roots": [] })).unwrap();
+        let (routed_to, McpMessage::Response(Response::Result { id, .. })) = table
+            .forward_response(Response::new(
+                RequestId::Str("acp-7".into()),
+                Ok(MessageMcpResponse::new(result.into())),
+            ))
+            .unwrap()
+        else {
+            panic!("expected result");
+        };
+        assert_eq!(routed_to, connection_id);
+        assert_eq!(id, RequestId::Number(0));
+    }
+
+    #[test]
+    fn cancellations_are_remapped_in_both_directions() {
+        let mut table = McpConnectionTable::new();
+        table.serve("tools");
+        let connection_id = table
+            .accept(&ConnectMcpRequest::new("tools"))
+            .unwrap()
+            .connection_id;
+        for outer_id in [40, 41] {
+            table
+                .forward_request(
+                    outer_id.into(),
+                    MessageMcpRequest::new(connection_id.clone(), "tools/call"),
+                )
+                .unwrap();
+        }
+
+        let cancel = |request_id: serde_json::Value| {
+            json!({ "requestId": request_id, "reason": "user" })
+                .as_object()
+                .cloned()
+        };
+        let McpMessage::Notification(forwarded) = table
+            .forward_notification(
+                MessageMcpNotification::new(connection_id.clone(), "notifications/cancelled")
+                    .params(cancel(json!(41))),
+            )
+            .unwrap()
+        else {
+            panic!("expected notification");
+        };
+        assert_eq!(forwarded.params, cancel(json!(1)));
+        assert_eq!(
+            table
+                .forward_notification(
+                    MessageMcpNotification::new(connection_id.clone(), "notifications/cancelled")
+                        .params(cancel(json!(7))),
+                )
+                .unwrap_err(),
+            McpConnectionError::UnknownRequest(RequestId::Number(7))
+        );
+
+        table
+            .relay_request(
+                &connection_id,
+                RequestId::Str("acp-3".into()),
+                Request {
+                    id: RequestId::Number(0),
+                    method: "sampling/createMessage".into(),
+                    params: None,
+                },
+            )
+            .unwrap();
+        let relayed = table
+            .relay_notification(
+                &connection_id,
+                Notification {
+                    method: "notifications/cancelled".into(),
+                    params: cancel(json!(0)),
+                },
+            )
+            .unwrap();
+        assert_eq!(relayed.params, cancel(json!("acp-3")));
+
+        let progress = json!({ "progressToken": "t", "progress": 1 })
+            .as_object()
+            .cloned();
+        let relayed = table
+            .relay_notification(
+                &connection_id,
+                Notification {
+                    method: "notifications/progress".into(),
+                    params: progress.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(relayed.params, progress);
+    }
+
+    #[test]
+    fn rejects_unknown_and_closed_connections() {
+        let mut table = McpConnectionTable::new();
+        assert_eq!(
+            table
+                .accept(&ConnectMcpRequest::new("missing"))
+                .unwrap_err(),
+            McpConnectionError::UnknownServer("missing".into())
+        );
+
+        table.serve("tools");
+        let connection_id = table
+            .accept(&ConnectMcpRequest::new("tools"))
+            .unwrap()
+            .connection_id;
+        table
+            .forward_request(
+                9.into(),
+                MessageMcpRequest::new(connection_id.clone(), "tools/list"),
+            )
+            .unwrap();
+        table
+            .relay_request(
+                &connection_id,
+                RequestId::Str("acp-1".into()),
+                Request {
+                    id: RequestId::Number(4),
+                    method: "roots/list".into(),
+                    params: None,
+                },
+            )
+            .unwrap();
+
+        let closed = table
+            .disconnect(&DisconnectMcpRequest::new(connection_id.clone()))
+            .unwrap();
+        assert_eq!(closed.unanswered, [RequestId::Number(9)]);
+        assert_eq!(closed.unanswered_relayed, [RequestId::Number(4)]);
+        assert!(!table.is_open(&connection_id));
+        assert_eq!(
+            table
+                .forward_response(Response::new(
+                    RequestId::Str("acp-1".into()),
+                    Ok(MessageMcpResponse::new(
+                        serde_json::value::to_raw_value(&json!({})).unwrap().into()
+                    )),
+                ))
+                .unwrap_err(),
+            McpConnectionError::UnexpectedResponse(RequestId::Str("acp-1".into()))
+        );
+
+        let error = table
+            .forward_notification(MessageMcpNotification::new(
+                connection_id.clone(),
+                "notifications/cancelled",
+            ))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            McpConnectionError::UnknownConnection(connection_id.clone())
+        );
+        assert_eq!(
+            table.disconnect(&DisconnectMcpRequest::new(connection_id.clone())),
+            Err(McpConnectionError::UnknownConnection(connection_id))
+        );
+        assert_eq!(
+            table
+                .forward_request(1.into(), MessageMcpRequest::new("nope", "tools/list"))
+                .unwrap_err(),
+            McpConnectionError::UnknownConnection("nope".into())
+        );
+        assert_eq!(Error::from(error).code, Error::invalid_params().code);
+    }
+}
@@ -0,0 +1,691 @@
+//! Normalized launch specs for [`McpServer`] configurations.
+//!
+//! A [`McpLaunchSpec`] describes how to reach an MCP server without the
+//! protocol metadata: a process to spawn, an HTTP endpoint to connect to, or
+//! an ACP-provided server. Specs can be checked against the agent's
+//! [`McpCapabilities`], redacted for logging, and converted to and from the
+//! `mcpServers` entries used by editor configuration files. Whole files are
+//! read and written through [`McpServersConfig`].
+
+use std::{path::PathBuf, process::Command};
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use serde_with::{Map, serde_as, skip_serializing_none};
+
+use super::{
+    EnvVariable, Error, HttpHeader, McpCapabilities, McpServer, McpServerHttp, McpServerSse,
+    McpServerStdio,
+};
+#[cfg(feature = "unstable_mcp_over_acp")]
+use super::{McpServerAcp, McpServerAcpId};
+use crate::mcp_launch::{expand_vars, redact_pairs};
+use crate::redact::{redact_args, redact_url};
+
+/// How to reach an MCP server, derived from a [`McpServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum McpLaunchSpec {
+    /// Spawn a process and speak MCP over its stdin/stdout.
+    Stdio(McpStdioLaunch),
+    /// Connect to a remote MCP server over HTTP or SSE.
+    Http(McpHttpEndpoint),
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// Connect through an MCP server provided over the ACP channel.
+    #[cfg(feature = "unstable_mcp_over_acp")]
+    Acp(McpAcpEndpoint),
+}
+
+/// A process to spawn for a stdio MCP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct McpStdioLaunch {
+    /// Human-readable name identifying this MCP server.
+    pub name: String,
+    /// Path to the MCP server executable.
+    pub command: PathBuf,
+    /// Command-line arguments to pass to the MCP server.
+    pub args: Vec<String>,
+    /// Environment variables to set, in order.
+    pub env: Vec<(String, String)>,
+}
+
+impl McpStdioLaunch {
+    /// Builds a [`Command`] that spawns the server with its arguments and environment.
+    ///
+    /// Standard I/O is left for the caller to configure.
+    #[must_use]
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.command);
+        command.args(&self.args);
+        command.envs(self.env.iter().map(|(name, value)| (name, value)));
+        command
+    }
+}
+
+/// The transport spoken by a remote MCP server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum McpHttpTransport {
+    /// Streamable HTTP.
+    #[default]
+    Http,
+    /// Server-sent events.
+    Sse,
+}
+
+/// A remote MCP server endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct McpHttpEndpoint {
+    /// Human-readable name identifying this MCP server.
+    pub name: String,
+    /// URL of the MCP server.
+    pub url: String,
+    /// Transport to use when connecting.
+    pub transport: McpHttpTransport,
+    /// HTTP headers to send with every request, in order.
+    pub headers: Vec<(String, String)>,
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// An MCP server provided over the ACP channel.
+#[cfg(feature = "unstable_mcp_over_acp")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct McpAcpEndpoint {
+    /// Human-readable name identifying this MCP server.
+    pub name: String,
+    /// Identifier of the server on the ACP connection.
+    pub server_id: McpServerAcpId,
+}
+
+impl McpLaunchSpec {
+    /// Returns the server's human-readable name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Stdio(launch) => &launch.name,
+            Self::Http(endpoint) => &endpoint.name,
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            Self::Acp(endpoint) => &endpoint.name,
+        }
+    }
+
+    /// Checks that the agent advertised support for this spec's transport.
+    ///
+    /// Stdio servers are always supported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpLaunchError::UnsupportedTransport`] when the matching
+    /// capability flag is not set.
+    pub fn check(&self, capabilities: &McpCapabilities) -> Result<(), McpLaunchError> {
+        let (supported, transport) = match self {
+            Self::Stdio(_) => (true, "stdio"),
+            Self::Http(endpoint) => match endpoint.transport {
+                McpHttpTransport::Http => (capabilities.http, "http"),
+                McpHttpTransport::Sse => (capabilities.sse, "sse"),
+            },
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            Self::Acp(_) => (capabilities.acp, "acp"),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(McpLaunchError::UnsupportedTransport {
+                name: self.name().to_string(),
+                transport: transport.to_string(),
+            })
+        }
+    }
+
+    /// Returns a copy with secret-looking header and environment values,
+    /// command-line options and URL credentials replaced, suitable for logging.
+    #[must_use]
+    pub fn redacted(&self) -> Self {
+        let mut spec = self.clone();
+        match &mut spec {
+            Self::Stdio(launch) => {
+                launch.args = redact_args(&launch.args);
+                launch.env = redact_pairs(&launch.env);
+            }
+            Self::Http(endpoint) => {
+                endpoint.url = redact_url(&endpoint.url);
+                endpoint.headers = redact_pairs(&endpoint.headers);
+            }
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            Self::Acp(_) => {}
+        }
+        spec
+    }
+
+    /// Converts this spec to an editor `mcpServers` entry, keyed by [`Self::name`].
+    #[must_use]
+    pub fn to_config(&self) -> McpServerConfig {
+        let mut config = McpServerConfig::default();
+        match self {
+            Self::Stdio(launch) => {
+                config.command = Some(launch.command.clone());
+                config.args.clone_from(&launch.args);
+                config.env.clone_from(&launch.env);
+            }
+            Self::Http(endpoint) => {
+                config.type_ = Some(
+                    match endpoint.transport {
+                        McpHttpTransport::Http => "http",
+                        McpHttpTransport::Sse => "sse",
+                    }
+                    .to_string(),
+                );
+                config.url = Some(endpoint.url.clone());
+                config.headers.clone_from(&endpoint.headers);
+            }
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            Self::Acp(endpoint) => {
+                config.type_ = Some("acp".to_string());
+                config.server_id = Some(endpoint.server_id.0.to_string());
+            }
+        }
+        config
+    }
+
+    /// Builds a spec from an editor `mcpServers` entry.
+    ///
+    /// Entries without a `type` are stdio servers when they have a `command`
+    /// and HTTP servers when they have a `url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpLaunchError::InvalidConfig`] when the entry names an
+    /// unknown type or lacks the fields its type requires.
+    pub fn from_config(
+        name: impl Into<String>,
+        config: &McpServerConfig,
+    ) -> Result<Self, McpLaunchError> {
+        let name = name.into();
+        let invalid = |reason: &str| McpLaunchError::InvalidConfig {
+            name: name.clone(),
+            reason: reason.to_string(),
+        };
+        let type_ = match (config.type_.as_deref(), &config.command, &config.url) {
+            (Some(type_), _, _) => type_,
+            (None, Some(_), _) => "stdio",
+            (None, None, Some(_)) => "http",
+            (None, None, None) => return Err(invalid("expected a `command` or `url`")),
+        };
+        let http = |transport| {
+            let url = config.url.clone().ok_or_else(|| invalid("missing `url`"))?;
+            Ok(Self::Http(McpHttpEndpoint {
+                name: name.clone(),
+                url,
+                transport,
+                headers: config.headers.clone(),
+            }))
+        };
+        match type_ {
+            "stdio" => {
+                let command = config
+                    .command
+                    .clone()
+                    .ok_or_else(|| invalid("missing `command`"))?;
+                Ok(Self::Stdio(McpStdioLaunch {
+                    name: name.clone(),
+                    command,
+                    args: config.args.clone(),
+                    env: config.env.clone(),
+                }))
+            }
+            "http" | "streamable-http" | "streamableHttp" => http(McpHttpTransport::Http),
+            "sse" => http(McpHttpTransport::Sse),
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            "acp" => {
+                let server_id = config
+                    .server_id
+                    .clone()
+                    .ok_or_else(|| invalid("missing `serverId`"))?;
+                Ok(Self::Acp(McpAcpEndpoint {
+                    name: name.clone(),
+                    server_id: McpServerAcpId::new(server_id),
+                }))
+            }
+            other => Err(invalid(&format!("unknown server type `{other}`"))),
+        }
+    }
+}
+
+impl From<McpServer> for McpLaunchSpec {
+    fn from(server: McpServer) -> Self {
+        match server {
+            McpServer::Stdio(stdio) => Self::Stdio(McpStdioLaunch {
+                name: stdio.name,
+                command: stdio.command,
+                args: stdio.args,
+                env: stdio
+                    .env
+                    .into_iter()
+                    .map(|variable| (variable.name, variable.value))
+                    .collect(),
+            }),
+            McpServer::Http(http) => Self::Http(McpHttpEndpoint {
+                name: http.name,
+                url: http.url,
+                transport: McpHttpTransport::Http,
+                headers: header_pairs(http.headers),
+            }),
+            McpServer::Sse(sse) => Self::Http(McpHttpEndpoint {
+                name: sse.name,
+                url: sse.url,
+                transport: McpHttpTransport::Sse,
+                headers: header_pairs(sse.headers),
+            }),
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            McpServer::Acp(acp) => Self::Acp(McpAcpEndpoint {
+                name: acp.name,
+                server_id: acp.server_id,
+            }),
+        }
+    }
+}
+
+impl From<McpLaunchSpec> for McpServer {
+    fn from(spec: McpLaunchSpec) -> Self {
+        match spec {
+            McpLaunchSpec::Stdio(launch) => McpServer::Stdio(
+                McpServerStdio::new(launch.name, launch.command)
+                    .args(launch.args)
+                    .env(
+                        launch
+                            .env
+                            .into_iter()
+                            .map(|(name, value)| EnvVariable::new(name, value))
+                            .collect(),
+                    ),
+            ),
+            McpLaunchSpec::Http(endpoint) => {
+                let headers = endpoint
+                    .headers
+                    .into_iter()
+                    .map(|(name, value)| HttpHeader::new(name, value))
+                    .collect();
+                match endpoint.transport {
+                    McpHttpTransport::Http => McpServer::Http(
+                        McpServerHttp::new(endpoint.name, endpoint.url).headers(headers),
+                    ),
+                    McpHttpTransport::Sse => McpServer::Sse(
+                        McpServerSse::new(endpoint.name, endpoint.url).headers(headers),
+                    ),
+                }
+            }
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            McpLaunchSpec::Acp(endpoint) => {
+                McpServer::Acp(McpServerAcp::new(endpoint.name, endpoint.server_id))
+            }
+        }
+    }
+}
+
+fn header_pairs(headers: Vec<HttpHeader>) -> Vec<(String, String)> {
+    headers
+        .into_iter()
+        .map(|header| (header.name, header.value))
+        .collect()
+}
+
+/// One entry of the `mcpServers` object used by editor configuration files.
+///
+/// Fields this crate does not interpret are kept in [`Self::extra`] so that
+/// entries round-trip unchanged.
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpServerConfig {
+    /// Transport type: `stdio`, `http`, `sse` or `acp`. Inferred when omitted.
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// Executable of a stdio server.
+    pub command: Option<PathBuf>,
+    /// Arguments of a stdio server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Environment of a stdio server.
+    #[serde_as(as = "Map<_, _>")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<(String, String)>,
+    /// URL of a remote server.
+    pub url: Option<String>,
+    /// Headers sent to a remote server.
+    #[serde_as(as = "Map<_, _>")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    /// ACP server id of an `acp` server.
+    pub server_id: Option<String>,
+    /// Fields not interpreted by this crate.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl McpServerConfig {
+    /// Returns whether the entry is marked `"disabled": true`.
+    #[must_use]
+    pub fn is_disabled(&self) -> bool {
+        self.extra.get("disabled") == Some(&serde_json::Value::Bool(true))
+    }
+
+    /// Returns a copy with `${VAR}` and `${VAR:-default}` references expanded
+    /// in the command, arguments, environment values, URL, headers and server id.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpLaunchError::UndefinedVariable`] for the first reference
+    /// that `lookup` cannot resolve and that has no default.
+    pub fn expanded(
+        &self,
+        name: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, McpLaunchError> {
+        let expand = |value: &str| {
+            expand_vars(value, &lookup).map_err(|variable| McpLaunchError::UndefinedVariable {
+                name: name.to_string(),
+                variable,
+            })
+        };
+        let expand_pairs = |pairs: &[(String, String)]| {
+            pairs
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), expand(value)?)))
+                .collect::<Result<Vec<_>, McpLaunchError>>()
+        };
+        let mut config = self.clone();
+        if let Some(command) = self.command.as_ref().and_then(|command| command.to_str()) {
+            config.command = Some(expand(command)?.into());
+        }
+        config.args = self
+            .args
+            .iter()
+            .map(|arg| expand(arg))
+            .collect::<Result<_, _>>()?;
+        config.env = expand_pairs(&self.env)?;
+        config.url = self.url.as_deref().map(expand).transpose()?;
+        config.headers = expand_pairs(&self.headers)?;
+        config.server_id = self.server_id.as_deref().map(expand).transpose()?;
+        Ok(config)
+    }
+}
+
+/// An editor configuration file holding an `mcpServers` object.
+///
+/// The VS Code `servers` key is accepted as an alias. Other top-level fields
+/// are kept in [`Self::extra`].
+#[serde_as]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpServersConfig {
+    /// Server entries keyed by name, in file order.
+    #[serde_as(as = "Map<_, _>")]
+    #[serde(default, alias = "servers")]
+    pub mcp_servers: Vec<(String, McpServerConfig)>,
+    /// Fields not interpreted by this crate.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl McpServersConfig {
+    /// Describes `servers` as `mcpServers` entries keyed by server name.
+    #[must_use]
+    pub fn from_servers(servers: impl IntoIterator<Item = McpServer>) -> Self {
+        Self {
+            mcp_servers: servers
+                .into_iter()
+                .map(|server| {
+                    let spec = McpLaunchSpec::from(server);
+                    (spec.name().to_string(), spec.to_config())
+                })
+                .collect(),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// Converts the enabled entries to [`McpServer`]s, expanding variable
+    /// references with `lookup`.
+    ///
+    /// Pass `|name| std::env::var(name).ok()` to expand from the process environment.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first entry that references an undefined variable or maps
+    /// to no [`McpServer`] variant.
+    pub fn to_servers(
+        &self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<McpServer>, McpLaunchError> {
+        self.mcp_servers
+            .iter()
+            .filter(|(_, config)| !config.is_disabled())
+            .map(|(name, config)| {
+                let config = config.expanded(name, &lookup)?;
+                McpLaunchSpec::from_config(name.clone(), &config).map(McpServer::from)
+            })
+            .collect()
+    }
+}
+
+/// A [`McpServer`] that cannot be launched or described.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum McpLaunchError {
+    /// The agent did not advertise support for the server's transport.
+    #[display("MCP server `{name}` uses unsupported transport `{transport}`")]
+    UnsupportedTransport {
+        /// Name of the rejected server.
+        name: String,
+        /// Transport the server asked for.
+        transport: String,
+    },
+    /// An `mcpServers` entry does not describe a usable server.
+    #[display("invalid MCP server config `{name}`: {reason}")]
+    InvalidConfig {
+        /// Key of the rejected entry.
+        name: String,
+        /// What is wrong with the entry.
+        reason: String,
+    },
+    /// An `mcpServers` entry references a variable that is not set.
+    #[display("MCP server config `{name}` references undefined variable `{variable}`")]
+    UndefinedVariable {
+        /// Key of the rejected entry.
+        name: String,
+        /// Name of the missing variable.
+        variable: String,
+    },
+}
+
+impl std::error::Error for McpLaunchError {}
+
+impl From<McpLaunchError> for Error {
+    fn from(error: McpLaunchError) -> Self {
+        Error::invalid_params().data(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::redact::REDACTED;
+
+    fn github_server() -> McpServer {
+        McpServer::Stdio(
+            McpServerStdio::new("github", "/usr/bin/github-mcp")
+                .args(vec!["--stdio".to_string()])
+                .env(vec![
+                    EnvVariable::new("GITHUB_TOKEN", "ghp_secret"),
+                    EnvVariable::new("RUST_LOG", "debug"),
+                ]),
+        )
+    }
+
+    #[test]
+    fn stdio_server_becomes_spawn_description() {
+        let McpLaunchSpec::Stdio(launch) = McpLaunchSpec::from(github_server()) else {
+            panic!("expected stdio launch");
+        };
+        let command = launch.to_command();
+        assert_eq!(command.get_program(), "/usr/bin/github-mcp");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["--stdio"]);
+        assert_eq!(command.get_envs().count(), 2);
+    }
+
+    #[test]
+    fn check_follows_capability_flags() {
+        let sse = McpLaunchSpec::from(McpServer::Sse(McpServerSse::new(
+            "events",
+            "https://example.com/sse",
+        )));
+        let capabilities = McpCapabilities::new().http(true);
+
+        assert!(
+            McpLaunchSpec::from(github_server())
+                .check(&capabilities)
+                .is_ok()
+        );
+        assert_eq!(
+            sse.check(&capabilities),
+            Err(McpLaunchError::UnsupportedTransport {
+                name: "events".to_string(),
+                transport: "sse".to_string(),
+            })
+        );
+        assert!(sse.check(&capabilities.sse(true)).is_ok());
+    }
+
+    #[test]
+    fn redaction_masks_secret_values_only() {
+        let McpLaunchSpec::Stdio(launch) = McpLaunchSpec::from(github_server()).redacted() else {
+            panic!("expected stdio launch");
+        };
+        assert_eq!(
+            launch.env,
+            [
+                ("GITHUB_TOKEN".to_string(), REDACTED.to_string()),
+                ("RUST_LOG".to_string(), "debug".to_string()),
+            ]
+        );
+
+        let http = McpServer::Http(
+            McpServerHttp::new("api", "https://example.com/mcp?api_key=sk-1")
+                .headers(vec![HttpHeader::new("Authorization", "Bearer abc")]),
+        );
+        let McpLaunchSpec::Http(endpoint) = McpLaunchSpec::from(http).redacted() else {
+            panic!("expected http endpoint");
+        };
+        assert_eq!(endpoint.headers[0].1, REDACTED);
+        assert_eq!(endpoint.url, "https://example.com/mcp?api_key=[REDACTED]");
+
+        let stdio = McpServer::Stdio(
+            McpServerStdio::new("db", "/usr/bin/db-mcp")
+                .args(vec!["--password".to_string(), "hunter2".to_string()]),
+        );
+        let McpLaunchSpec::Stdio(launch) = McpLaunchSpec::from(stdio).redacted() else {
+            panic!("expected stdio launch");
+        };
+        assert_eq!(launch.args, ["--password", REDACTED]);
+    }
+
+    #[test]
+    fn config_entries_round_trip() {
+        let entry = json!({
+            "command": "/usr/bin/github-mcp",
+            "args": ["--stdio"],
+            "env": {"GITHUB_TOKEN": "ghp_secret", "RUST_LOG": "debug"},
+            "disabled": false
+        });
+        let config: McpServerConfig = serde_json::from_value(entry.clone()).unwrap();
+        let spec = McpLaunchSpec::from_config("github", &config).unwrap();
+        assert_eq!(McpServer::from(spec.clone()), github_server());
+
+        let mut exported = spec.to_config();
+        exported.extra = config.extra;
+        assert_eq!(serde_json::to_value(&exported).unwrap(), entry);
+
+        let sse = json!({
+            "type": "sse",
+            "url": "https://example.com/sse",
+            "headers": {"X-Api-Key": "k"}
+        });
+        let config: McpServerConfig = serde_json::from_value(sse.clone()).unwrap();
+        let spec = McpLaunchSpec::from_config("events", &config).unwrap();
+        assert!(matches!(McpServer::from(spec.clone()), McpServer::Sse(_)));
+        assert_eq!(serde_json::to_value(spec.to_config()).unwrap(), sse);
+    }
+
+    #[test]
+    fn config_without_transport_is_rejected() {
+        let config: McpServerConfig = serde_json::from_value(json!({"type": "ws"})).unwrap();
+        assert_eq!(
+            McpLaunchSpec::from_config("socket", &config),
+            Err(McpLaunchError::InvalidConfig {
+                name: "socket".to_string(),
+                reason: "unknown server type `ws`".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn config_files_expand_variables_and_skip_disabled_entries() {
+        let file: McpServersConfig = serde_json::from_value(json!({
+            "mcpServers": {
+                "github": {
+                    "command": "${BIN:-/usr/bin}/github-mcp",
+                    "args": ["--stdio"],
+                    "env": {"GITHUB_TOKEN": "${GITHUB_TOKEN}", "RUST_LOG": "debug"}
+                },
+                "old": {"command": "old-mcp", "disabled": true}
+            },
+            "inputs": []
+        }))
+        .unwrap();
+        let env = |name: &str| (name == "GITHUB_TOKEN").then(|| "ghp_secret".to_string());
+        assert_eq!(file.to_servers(env).unwrap(), [github_server()]);
+        assert_eq!(
+            file.to_servers(|_| None),
+            Err(McpLaunchError::UndefinedVariable {
+                name: "github".to_string(),
+                variable: "GITHUB_TOKEN".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn config_files_round_trip_servers() {
+        let servers = vec![
+            github_server(),
+            McpServer::Http(McpServerHttp::new("api", "https://example.com/mcp")),
+        ];
+        let json = serde_json::to_value(McpServersConfig::from_servers(servers.clone())).unwrap();
+        assert_eq!(
+            json["mcpServers"]["api"],
+            json!({"type": "http", "url": "https://example.com/mcp"})
+        );
+
+        let file: McpServersConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(file.to_servers(|_| None).unwrap(), servers);
+
+        let vscode: McpServersConfig =
+            serde_json::from_value(json!({"servers": {"broken": {"args": []}}})).unwrap();
+        assert!(matches!(
+            vscode.to_servers(|_| None),
+            Err(McpLaunchError::InvalidConfig { name, .. }) if name == "broken"
+        ));
+    }
+}
@@ -0,0 +1,760 @@
+//! Typed payloads for the core MCP methods tunnelled through `mcp/message`.
+//!
+//! [`MessageMcpRequest`] carries MCP traffic as a method name and a raw JSON
+//! object. The [`McpRequest`] implementations here give the most common MCP
+//! methods typed parameters and results, and convert them to and from the
+//! ACP envelope. MCP content blocks share their wire format with ACP's
+//! [`ContentBlock`], so they are reused as-is.
+
+use std::collections::BTreeMap;
+
+use derive_more::{Display, From};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_with::{DefaultOnError, serde_as, skip_serializing_none};
+
+use super::{
+    ContentBlock, EmbeddedResourceResource, McpConnectionId, MessageMcpRequest, MessageMcpResponse,
+    Meta, Role,
+};
+use crate::IntoOption;
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// An MCP request with a typed result.
+pub trait McpRequest: Serialize + DeserializeOwned {
+    /// The MCP method name.
+    const METHOD: &'static str;
+    /// The result returned by the MCP server.
+    type Result: Serialize + DeserializeOwned;
+
+    /// Wraps this request in an `mcp/message` request for `connection_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpPayloadError::Json`] if the request does not serialize to a JSON object.
+    fn into_message(
+        self,
+        connection_id: impl Into<McpConnectionId>,
+    ) -> Result<MessageMcpRequest, McpPayloadError> {
+        let params: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_value(serde_json::to_value(self)?)?;
+        Ok(MessageMcpRequest::new(connection_id, Self::METHOD)
+            .params((!params.is_empty()).then_some(params)))
+    }
+
+    /// Reads this request from an `mcp/message` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpPayloadError::MethodMismatch`] if the message is for a
+    /// different method, or [`McpPayloadError::Json`] if its params are invalid.
+    fn from_message(message: &MessageMcpRequest) -> Result<Self, McpPayloadError> {
+        if message.method != Self::METHOD {
+            return Err(McpPayloadError::MethodMismatch {
+                expected: Self::METHOD,
+                actual: message.method.clone(),
+            });
+        }
+        Ok(serde_json::from_value(serde_json::Value::Object(
+            message.params.clone().unwrap_or_default(),
+        ))?)
+    }
+
+    /// Reads the typed result from the response to this request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpPayloadError::Json`] if the response is not a valid result.
+    fn result_from_response(
+        response: &MessageMcpResponse,
+    ) -> Result<Self::Result, McpPayloadError> {
+        Ok(serde_json::from_str(response.0.get())?)
+    }
+
+    /// Wraps a typed result as the response to this request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpPayloadError::Json`] if the result cannot be serialized.
+    fn result_into_response(result: &Self::Result) -> Result<MessageMcpResponse, McpPayloadError> {
+        Ok(MessageMcpResponse::new(
+            serde_json::value::to_raw_value(result)?.into(),
+        ))
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Error converting between typed MCP payloads and `mcp/message` payloads.
+#[derive(Debug, Display, From)]
+#[non_exhaustive]
+pub enum McpPayloadError {
+    /// The message is for a different MCP method.
+    #[from(ignore)]
+    #[display("expected MCP method `{expected}`, got `{actual}`")]
+    MethodMismatch {
+        /// The method the typed payload belongs to.
+        expected: &'static str,
+        /// The method of the message.
+        actual: String,
+    },
+    /// The payload does not match the typed structure.
+    #[display("invalid MCP payload: {_0}")]
+    Json(serde_json::Error),
+}
+
+impl std::error::Error for McpPayloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::MethodMismatch { .. } => None,
+            Self::Json(error) => Some(error),
+        }
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// One of the core MCP requests, as received by an ACP-hosted MCP server.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum McpCoreRequest {
+    /// `tools/list`
+    ListTools(McpListToolsRequest),
+    /// `tools/call`
+    CallTool(McpCallToolRequest),
+    /// `resources/read`
+    ReadResource(McpReadResourceRequest),
+    /// `prompts/get`
+    GetPrompt(McpGetPromptRequest),
+}
+
+impl McpCoreRequest {
+    /// Reads a core MCP request from an `mcp/message` request.
+    ///
+    /// Returns `Ok(None)` for methods without a typed payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`McpPayloadError::Json`] if the params do not match the method.
+    pub fn from_message(message: &MessageMcpRequest) -> Result<Option<Self>, McpPayloadError> {
+        Ok(Some(match message.method.as_str() {
+            McpListToolsRequest::METHOD => {
+                Self::ListTools(McpListToolsRequest::from_message(message)?)
+            }
+            McpCallToolRequest::METHOD => {
+                Self::CallTool(McpCallToolRequest::from_message(message)?)
+            }
+            McpReadResourceRequest::METHOD => {
+                Self::ReadResource(McpReadResourceRequest::from_message(message)?)
+            }
+            McpGetPromptRequest::METHOD => {
+                Self::GetPrompt(McpGetPromptRequest::from_message(message)?)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Parameters for the MCP `tools/list` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpListToolsRequest {
+    /// Opaque pagination cursor returned by a previous `tools/list` result.
+    pub cursor: Option<String>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpListToolsRequest {
+    /// Builds [`McpListToolsRequest`] for the first page.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opaque pagination cursor returned by a previous `tools/list` result.
+    #[must_use]
+    pub fn cursor(mut self, cursor: impl IntoOption<String>) -> Self {
+        self.cursor = cursor.into_option();
+        self
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+impl McpRequest for McpListToolsRequest {
+    const METHOD: &'static str = "tools/list";
+    type Result = McpListToolsResult;
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Result of the MCP `tools/list` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpListToolsResult {
+    /// The tools offered by the server.
+    pub tools: Vec<McpTool>,
+    /// Cursor for the next page, if there are more tools.
+    pub next_cursor: Option<String>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpListToolsResult {
+    /// Builds [`McpListToolsResult`] for the last page.
+    #[must_use]
+    pub fn new(tools: Vec<McpTool>) -> Self {
+        Self {
+            tools,
+            next_cursor: None,
+            meta: None,
+        }
+    }
+
+    /// Cursor for the next page, if there are more tools.
+    #[must_use]
+    pub fn next_cursor(mut self, next_cursor: impl IntoOption<String>) -> Self {
+        self.next_cursor = next_cursor.into_option();
+        self
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// A tool offered by an MCP server.
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpTool {
+    /// Programmatic name of the tool.
+    pub name: String,
+    /// Human-readable name of the tool.
+    pub title: Option<String>,
+    /// What the tool does, for the model.
+    pub description: Option<String>,
+    /// JSON Schema of the tool's arguments.
+    pub input_schema: serde_json::Value,
+    /// JSON Schema of the tool's structured output.
+    pub output_schema: Option<serde_json::Value>,
+    /// Hints about the tool's behavior, such as whether it is read-only.
+    pub annotations: Option<serde_json::Value>,
+}
+
+impl McpTool {
+    /// Builds [`McpTool`] with the required fields set; optional fields start unset.
+    #[must_use]
+    pub fn new(name: impl Into<String>, input_schema: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            title: None,
+            description: None,
+            input_schema,
+            output_schema: None,
+            annotations: None,
+        }
+    }
+
+    /// Human-readable name of the tool.
+    #[must_use]
+    pub fn title(mut self, title: impl IntoOption<String>) -> Self {
+        self.title = title.into_option();
+        self
+    }
+
+    /// What the tool does, for the model.
+    #[must_use]
+    pub fn description(mut self, description: impl IntoOption<String>) -> Self {
+        self.description = description.into_option();
+        self
+    }
+
+    /// JSON Schema of the tool's structured output.
+    #[must_use]
+    pub fn output_schema(mut self, output_schema: impl IntoOption<serde_json::Value>) -> Self {
+        self.output_schema = output_schema.into_option();
+        self
+    }
+
+    /// Hints about the tool's behavior, such as whether it is read-only.
+    #[must_use]
+    pub fn annotations(mut self, annotations: impl IntoOption<serde_json::Value>) -> Self {
+        self.annotations = annotations.into_option();
+        self
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Parameters for the MCP `tools/call` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpCallToolRequest {
+    /// Name of the tool to call.
+    pub name: String,
+    /// Arguments matching the tool's input schema.
+    pub arguments: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpCallToolRequest {
+    /// Builds [`McpCallToolRequest`] without arguments.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            arguments: None,
+            meta: None,
+        }
+    }
+
+    /// Arguments matching the tool's input schema.
+    #[must_use]
+    pub fn arguments(
+        mut self,
+        arguments: impl IntoOption<serde_json::Map<String, serde_json::Value>>,
+    ) -> Self {
+        self.arguments = arguments.into_option();
+        self
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+impl McpRequest for McpCallToolRequest {
+    const METHOD: &'static str = "tools/call";
+    type Result = McpCallToolResult;
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Result of the MCP `tools/call` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpCallToolResult {
+    /// Unstructured output of the tool.
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+    /// Output matching the tool's output schema.
+    pub structured_content: Option<serde_json::Value>,
+    /// Whether the tool reported an error. Tool errors are results, not JSON-RPC errors.
+    pub is_error: Option<bool>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpCallToolResult {
+    /// Builds a successful [`McpCallToolResult`].
+    #[must_use]
+    pub fn new(content: Vec<ContentBlock>) -> Self {
+        Self {
+            content,
+            structured_content: None,
+            is_error: None,
+            meta: None,
+        }
+    }
+
+    /// Output matching the tool's output schema.
+    #[must_use]
+    pub fn structured_content(
+        mut self,
+        structured_content: impl IntoOption<serde_json::Value>,
+    ) -> Self {
+        self.structured_content = structured_content.into_option();
+        self
+    }
+
+    /// Whether the tool reported an error.
+    #[must_use]
+    pub fn is_error(mut self, is_error: impl IntoOption<bool>) -> Self {
+        self.is_error = is_error.into_option();
+        self
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Parameters for the MCP `resources/read` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpReadResourceRequest {
+    /// URI of the resource to read.
+    pub uri: String,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpReadResourceRequest {
+    /// Builds [`McpReadResourceRequest`] for `uri`.
+    #[must_use]
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            meta: None,
+        }
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+impl McpRequest for McpReadResourceRequest {
+    const METHOD: &'static str = "resources/read";
+    type Result = McpReadResourceResult;
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Result of the MCP `resources/read` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpReadResourceResult {
+    /// The contents of the resource and any sub-resources.
+    pub contents: Vec<EmbeddedResourceResource>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpReadResourceResult {
+    /// Builds [`McpReadResourceResult`] from resource contents.
+    #[must_use]
+    pub fn new(contents: Vec<EmbeddedResourceResource>) -> Self {
+        Self {
+            contents,
+            meta: None,
+        }
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Parameters for the MCP `prompts/get` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpGetPromptRequest {
+    /// Name of the prompt template.
+    pub name: String,
+    /// Values for the template's arguments.
+    pub arguments: Option<BTreeMap<String, String>>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpGetPromptRequest {
+    /// Builds [`McpGetPromptRequest`] without arguments.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            arguments: None,
+            meta: None,
+        }
+    }
+
+    /// Values for the template's arguments.
+    #[must_use]
+    pub fn arguments(mut self, arguments: impl IntoOption<BTreeMap<String, String>>) -> Self {
+        self.arguments = arguments.into_option();
+        self
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+impl McpRequest for McpGetPromptRequest {
+    const METHOD: &'static str = "prompts/get";
+    type Result = McpGetPromptResult;
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// Result of the MCP `prompts/get` request.
+#[serde_as]
+#[skip_serializing_none]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpGetPromptResult {
+    /// Description of the prompt.
+    pub description: Option<String>,
+    /// The messages the prompt expands to.
+    pub messages: Vec<McpPromptMessage>,
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
+    #[serde(default)]
+    #[serde(rename = "_meta")]
+    pub meta: Option<Meta>,
+}
+
+impl McpGetPromptResult {
+    /// Builds [`McpGetPromptResult`] from the expanded messages.
+    #[must_use]
+    pub fn new(messages: Vec<McpPromptMessage>) -> Self {
+        Self {
+            description: None,
+            messages,
+            meta: None,
+        }
+    }
+
+    /// Description of the prompt.
+    #[must_use]
+    pub fn description(mut self, description: impl IntoOption<String>) -> Self {
+        self.description = description.into_option();
+        self
+    }
+
+    /// Metadata reserved by MCP for protocol-level extensions.
+    #[must_use]
+    pub fn meta(mut self, meta: impl IntoOption<Meta>) -> Self {
+        self.meta = meta.into_option();
+        self
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// A message produced by an MCP prompt.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct McpPromptMessage {
+    /// Who the message is attributed to.
+    pub role: Role,
+    /// The message content.
+    pub content: ContentBlock,
+}
+
+impl McpPromptMessage {
+    /// Builds [`McpPromptMessage`].
+    #[must_use]
+    pub fn new(role: Role, content: impl Into<ContentBlock>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::v1::{TextContent, TextResourceContents};
+
+    #[test]
+    fn call_tool_round_trips_through_message() {
+        let arguments = json!({ "path": "README.md" }).as_object().cloned();
+        let message = McpCallToolRequest::new("read_file")
+            .arguments(arguments)
+            .into_message("conn-1")
+            .unwrap();
+        assert_eq!(message.method, "tools/call");
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "connectionId": "conn-1",
+                "method": "tools/call",
+                "params": { "name": "read_file", "arguments": { "path": "README.md" } }
+            })
+        );
+
+        let Some(McpCoreRequest::CallTool(request)) =
+            McpCoreRequest::from_message(&message).unwrap()
+        else {
+            panic!("expected tools/call");
+        };
+        assert_eq!(request.name, "read_file");
+        assert!(matches!(
+            McpListToolsRequest::from_message(&message),
+            Err(McpPayloadError::MethodMismatch {
+                expected: "tools/list",
+                ..
+            })
+        ));
+
+        let response = McpCallToolRequest::result_into_response(&McpCallToolResult::new(vec![
+            ContentBlock::Text(TextContent::new("Readme")),
+        ]))
+        .unwrap();
+        assert_eq!(
+            response.0.get(),
+            r#"{"content":[{"type":"text","text":"Readme"}]}"#
+        );
+        let result = McpCallToolRequest::result_from_response(&response).unwrap();
+        assert_eq!(result.content, [ContentBlock::from("Readme")]);
+    }
+
+    #[test]
+    fn parses_server_results() {
+        let response = MessageMcpResponse::new(
+            serde_json::value::to_raw_value(&json!({
+                "tools": [{
+                    "name": "search",
+                    "inputSchema": { "type": "object" },
+                    "annotations": { "readOnlyHint": true }
+                }],
+                "nextCursor": "page-2"
+            }))
+            .unwrap()
+            .into(),
+        );
+        let result = McpListToolsRequest::result_from_response(&response).unwrap();
+        assert_eq!(result.tools[0].name, "search");
+        assert_eq!(result.next_cursor.as_deref(), Some("page-2"));
+
+        let response = McpReadResourceRequest::result_from_response(&MessageMcpResponse::new(
+            serde_json::value::to_raw_value(&json!({
+                "contents": [{ "uri": "file:///a.txt", "mimeType": "text/plain", "text": "a" }]
+            }))
+            .unwrap()
+            .into(),
+        ))
+        .unwrap();
+        assert_eq!(
+            response.contents,
+            [EmbeddedResourceResource::TextResourceContents(
+                TextResourceContents::new("a", "file:///a.txt").mime_type("text/plain")
+            )]
+        );
+
+        let message = McpListToolsRequest::new().into_message("conn-1").unwrap();
+        assert_eq!(message.params, None);
+        assert_eq!(
+            McpCoreRequest::from_message(&MessageMcpRequest::new("c", "ping")).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,452 @@
+//! Client-side tracking of agent plans.
+//!
+//! Agents report plans either as a whole-session [`Plan`] that replaces the
+//! previous entry list, or (with `unstable_plan_operations`) as any number of
+//! plans addressed by [`PlanId`]. [`PlanStore`] applies both styles, computes
+//! [`PlanProgress`] and reports a [`PlanDiff`] for every change so a UI can
+//! animate individual entries.
+//!
+//! See protocol docs: [Agent Plan](https://agentclientprotocol.com/protocol/agent-plan)
+
+use super::{Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus, SessionUpdate};
+#[cfg(feature = "unstable_plan_operations")]
+use super::{PlanId, PlanRemoved, PlanUpdate, PlanUpdateContent};
+
+/// Identifies a plan tracked by a [`PlanStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PlanKey {
+    /// The single session plan reported through [`SessionUpdate::Plan`].
+    Session,
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// A plan reported through `plan_update`.
+    #[cfg(feature = "unstable_plan_operations")]
+    Id(PlanId),
+}
+
+/// The latest content of a tracked plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PlanContent {
+    /// Structured plan entries.
+    Entries(Vec<PlanEntry>),
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// A URI pointing to a file containing the plan.
+    #[cfg(feature = "unstable_plan_operations")]
+    File(String),
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// Raw markdown content for the plan.
+    #[cfg(feature = "unstable_plan_operations")]
+    Markdown(String),
+}
+
+impl PlanContent {
+    /// The structured entries, or an empty slice for file and markdown plans.
+    #[must_use]
+    pub fn entries(&self) -> &[PlanEntry] {
+        match self {
+            Self::Entries(entries) => entries,
+            #[cfg(feature = "unstable_plan_operations")]
+            Self::File(_) | Self::Markdown(_) => &[],
+        }
+    }
+}
+
+/// A change produced by applying an update to a [`PlanStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PlanChange {
+    /// A plan was created or its content replaced.
+    Updated {
+        /// The plan that changed.
+        key: PlanKey,
+        /// Entry-level differences. Empty when neither version has structured entries.
+        diff: PlanDiff,
+    },
+    /// A plan was removed.
+    Removed {
+        /// The plan that was removed.
+        key: PlanKey,
+        /// Its content before removal.
+        previous: PlanContent,
+    },
+}
+
+/// All plans known for a session, in the order they were first reported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PlanStore {
+    plans: Vec<(PlanKey, PlanContent)>,
+}
+
+impl PlanStore {
+    /// Builds an empty [`PlanStore`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies any plan-related session update, ignoring all others.
+    pub fn apply(&mut self, update: &SessionUpdate) -> Option<PlanChange> {
+        match update {
+            SessionUpdate::Plan(plan) => Some(self.apply_plan(plan)),
+            #[cfg(feature = "unstable_plan_operations")]
+            SessionUpdate::PlanUpdate(update) => Some(self.apply_plan_update(update)),
+            #[cfg(feature = "unstable_plan_operations")]
+            SessionUpdate::PlanRemoved(removed) => self.apply_plan_removed(removed),
+            _ => None,
+        }
+    }
+
+    /// Replaces the session plan.
+    pub fn apply_plan(&mut self, plan: &Plan) -> PlanChange {
+        self.replace(PlanKey::Session, PlanContent::Entries(plan.entries.clone()))
+    }
+
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// Creates or replaces the plan addressed by a `plan_update`.
+    #[cfg(feature = "unstable_plan_operations")]
+    pub fn apply_plan_update(&mut self, update: &PlanUpdate) -> PlanChange {
+        let (plan_id, content) = match &update.plan {
+            PlanUpdateContent::Items(items) => {
+                (&items.plan_id, PlanContent::Entries(items.entries.clone()))
+            }
+            PlanUpdateContent::File(file) => (&file.plan_id, PlanContent::File(file.uri.clone())),
+            PlanUpdateContent::Markdown(markdown) => (
+                &markdown.plan_id,
+                PlanContent::Markdown(markdown.content.clone()),
+            ),
+        };
+        self.replace(PlanKey::Id(plan_id.clone()), content)
+    }
+
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// Removes the plan addressed by a `plan_removed`.
+    ///
+    /// Returns `None` if the plan was not known.
+    #[cfg(feature = "unstable_plan_operations")]
+    pub fn apply_plan_removed(&mut self, removed: &PlanRemoved) -> Option<PlanChange> {
+        let key = PlanKey::Id(removed.plan_id.clone());
+        let index = self.plans.iter().position(|(known, _)| known == &key)?;
+        let (key, previous) = self.plans.remove(index);
+        Some(PlanChange::Removed { key, previous })
+    }
+
+    /// Looks up a plan.
+    #[must_use]
+    pub fn get(&self, key: &PlanKey) -> Option<&PlanContent> {
+        self.plans
+            .iter()
+            .find(|(known, _)| known == key)
+            .map(|(_, content)| content)
+    }
+
+    /// All plans in the order they were first reported.
+    pub fn plans(&self) -> impl Iterator<Item = (&PlanKey, &PlanContent)> {
+        self.plans.iter().map(|(key, content)| (key, content))
+    }
+
+    /// Combined progress over the entries of every tracked plan.
+    #[must_use]
+    pub fn progress(&self) -> PlanProgress {
+        PlanProgress::of(self.plans.iter().flat_map(|(_, content)| content.entries()))
+    }
+
+    fn replace(&mut self, key: PlanKey, content: PlanContent) -> PlanChange {
+        let diff =
+            if let Some((_, existing)) = self.plans.iter_mut().find(|(known, _)| known == &key) {
+                let diff = PlanDiff::between(existing.entries(), content.entries());
+                *existing = content;
+                diff
+            } else {
+                let diff = PlanDiff::between(&[], content.entries());
+                self.plans.push((key.clone(), content));
+                diff
+            };
+        PlanChange::Updated { key, diff }
+    }
+}
+
+/// Completion statistics for a set of plan entries.
+///
+/// Weighted totals count high priority entries three times, medium priority
+/// entries twice and low priority entries once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PlanProgress {
+    /// Entries that have not started.
+    pub pending: usize,
+    /// Entries being worked on.
+    pub in_progress: usize,
+    /// Entries that are done.
+    pub completed: usize,
+    /// Sum of the priority weights of all entries.
+    pub total_weight: u64,
+    /// Sum of the priority weights of completed entries.
+    pub completed_weight: u64,
+}
+
+impl PlanProgress {
+    /// Computes progress over a set of entries.
+    #[must_use]
+    pub fn of<'a>(entries: impl IntoIterator<Item = &'a PlanEntry>) -> Self {
+        let mut progress = Self::default();
+        for entry in entries {
+            let weight = priority_weight(&entry.priority);
+            progress.total_weight += weight;
+            match entry.status {
+                PlanEntryStatus::Pending => progress.pending += 1,
+                PlanEntryStatus::InProgress => progress.in_progress += 1,
+                PlanEntryStatus::Completed => {
+                    progress.completed += 1;
+                    progress.completed_weight += weight;
+                }
+            }
+        }
+        progress
+    }
+
+    /// Total number of entries.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.pending + self.in_progress + self.completed
+    }
+
+    /// Priority-weighted completion between `0.0` and `1.0`, or `None` when there are no entries.
+    #[must_use]
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "Weights are small enough to be exact in an f64"
+    )]
+    pub fn weighted_fraction(&self) -> Option<f64> {
+        (self.total_weight > 0).then(|| self.completed_weight as f64 / self.total_weight as f64)
+    }
+}
+
+fn priority_weight(priority: &PlanEntryPriority) -> u64 {
+    match priority {
+        PlanEntryPriority::High => 3,
+        PlanEntryPriority::Medium => 2,
+        PlanEntryPriority::Low => 1,
+    }
+}
+
+/// Entry-level differences between two versions of a plan.
+///
+/// Entries carry no identifier, so they are matched by their `content` text,
+/// pairing duplicates in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PlanDiff {
+    /// For each entry of the new plan, the index of the matching entry in the previous plan.
+    pub previous_indices: Vec<Option<usize>>,
+    /// Indices (in the new plan) of entries that did not exist before.
+    pub added: Vec<usize>,
+    /// Indices (in the previous plan) of entries that no longer exist.
+    pub removed: Vec<usize>,
+    /// Indices (in the new plan) of matched entries whose status changed.
+    pub status_changed: Vec<usize>,
+    /// Indices (in the new plan) of matched entries whose priority changed.
+    pub priority_changed: Vec<usize>,
+}
+
+impl PlanDiff {
+    /// Computes the differences between two entry lists.
+    #[must_use]
+    pub fn between(previous: &[PlanEntry], current: &[PlanEntry]) -> Self {
+        let mut diff = Self::default();
+        let mut matched = vec![false; previous.len()];
+
+        for (index, entry) in current.iter().enumerate() {
+            let previous_index = previous
+                .iter()
+                .enumerate()
+                .position(|(old_index, old)| !matched[old_index] && old.content == entry.content);
+            diff.previous_indices.push(previous_index);
+
+            let Some(previous_index) = previous_index else {
+                diff.added.push(index);
+                continue;
+            };
+            matched[previous_index] = true;
+            let old = &previous[previous_index];
+            if old.status != entry.status {
+                diff.status_changed.push(index);
+            }
+            if old.priority != entry.priority {
+                diff.priority_changed.push(index);
+            }
+        }
+
+        diff.removed = matched
+            .iter()
+            .enumerate()
+            .filter(|(_, matched)| !**matched)
+            .map(|(index, _)| index)
+            .collect();
+        diff
+    }
+
+    /// Returns `true` if no entry was added, removed, reordered or changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.status_changed.is_empty()
+            && self.priority_changed.is_empty()
+            && self
+                .previous_indices
+                .iter()
+                .enumerate()
+                .all(|(index, previous)| *previous == Some(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str, priority: PlanEntryPriority, status: PlanEntryStatus) -> PlanEntry {
+        PlanEntry::new(content, priority, status)
+    }
+
+    #[test]
+    fn session_plan_is_replaced_and_diffed() {
+        let mut store = PlanStore::new();
+        let first = Plan::new(vec![
+            entry(
+                "Read code",
+                PlanEntryPriority::High,
+                PlanEntryStatus::Completed,
+            ),
+            entry(
+                "Write fix",
+                PlanEntryPriority::High,
+                PlanEntryStatus::InProgress,
+            ),
+            entry(
+                "Update docs",
+                PlanEntryPriority::Low,
+                PlanEntryStatus::Pending,
+            ),
+        ]);
+        let Some(PlanChange::Updated { key, diff }) =
+            store.apply(&SessionUpdate::Plan(first.clone()))
+        else {
+            panic!("expected plan update");
+        };
+        assert_eq!(key, PlanKey::Session);
+        assert_eq!(diff.added, [0, 1, 2]);
+
+        let second = Plan::new(vec![
+            entry(
+                "Read code",
+                PlanEntryPriority::High,
+                PlanEntryStatus::Completed,
+            ),
+            entry(
+                "Add test",
+                PlanEntryPriority::Medium,
+                PlanEntryStatus::Pending,
+            ),
+            entry(
+                "Write fix",
+                PlanEntryPriority::High,
+                PlanEntryStatus::Completed,
+            ),
+        ]);
+        let PlanChange::Updated { diff, .. } = store.apply_plan(&second) else {
+            panic!("expected plan update");
+        };
+        assert_eq!(diff.previous_indices, [Some(0), None, Some(1)]);
+        assert_eq!(diff.added, [1]);
+        assert_eq!(diff.removed, [2]);
+        assert_eq!(diff.status_changed, [2]);
+        assert!(diff.priority_changed.is_empty());
+
+        let PlanChange::Updated { diff, .. } = store.apply_plan(&second) else {
+            panic!("expected plan update");
+        };
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn progress_counts_statuses_and_weights_priorities() {
+        let progress = PlanProgress::of(&[
+            entry("a", PlanEntryPriority::High, PlanEntryStatus::Completed),
+            entry("b", PlanEntryPriority::Medium, PlanEntryStatus::InProgress),
+            entry("c", PlanEntryPriority::Low, PlanEntryStatus::Pending),
+        ]);
+        assert_eq!(
+            (progress.pending, progress.in_progress, progress.completed),
+            (1, 1, 1)
+        );
+        assert_eq!(progress.total(), 3);
+        assert_eq!(progress.weighted_fraction(), Some(0.5));
+        assert_eq!(PlanProgress::default().weighted_fraction(), None);
+    }
+
+    #[cfg(feature = "unstable_plan_operations")]
+    #[test]
+    fn plan_operations_track_multiple_plans() {
+        let mut store = PlanStore::new();
+        store.apply_plan(&Plan::new(vec![entry(
+            "Session task",
+            PlanEntryPriority::Low,
+            PlanEntryStatus::Completed,
+        )]));
+        store.apply(&SessionUpdate::PlanUpdate(PlanUpdate::new(
+            PlanUpdateContent::items(
+                "backend",
+                vec![entry(
+                    "Migrate schema",
+                    PlanEntryPriority::High,
+                    PlanEntryStatus::Pending,
+                )],
+            ),
+        )));
+        store.apply(&SessionUpdate::PlanUpdate(PlanUpdate::new(
+            PlanUpdateContent::markdown("notes", "# Notes"),
+        )));
+
+        assert_eq!(
+            store
+                .plans()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>(),
+            [
+                PlanKey::Session,
+                PlanKey::Id("backend".into()),
+                PlanKey::Id("notes".into())
+            ]
+        );
+        assert_eq!(store.progress().total(), 2);
+        assert_eq!(store.progress().weighted_fraction(), Some(0.25));
+
+        let change = store.apply(&SessionUpdate::PlanRemoved(PlanRemoved::new("notes")));
+        assert_eq!(
+            change,
+            Some(PlanChange::Removed {
+                key: PlanKey::Id("notes".into()),
+                previous: PlanContent::Markdown("# Notes".into()),
+            })
+        );
+        assert_eq!(store.apply_plan_removed(&PlanRemoved::new("notes")), None);
+        assert!(store.get(&PlanKey::Id("backend".into())).is_some());
+    }
+}
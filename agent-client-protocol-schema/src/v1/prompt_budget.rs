@@ -0,0 +1,224 @@
+//! Estimating prompt size and trimming prompts to fit the context window.
+
+use super::{ContentBlock, EmbeddedResourceResource, UsageUpdate};
+use crate::{ContentSize, HeuristicTokenizer, Tokenizer, binary::decoded_len, tokens::len};
+
+impl ContentBlock {
+    /// Estimates the size of this block when sent in a prompt.
+    ///
+    /// Resource links count only their own text; the agent may read the
+    /// linked resource later.
+    #[must_use]
+    pub fn estimate_size(&self, tokenizer: &impl Tokenizer) -> ContentSize {
+        match self {
+            Self::Text(text) => ContentSize::new(len(&text.text), tokenizer.count_text(&text.text)),
+            Self::Image(image) => binary_size(&image.data, &image.mime_type, tokenizer),
+            Self::Audio(audio) => binary_size(&audio.data, &audio.mime_type, tokenizer),
+            Self::ResourceLink(link) => {
+                let text = [
+                    Some(link.name.as_str()),
+                    Some(link.uri.as_str()),
+                    link.title.as_deref(),
+                    link.description.as_deref(),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+                ContentSize::new(len(&text), tokenizer.count_text(&text))
+            }
+            Self::Resource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(text) => ContentSize::new(
+                    len(&text.uri) + len(&text.text),
+                    tokenizer.count_text(&text.uri) + tokenizer.count_text(&text.text),
+                ),
+                EmbeddedResourceResource::BlobResourceContents(blob) => {
+                    let mime_type = blob
+                        .mime_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream");
+                    ContentSize::new(len(&blob.uri), tokenizer.count_text(&blob.uri))
+                        + binary_size(&blob.blob, mime_type, tokenizer)
+                }
+            },
+        }
+    }
+
+    /// The block's `annotations.priority`, from 0 (least) to 1 (most important).
+    #[must_use]
+    pub fn priority(&self) -> Option<f64> {
+        match self {
+            Self::Text(text) => text.annotations.as_ref(),
+            Self::Image(image) => image.annotations.as_ref(),
+            Self::Audio(audio) => audio.annotations.as_ref(),
+            Self::ResourceLink(link) => link.annotations.as_ref(),
+            Self::Resource(resource) => resource.annotations.as_ref(),
+        }
+        .and_then(|annotations| annotations.priority)
+    }
+}
+
+fn binary_size(data: &str, mime_type: &str, tokenizer: &impl Tokenizer) -> ContentSize {
+    let decoded = u64::try_from(decoded_len(data)).unwrap_or(u64::MAX);
+    ContentSize::new(len(data), tokenizer.count_binary(mime_type, decoded))
+}
+
+/// The estimated size of a whole prompt.
+#[must_use]
+pub fn estimate_prompt_size(prompt: &[ContentBlock], tokenizer: &impl Tokenizer) -> ContentSize {
+    prompt
+        .iter()
+        .map(|block| block.estimate_size(tokenizer))
+        .sum()
+}
+
+/// A prompt trimmed by a [`PromptTrimmer`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct TrimmedPrompt {
+    /// The remaining blocks, in their original order.
+    pub prompt: Vec<ContentBlock>,
+    /// Positions in the original prompt of the removed blocks, in removal order.
+    pub removed: Vec<usize>,
+    /// The estimated size of the remaining blocks.
+    pub size: ContentSize,
+    /// Whether the remaining blocks fit the budget.
+    pub fits: bool,
+}
+
+/// Drops the least important blocks of a prompt until it fits a token budget.
+///
+/// Blocks are removed lowest `annotations.priority` first, later blocks
+/// before earlier ones of equal priority. Blocks without a priority, such as
+/// the text the user typed, are never removed, so the result may still be
+/// over budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PromptTrimmer<T = HeuristicTokenizer> {
+    budget: u64,
+    tokenizer: T,
+}
+
+impl PromptTrimmer {
+    /// Trims prompts to `budget` tokens, estimated with [`HeuristicTokenizer`].
+    #[must_use]
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            tokenizer: HeuristicTokenizer::new(),
+        }
+    }
+
+    /// Trims prompts to the context window left according to `usage`.
+    #[must_use]
+    pub fn for_usage(usage: &UsageUpdate) -> Self {
+        Self::new(usage.remaining())
+    }
+}
+
+impl<T: Tokenizer> PromptTrimmer<T> {
+    /// Estimates sizes with `tokenizer` instead.
+    #[must_use]
+    pub fn tokenizer<U: Tokenizer>(self, tokenizer: U) -> PromptTrimmer<U> {
+        PromptTrimmer {
+            budget: self.budget,
+            tokenizer,
+        }
+    }
+
+    /// The token budget prompts are trimmed to.
+    #[must_use]
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Removes blocks from `prompt` until it fits the budget or only
+    /// unprioritized blocks remain.
+    #[must_use]
+    pub fn trim(&self, prompt: Vec<ContentBlock>) -> TrimmedPrompt {
+        let sizes: Vec<_> = prompt
+            .iter()
+            .map(|block| block.estimate_size(&self.tokenizer))
+            .collect();
+        let mut size: ContentSize = sizes.iter().copied().sum();
+
+        let mut candidates: Vec<_> = prompt
+            .iter()
+            .enumerate()
+            .filter_map(|(index, block)| Some((block.priority()?, index)))
+            .collect();
+        candidates.sort_by(|(a, a_index), (b, b_index)| {
+            a.total_cmp(b).then_with(|| b_index.cmp(a_index))
+        });
+
+        let mut removed = Vec::new();
+        for (_, index) in candidates {
+            if size.tokens <= self.budget {
+                break;
+            }
+            size = ContentSize::new(
+                size.bytes - sizes[index].bytes,
+                size.tokens - sizes[index].tokens,
+            );
+            removed.push(index);
+        }
+
+        let prompt = prompt
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !removed.contains(index))
+            .map(|(_, block)| block)
+            .collect();
+        TrimmedPrompt {
+            prompt,
+            removed,
+            size,
+            fits: size.tokens <= self.budget,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::{Annotations, ImageContent, TextContent};
+
+    fn text(text: &str, priority: Option<f64>) -> ContentBlock {
+        ContentBlock::Text(
+            TextContent::new(text).annotations(priority.map(|p| Annotations::new().priority(p))),
+        )
+    }
+
+    #[test]
+    fn estimates_text_and_payload_sizes() {
+        let tokenizer = HeuristicTokenizer::new();
+        let prompt = [
+            text("hello world", None),
+            ContentBlock::Image(ImageContent::new("iVBORw0KGgo=", "image/png")),
+        ];
+        assert_eq!(prompt[0].estimate_size(&tokenizer), ContentSize::new(11, 3));
+        assert_eq!(
+            estimate_prompt_size(&prompt, &tokenizer),
+            ContentSize::new(23, 1_603)
+        );
+    }
+
+    #[test]
+    fn trims_lowest_priority_blocks_first() {
+        let prompt = vec![
+            text("what does this do?", None),
+            text(&"a".repeat(400), Some(0.2)),
+            text(&"b".repeat(400), Some(0.9)),
+            text(&"c".repeat(400), Some(0.2)),
+        ];
+        let trimmed = PromptTrimmer::new(150).trim(prompt.clone());
+        assert_eq!(trimmed.removed, [3, 1]);
+        assert_eq!(trimmed.prompt, [prompt[0].clone(), prompt[2].clone()]);
+        assert_eq!(trimmed.size.tokens, 105);
+        assert!(trimmed.fits);
+
+        let trimmed = PromptTrimmer::for_usage(&UsageUpdate::new(1_000, 1_002)).trim(prompt);
+        assert_eq!(trimmed.prompt.len(), 1);
+        assert!(!trimmed.fits);
+    }
+}
@@ -0,0 +1,358 @@
+//! **UNSTABLE**
+//!
+//! This capability is not part of the spec yet, and may be removed or changed at any point.
+//!
+//! Client-side tracking of the providers reported by `providers/list`.
+
+use derive_more::Display;
+
+use super::{
+    DisableProviderRequest, Error, ListProvidersResponse, LlmProtocol, ProviderCurrentConfig,
+    ProviderId, ProviderInfo, SetProviderRequest,
+};
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// The providers of one agent, kept in sync with `providers/*` calls.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ProviderManager {
+    providers: Vec<ProviderInfo>,
+}
+
+impl ProviderManager {
+    /// Builds a manager from a `providers/list` response.
+    #[must_use]
+    pub fn new(response: ListProvidersResponse) -> Self {
+        Self {
+            providers: response.providers,
+        }
+    }
+
+    /// Replaces the tracked providers with a fresh `providers/list` response.
+    pub fn refresh(&mut self, response: ListProvidersResponse) {
+        self.providers = response.providers;
+    }
+
+    /// All tracked providers, in the order the agent listed them.
+    #[must_use]
+    pub fn providers(&self) -> &[ProviderInfo] {
+        &self.providers
+    }
+
+    /// Looks up a provider by id.
+    #[must_use]
+    pub fn get(&self, provider_id: &ProviderId) -> Option<&ProviderInfo> {
+        self.providers
+            .iter()
+            .find(|provider| &provider.provider_id == provider_id)
+    }
+
+    /// Providers that are currently enabled.
+    pub fn enabled(&self) -> impl Iterator<Item = &ProviderInfo> {
+        self.providers
+            .iter()
+            .filter(|provider| provider.current.is_some())
+    }
+
+    /// Checks a `providers/set` request before sending it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProviderError::UnknownProvider`] for an unlisted provider,
+    /// [`ProviderError::UnsupportedProtocol`] when the provider does not list
+    /// the requested `apiType`, and [`ProviderError::MissingBaseUrl`] for an
+    /// empty base URL.
+    pub fn validate_set(&self, request: &SetProviderRequest) -> Result<(), ProviderError> {
+        let provider = self.provider(&request.provider_id)?;
+        if !provider.supported.contains(&request.api_type) {
+            return Err(ProviderError::UnsupportedProtocol {
+                provider_id: request.provider_id.clone(),
+                api_type: request.api_type.clone(),
+            });
+        }
+        if request.base_url.trim().is_empty() {
+            return Err(ProviderError::MissingBaseUrl(request.provider_id.clone()));
+        }
+        Ok(())
+    }
+
+    /// Checks a `providers/disable` request before sending it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProviderError::UnknownProvider`] for an unlisted provider and
+    /// [`ProviderError::Required`] for a provider that cannot be disabled.
+    pub fn validate_disable(&self, request: &DisableProviderRequest) -> Result<(), ProviderError> {
+        let provider = self.provider(&request.provider_id)?;
+        if provider.required {
+            return Err(ProviderError::Required(request.provider_id.clone()));
+        }
+        Ok(())
+    }
+
+    /// Records a successful `providers/set` call.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::validate_set`].
+    pub fn apply_set(&mut self, request: &SetProviderRequest) -> Result<(), ProviderError> {
+        self.validate_set(request)?;
+        let provider = self.provider_mut(&request.provider_id)?;
+        provider.current = Some(ProviderCurrentConfig::new(
+            request.api_type.clone(),
+            request.base_url.clone(),
+        ));
+        Ok(())
+    }
+
+    /// Records a successful `providers/disable` call.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::validate_disable`].
+    pub fn apply_disable(&mut self, request: &DisableProviderRequest) -> Result<(), ProviderError> {
+        self.validate_disable(request)?;
+        self.provider_mut(&request.provider_id)?.current = None;
+        Ok(())
+    }
+
+    /// Builds `providers/set` requests that point each provider at the first
+    /// imported endpoint whose protocol it supports.
+    ///
+    /// Providers without a matching endpoint are left out.
+    #[must_use]
+    pub fn set_requests(&self, endpoints: &[ProviderCurrentConfig]) -> Vec<SetProviderRequest> {
+        self.providers
+            .iter()
+            .filter_map(|provider| {
+                let endpoint = endpoints
+                    .iter()
+                    .find(|endpoint| provider.supported.contains(&endpoint.api_type))?;
+                Some(SetProviderRequest::new(
+                    provider.provider_id.clone(),
+                    endpoint.api_type.clone(),
+                    endpoint.base_url.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Reads provider endpoints from the environment variables commonly used
+    /// by LLM SDKs, through `lookup`.
+    ///
+    /// | Protocol | Base URL | Otherwise, when set |
+    /// |----------|----------|---------------------|
+    /// | Anthropic | `ANTHROPIC_BASE_URL` | `ANTHROPIC_API_KEY` |
+    /// | `OpenAI` | `OPENAI_BASE_URL`, `OPENAI_API_BASE` | `OPENAI_API_KEY` |
+    /// | Azure | `AZURE_OPENAI_ENDPOINT` | `AZURE_OPENAI_RESOURCE_NAME` |
+    /// | Vertex | `ANTHROPIC_VERTEX_BASE_URL` | `CLOUD_ML_REGION` or `GOOGLE_CLOUD_LOCATION`, with `ANTHROPIC_VERTEX_PROJECT_ID` or `GOOGLE_CLOUD_PROJECT` |
+    /// | Bedrock | `ANTHROPIC_BEDROCK_BASE_URL` | `AWS_REGION` or `AWS_DEFAULT_REGION`, with `AWS_PROFILE`, `AWS_ACCESS_KEY_ID` or `AWS_BEARER_TOKEN_BEDROCK` |
+    ///
+    /// Pass `|name| std::env::var(name).ok()` to read the process environment.
+    #[must_use]
+    pub fn endpoints_from_env(
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Vec<ProviderCurrentConfig> {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| lookup(name).filter(|value| !value.is_empty()))
+        };
+
+        let anthropic = var(&["ANTHROPIC_BASE_URL"]).or_else(|| {
+            var(&["ANTHROPIC_API_KEY"]).map(|_| "https://api.anthropic.com".to_string())
+        });
+        let openai = var(&["OPENAI_BASE_URL", "OPENAI_API_BASE"])
+            .or_else(|| var(&["OPENAI_API_KEY"]).map(|_| "https://api.openai.com/v1".to_string()));
+        let azure = var(&["AZURE_OPENAI_ENDPOINT"]).or_else(|| {
+            var(&["AZURE_OPENAI_RESOURCE_NAME"])
+                .map(|resource| format!("https://{resource}.openai.azure.com"))
+        });
+        let vertex = var(&["ANTHROPIC_VERTEX_BASE_URL"]).or_else(|| {
+            var(&["ANTHROPIC_VERTEX_PROJECT_ID", "GOOGLE_CLOUD_PROJECT"])?;
+            let region = var(&["CLOUD_ML_REGION", "GOOGLE_CLOUD_LOCATION"])?;
+            Some(if region == "global" {
+                "https://aiplatform.googleapis.com".to_string()
+            } else {
+                format!("https://{region}-aiplatform.googleapis.com")
+            })
+        });
+        let bedrock = var(&["ANTHROPIC_BEDROCK_BASE_URL"]).or_else(|| {
+            var(&[
+                "AWS_PROFILE",
+                "AWS_ACCESS_KEY_ID",
+                "AWS_BEARER_TOKEN_BEDROCK",
+            ])?;
+            let region = var(&["AWS_REGION", "AWS_DEFAULT_REGION"])?;
+            Some(format!("https://bedrock-runtime.{region}.amazonaws.com"))
+        });
+
+        [
+            (LlmProtocol::Anthropic, anthropic),
+            (LlmProtocol::OpenAi, openai),
+            (LlmProtocol::Azure, azure),
+            (LlmProtocol::Vertex, vertex),
+            (LlmProtocol::Bedrock, bedrock),
+        ]
+        .into_iter()
+        .filter_map(|(api_type, base_url)| Some(ProviderCurrentConfig::new(api_type, base_url?)))
+        .collect()
+    }
+
+    fn provider(&self, provider_id: &ProviderId) -> Result<&ProviderInfo, ProviderError> {
+        self.get(provider_id)
+            .ok_or_else(|| ProviderError::UnknownProvider(provider_id.clone()))
+    }
+
+    fn provider_mut(
+        &mut self,
+        provider_id: &ProviderId,
+    ) -> Result<&mut ProviderInfo, ProviderError> {
+        self.providers
+            .iter_mut()
+            .find(|provider| &provider.provider_id == provider_id)
+            .ok_or_else(|| ProviderError::UnknownProvider(provider_id.clone()))
+    }
+}
+
+/// **UNSTABLE**
+///
+/// This capability is not part of the spec yet, and may be removed or changed at any point.
+///
+/// A `providers/*` call that the tracked provider list rejects.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum ProviderError {
+    /// The provider was not listed by the agent.
+    #[display("unknown provider: {_0}")]
+    UnknownProvider(ProviderId),
+    /// The provider does not support the requested protocol.
+    #[display("provider {provider_id} does not support {api_type:?}")]
+    UnsupportedProtocol {
+        /// The provider being configured.
+        provider_id: ProviderId,
+        /// The rejected protocol.
+        api_type: LlmProtocol,
+    },
+    /// The request has no base URL.
+    #[display("provider {_0} needs a base URL")]
+    MissingBaseUrl(ProviderId),
+    /// The provider is required and cannot be disabled.
+    #[display("provider {_0} is required and cannot be disabled")]
+    Required(ProviderId),
+}
+
+impl std::error::Error for ProviderError {}
+
+impl From<ProviderError> for Error {
+    fn from(error: ProviderError) -> Self {
+        Error::invalid_params().data(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn manager() -> ProviderManager {
+        ProviderManager::new(ListProvidersResponse::new(vec![
+            ProviderInfo::new(
+                "main",
+                vec![LlmProtocol::Anthropic, LlmProtocol::Bedrock],
+                true,
+                ProviderCurrentConfig::new(LlmProtocol::Anthropic, "https://api.anthropic.com"),
+            ),
+            ProviderInfo::new("fast", vec![LlmProtocol::OpenAi], false, None),
+        ]))
+    }
+
+    #[test]
+    fn set_requests_are_checked_against_supported_protocols() {
+        let mut manager = manager();
+        assert_eq!(
+            manager.validate_set(&SetProviderRequest::new(
+                "fast",
+                LlmProtocol::Anthropic,
+                "https://api.anthropic.com"
+            )),
+            Err(ProviderError::UnsupportedProtocol {
+                provider_id: "fast".into(),
+                api_type: LlmProtocol::Anthropic,
+            })
+        );
+        assert_eq!(
+            manager.validate_set(&SetProviderRequest::new("fast", LlmProtocol::OpenAi, " ")),
+            Err(ProviderError::MissingBaseUrl("fast".into()))
+        );
+
+        let set = SetProviderRequest::new("fast", LlmProtocol::OpenAi, "http://localhost:8080/v1");
+        manager.apply_set(&set).unwrap();
+        assert_eq!(manager.enabled().count(), 2);
+        assert_eq!(
+            manager.get(&"fast".into()).unwrap().current,
+            Some(ProviderCurrentConfig::new(
+                LlmProtocol::OpenAi,
+                "http://localhost:8080/v1"
+            ))
+        );
+    }
+
+    #[test]
+    fn required_providers_cannot_be_disabled() {
+        let mut manager = manager();
+        assert_eq!(
+            manager.apply_disable(&DisableProviderRequest::new("main")),
+            Err(ProviderError::Required("main".into()))
+        );
+        assert_eq!(
+            manager.validate_disable(&DisableProviderRequest::new("other")),
+            Err(ProviderError::UnknownProvider("other".into()))
+        );
+        manager
+            .apply_disable(&DisableProviderRequest::new("fast"))
+            .unwrap();
+        assert_eq!(manager.enabled().count(), 1);
+    }
+
+    #[test]
+    fn endpoints_are_imported_from_env_conventions() {
+        let env = HashMap::from([
+            ("ANTHROPIC_BASE_URL", "https://proxy.example.com"),
+            ("OPENAI_API_KEY", "sk-test"),
+            ("AWS_REGION", "us-east-1"),
+            ("AWS_PROFILE", "dev"),
+            ("CLOUD_ML_REGION", "us-east5"),
+        ]);
+        let endpoints =
+            ProviderManager::endpoints_from_env(|name| env.get(name).map(ToString::to_string));
+        assert_eq!(
+            endpoints,
+            [
+                ProviderCurrentConfig::new(LlmProtocol::Anthropic, "https://proxy.example.com"),
+                ProviderCurrentConfig::new(LlmProtocol::OpenAi, "https://api.openai.com/v1"),
+                ProviderCurrentConfig::new(
+                    LlmProtocol::Bedrock,
+                    "https://bedrock-runtime.us-east-1.amazonaws.com"
+                ),
+            ]
+        );
+
+        let requests = manager().set_requests(&endpoints);
+        assert_eq!(
+            requests,
+            [
+                SetProviderRequest::new(
+                    "main",
+                    LlmProtocol::Anthropic,
+                    "https://proxy.example.com"
+                ),
+                SetProviderRequest::new("fast", LlmProtocol::OpenAi, "https://api.openai.com/v1"),
+            ]
+        );
+    }
+}
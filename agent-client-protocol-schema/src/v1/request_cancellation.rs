@@ -0,0 +1,240 @@
+//! Cancelling requests in flight with `$/cancel_request` and `session/cancel`.
+
+use std::collections::{HashMap, hash_map::Entry};
+
+use super::{CancelNotification, CancelRequestNotification, Error, RequestId, Response, SessionId};
+use crate::rpc::CancellationToken;
+
+/// Cancellation state of the requests this side is handling.
+///
+/// Incoming requests are tracked with [`Self::start`] until answered; the
+/// returned token is cancelled when the peer sends `$/cancel_request` for
+/// the request, or `session/cancel` for its session. Requests this side
+/// sends to the peer while handling one, such as the permission and terminal
+/// requests of a prompt turn, are tracked with [`Self::start_nested`] and
+/// cancelled along with it.
+///
+/// Cancelled requests must still be answered, with a result or with
+/// [`Self::cancelled_response`].
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct RequestCancellations {
+    incoming: HashMap<RequestId, IncomingRequest>,
+    /// Ids of nested outgoing requests, mapped to the incoming request they serve.
+    nested: HashMap<RequestId, RequestId>,
+}
+
+#[derive(Debug)]
+struct IncomingRequest {
+    token: CancellationToken,
+    session_id: Option<SessionId>,
+}
+
+/// What a cancel notification cancelled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Cancellation {
+    /// Incoming requests whose tokens were cancelled by this notification.
+    pub cancelled: Vec<RequestId>,
+    /// `$/cancel_request` notifications to send to the peer for the nested
+    /// requests of the cancelled ones.
+    pub forward: Vec<CancelRequestNotification>,
+}
+
+impl Cancellation {
+    /// Returns whether nothing was cancelled, as for unknown or finished ids.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cancelled.is_empty() && self.forward.is_empty()
+    }
+}
+
+impl RequestCancellations {
+    /// Builds an empty [`RequestCancellations`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks an incoming request and returns its cancellation token.
+    ///
+    /// Requests that act on a session, such as `session/prompt` or
+    /// `session/request_permission`, should pass its id so that
+    /// `session/cancel` reaches them.
+    ///
+    /// Returns `None`, leaving the tracked request untouched, if a request
+    /// with the same id is still in flight. The duplicate should be answered
+    /// with [`Error::invalid_request`] without calling [`Self::finish`], which
+    /// would stop tracking the original.
+    pub fn start(
+        &mut self,
+        id: impl Into<RequestId>,
+        session_id: Option<SessionId>,
+    ) -> Option<CancellationToken> {
+        let Entry::Vacant(entry) = self.incoming.entry(id.into()) else {
+            return None;
+        };
+        let token = CancellationToken::new();
+        entry.insert(IncomingRequest {
+            token: token.clone(),
+            session_id,
+        });
+        Some(token)
+    }
+
+    /// Tracks a request sent to the peer while handling the incoming request `parent`.
+    pub fn start_nested(&mut self, id: impl Into<RequestId>, parent: impl Into<RequestId>) {
+        self.nested.insert(id.into(), parent.into());
+    }
+
+    /// Stops tracking an incoming request once it is answered, together
+    /// with its nested requests.
+    pub fn finish(&mut self, id: &RequestId) {
+        if self.incoming.remove(id).is_some() {
+            self.nested.retain(|_, parent| parent != id);
+        }
+    }
+
+    /// Stops tracking a nested request once the peer answered it.
+    pub fn finish_nested(&mut self, id: &RequestId) {
+        self.nested.remove(id);
+    }
+
+    /// The cancellation token of an incoming request, if it is tracked.
+    #[must_use]
+    pub fn token(&self, id: &RequestId) -> Option<&CancellationToken> {
+        self.incoming.get(id).map(|request| &request.token)
+    }
+
+    /// Handles a `$/cancel_request` notification from the peer.
+    ///
+    /// Unknown, finished and already cancelled requests are ignored.
+    pub fn cancel_request(&mut self, notification: &CancelRequestNotification) -> Cancellation {
+        let mut cancellation = Cancellation::default();
+        self.cancel(&notification.request_id, &mut cancellation);
+        cancellation
+    }
+
+    /// Handles a `session/cancel` notification from the peer, cancelling
+    /// every incoming request in the session and their nested requests.
+    pub fn cancel_session(&mut self, notification: &CancelNotification) -> Cancellation {
+        let mut ids: Vec<_> = self
+            .incoming
+            .iter()
+            .filter(|(_, request)| request.session_id.as_ref() == Some(&notification.session_id))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        let mut cancellation = Cancellation::default();
+        for id in &ids {
+            self.cancel(id, &mut cancellation);
+        }
+        cancellation
+    }
+
+    /// Stops tracking an incoming request and builds its `RequestCancelled`
+    /// (-32800) error response.
+    #[must_use]
+    pub fn cancelled_response<R>(&mut self, id: RequestId) -> Response<R> {
+        self.finish(&id);
+        Response::new(id, Err(Error::request_cancelled()))
+    }
+
+    fn cancel(&mut self, id: &RequestId, cancellation: &mut Cancellation) {
+        let Some(request) = self.incoming.get(id) else {
+            return;
+        };
+        if !request.token.cancel() {
+            return;
+        }
+        cancellation.cancelled.push(id.clone());
+        let mut nested: Vec<_> = self
+            .nested
+            .iter()
+            .filter(|(_, parent)| *parent == id)
+            .map(|(nested, _)| nested.clone())
+            .collect();
+        nested.sort();
+        cancellation
+            .forward
+            .extend(nested.into_iter().map(CancelRequestNotification::new));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::ErrorCode;
+
+    #[test]
+    fn cancel_request_cancels_known_requests_once() {
+        let mut cancellations = RequestCancellations::new();
+        let token = cancellations.start(3, None).unwrap();
+
+        let cancellation = cancellations.cancel_request(&CancelRequestNotification::new(3));
+        assert_eq!(cancellation.cancelled, [3.into()]);
+        assert!(token.is_cancelled());
+        assert!(
+            cancellations
+                .cancel_request(&CancelRequestNotification::new(3))
+                .is_empty()
+        );
+        assert!(
+            cancellations
+                .cancel_request(&CancelRequestNotification::new(4))
+                .is_empty()
+        );
+
+        let response: Response<()> = cancellations.cancelled_response(3.into());
+        let Response::Error { id, error } = response else {
+            panic!("expected an error response");
+        };
+        assert_eq!((id, error.code), (3.into(), ErrorCode::RequestCancelled));
+        assert!(
+            cancellations
+                .cancel_request(&CancelRequestNotification::new(3))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn session_cancel_reaches_prompt_turns_and_their_nested_requests() {
+        let mut cancellations = RequestCancellations::new();
+        let prompt = cancellations.start(1, Some("sess-1".into())).unwrap();
+        let other = cancellations.start(2, Some("sess-2".into())).unwrap();
+        cancellations.start_nested(10, 1);
+        cancellations.start_nested(11, 1);
+        cancellations.start_nested(12, 2);
+        cancellations.finish_nested(&11.into());
+
+        let cancellation = cancellations.cancel_session(&CancelNotification::new("sess-1"));
+        assert!(prompt.is_cancelled());
+        assert!(!other.is_cancelled());
+        assert_eq!(cancellation.cancelled, [1.into()]);
+        assert_eq!(cancellation.forward, [CancelRequestNotification::new(10)]);
+
+        cancellations.finish(&2.into());
+        assert!(
+            cancellations
+                .cancel_session(&CancelNotification::new("sess-2"))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_do_not_replace_requests_in_flight() {
+        let mut cancellations = RequestCancellations::new();
+        let token = cancellations.start(5, None).unwrap();
+        cancellations.start_nested(20, 5);
+
+        assert!(cancellations.start(5, Some("sess-1".into())).is_none());
+
+        let cancellation = cancellations.cancel_request(&CancelRequestNotification::new(5));
+        assert!(token.is_cancelled());
+        assert_eq!(cancellation.cancelled, [5.into()]);
+        assert_eq!(cancellation.forward, [CancelRequestNotification::new(20)]);
+
+        cancellations.finish(&5.into());
+        assert!(cancellations.start(5, None).is_some());
+    }
+}
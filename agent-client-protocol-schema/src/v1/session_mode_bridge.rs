@@ -0,0 +1,378 @@
+//! Bridging between legacy session modes and mode config options.
+//!
+//! Older agents expose modes through [`SessionModeState`], `session/set_mode`
+//! and [`CurrentModeUpdate`], while newer agents publish a
+//! [`SessionConfigOption`] with the [`SessionConfigOptionCategory::Mode`]
+//! category. [`SessionModeBridge`] presents whichever form the agent speaks
+//! as either form, so a client UI only has to implement one of them.
+//!
+//! See protocol docs: [Session Modes](https://agentclientprotocol.com/protocol/session-modes)
+
+use super::{
+    CurrentModeUpdate, SessionConfigId, SessionConfigKind, SessionConfigOption,
+    SessionConfigOptionCategory, SessionConfigOptionValue, SessionConfigSelectOption,
+    SessionConfigSelectOptions, SessionId, SessionMode, SessionModeId, SessionModeState,
+    SetSessionConfigOptionRequest, SetSessionModeRequest,
+};
+
+/// Config option id used when a mode option is synthesized from a [`SessionModeState`].
+pub const SESSION_MODE_CONFIG_ID: &str = "mode";
+
+/// Presents an agent's session modes both as a [`SessionModeState`] and as a
+/// mode [`SessionConfigOption`], and routes mode changes to the request the
+/// agent understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionModeBridge {
+    state: SessionModeState,
+    option: SessionConfigOption,
+    source: SessionModeSource,
+}
+
+/// Which mechanism the agent uses to expose its modes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionModeSource {
+    /// The agent sent a [`SessionModeState`] and expects `session/set_mode`.
+    Legacy,
+    /// The agent sent a mode config option and expects `session/set_config_option`.
+    ConfigOption(SessionConfigId),
+}
+
+/// A mode change, expressed as the request the agent understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionModeRequest {
+    /// Send as `session/set_mode`.
+    SetMode(SetSessionModeRequest),
+    /// Send as `session/set_config_option`.
+    SetConfigOption(SetSessionConfigOptionRequest),
+}
+
+impl SessionModeBridge {
+    /// Wraps modes the agent reported through a [`SessionModeState`].
+    #[must_use]
+    pub fn from_legacy(state: SessionModeState) -> Self {
+        Self {
+            option: synthesize_config_option(&state),
+            state,
+            source: SessionModeSource::Legacy,
+        }
+    }
+
+    /// Wraps the first select option in the [`SessionConfigOptionCategory::Mode`] category.
+    ///
+    /// Returns `None` if there is no such option.
+    #[must_use]
+    pub fn from_config_options(options: &[SessionConfigOption]) -> Option<Self> {
+        options.iter().find_map(|option| {
+            Some(Self {
+                state: mode_state(option)?,
+                option: option.clone(),
+                source: SessionModeSource::ConfigOption(option.id.clone()),
+            })
+        })
+    }
+
+    /// Picks the mechanism from a session setup response.
+    ///
+    /// A mode config option takes precedence over legacy modes, as
+    /// recommended for agents that send both.
+    #[must_use]
+    pub fn from_session(
+        modes: Option<&SessionModeState>,
+        config_options: Option<&[SessionConfigOption]>,
+    ) -> Option<Self> {
+        config_options
+            .and_then(Self::from_config_options)
+            .or_else(|| modes.cloned().map(Self::from_legacy))
+    }
+
+    /// Which mechanism the agent uses.
+    #[must_use]
+    pub fn source(&self) -> &SessionModeSource {
+        &self.source
+    }
+
+    /// The modes as a legacy [`SessionModeState`].
+    #[must_use]
+    pub fn mode_state(&self) -> &SessionModeState {
+        &self.state
+    }
+
+    /// The currently active mode.
+    #[must_use]
+    pub fn current_mode_id(&self) -> &SessionModeId {
+        &self.state.current_mode_id
+    }
+
+    /// The modes as a mode [`SessionConfigOption`].
+    ///
+    /// Legacy modes are synthesized into a select option with the id
+    /// [`SESSION_MODE_CONFIG_ID`].
+    #[must_use]
+    pub fn config_option(&self) -> &SessionConfigOption {
+        &self.option
+    }
+
+    /// Builds the request that switches to `mode_id`.
+    #[must_use]
+    pub fn set_mode(
+        &self,
+        session_id: impl Into<SessionId>,
+        mode_id: impl Into<SessionModeId>,
+    ) -> SessionModeRequest {
+        let mode_id = mode_id.into();
+        match &self.source {
+            SessionModeSource::Legacy => {
+                SessionModeRequest::SetMode(SetSessionModeRequest::new(session_id, mode_id))
+            }
+            SessionModeSource::ConfigOption(config_id) => {
+                SessionModeRequest::SetConfigOption(SetSessionConfigOptionRequest::new(
+                    session_id,
+                    config_id.clone(),
+                    SessionConfigOptionValue::value_id(mode_id.0),
+                ))
+            }
+        }
+    }
+
+    /// Translates a legacy `session/set_mode` request into the request the agent understands.
+    #[must_use]
+    pub fn translate_set_mode(&self, request: SetSessionModeRequest) -> SessionModeRequest {
+        let meta = request.meta.clone();
+        match self.set_mode(request.session_id, request.mode_id) {
+            SessionModeRequest::SetMode(request) => SessionModeRequest::SetMode(request.meta(meta)),
+            SessionModeRequest::SetConfigOption(request) => {
+                SessionModeRequest::SetConfigOption(request.meta(meta))
+            }
+        }
+    }
+
+    /// Translates a `session/set_config_option` request into the request the agent understands.
+    ///
+    /// # Errors
+    ///
+    /// Returns the original request if it does not target this bridge's mode
+    /// option or does not carry a value id.
+    pub fn translate_set_config_option(
+        &self,
+        request: SetSessionConfigOptionRequest,
+    ) -> Result<SessionModeRequest, SetSessionConfigOptionRequest> {
+        if request.config_id != self.option.id {
+            return Err(request);
+        }
+        match (&self.source, request.value.as_value_id()) {
+            (SessionModeSource::ConfigOption(_), Some(_)) => {
+                Ok(SessionModeRequest::SetConfigOption(request))
+            }
+            (SessionModeSource::Legacy, Some(value)) => Ok(SessionModeRequest::SetMode(
+                SetSessionModeRequest::new(request.session_id.clone(), value.0.clone())
+                    .meta(request.meta.clone()),
+            )),
+            (_, None) => Err(request),
+        }
+    }
+
+    /// Records a `current_mode_update` session notification.
+    pub fn apply_current_mode_update(&mut self, update: &CurrentModeUpdate) {
+        self.state.current_mode_id = update.current_mode_id.clone();
+        if let SessionConfigKind::Select(select) = &mut self.option.kind {
+            select.current_value = update.current_mode_id.0.clone().into();
+        }
+    }
+
+    /// Records a fresh set of config options, such as from a `config_option_update`.
+    ///
+    /// Returns `false` if the options no longer contain this bridge's mode
+    /// option, in which case the previous state is kept. Legacy bridges
+    /// ignore config options and always return `true`.
+    pub fn apply_config_options(&mut self, options: &[SessionConfigOption]) -> bool {
+        let SessionModeSource::ConfigOption(config_id) = &self.source else {
+            return true;
+        };
+        let Some((option, state)) = options
+            .iter()
+            .find(|option| &option.id == config_id)
+            .and_then(|option| Some((option, mode_state(option)?)))
+        else {
+            return false;
+        };
+        self.option = option.clone();
+        self.state = state;
+        true
+    }
+}
+
+impl From<SessionMode> for SessionConfigSelectOption {
+    fn from(mode: SessionMode) -> Self {
+        SessionConfigSelectOption::new(mode.id.0, mode.name)
+            .description(mode.description)
+            .meta(mode.meta)
+    }
+}
+
+impl From<SessionConfigSelectOption> for SessionMode {
+    fn from(option: SessionConfigSelectOption) -> Self {
+        SessionMode::new(option.value.0, option.name)
+            .description(option.description)
+            .meta(option.meta)
+    }
+}
+
+fn synthesize_config_option(state: &SessionModeState) -> SessionConfigOption {
+    let options: Vec<SessionConfigSelectOption> = state
+        .available_modes
+        .iter()
+        .cloned()
+        .map(Into::into)
+        .collect();
+    SessionConfigOption::select(
+        SESSION_MODE_CONFIG_ID,
+        "Mode",
+        state.current_mode_id.0.clone(),
+        options,
+    )
+    .category(SessionConfigOptionCategory::Mode)
+}
+
+/// Reads a mode select option as a [`SessionModeState`].
+fn mode_state(option: &SessionConfigOption) -> Option<SessionModeState> {
+    if option.category != Some(SessionConfigOptionCategory::Mode) {
+        return None;
+    }
+    let SessionConfigKind::Select(select) = &option.kind else {
+        return None;
+    };
+    let available_modes = match &select.options {
+        SessionConfigSelectOptions::Ungrouped(options) => {
+            options.iter().cloned().map(Into::into).collect()
+        }
+        SessionConfigSelectOptions::Grouped(groups) => groups
+            .iter()
+            .flat_map(|group| group.options.iter().cloned())
+            .map(Into::into)
+            .collect(),
+    };
+    Some(SessionModeState::new(
+        select.current_value.0.clone(),
+        available_modes,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy() -> SessionModeState {
+        SessionModeState::new(
+            "ask",
+            vec![
+                SessionMode::new("ask", "Ask").description("Ask before edits"),
+                SessionMode::new("code", "Code"),
+            ],
+        )
+    }
+
+    fn mode_option(current: &'static str) -> SessionConfigOption {
+        SessionConfigOption::select(
+            "agent-mode",
+            "Agent mode",
+            current,
+            vec![
+                SessionConfigSelectOption::new("ask", "Ask").description("Ask before edits"),
+                SessionConfigSelectOption::new("code", "Code"),
+            ],
+        )
+        .category(SessionConfigOptionCategory::Mode)
+    }
+
+    #[test]
+    fn legacy_modes_are_presented_as_config_option() {
+        let mut bridge = SessionModeBridge::from_legacy(legacy());
+
+        let option = bridge.config_option().clone();
+        assert_eq!(option.id, SessionConfigId::new(SESSION_MODE_CONFIG_ID));
+        assert_eq!(option.category, Some(SessionConfigOptionCategory::Mode));
+        assert_eq!(
+            SessionModeBridge::from_config_options(&[option])
+                .unwrap()
+                .mode_state(),
+            &legacy()
+        );
+
+        let request = SetSessionConfigOptionRequest::new("s", SESSION_MODE_CONFIG_ID, "code");
+        assert_eq!(
+            bridge.translate_set_config_option(request),
+            Ok(SessionModeRequest::SetMode(SetSessionModeRequest::new(
+                "s", "code"
+            )))
+        );
+
+        bridge.apply_current_mode_update(&CurrentModeUpdate::new("code"));
+        assert_eq!(bridge.current_mode_id(), &SessionModeId::new("code"));
+        assert!(matches!(
+            &bridge.config_option().kind,
+            SessionConfigKind::Select(select) if select.current_value == "code".into()
+        ));
+    }
+
+    #[test]
+    fn config_option_modes_are_presented_as_legacy_modes() {
+        let mut bridge =
+            SessionModeBridge::from_session(Some(&legacy()), Some(&[mode_option("ask")])).unwrap();
+        assert_eq!(
+            bridge.source(),
+            &SessionModeSource::ConfigOption("agent-mode".into())
+        );
+        assert_eq!(bridge.mode_state(), &legacy());
+
+        assert_eq!(
+            bridge.translate_set_mode(SetSessionModeRequest::new("s", "code")),
+            SessionModeRequest::SetConfigOption(SetSessionConfigOptionRequest::new(
+                "s",
+                "agent-mode",
+                "code"
+            ))
+        );
+
+        assert!(bridge.apply_config_options(&[mode_option("code")]));
+        assert_eq!(bridge.current_mode_id(), &SessionModeId::new("code"));
+        assert!(!bridge.apply_config_options(&[]));
+        assert_eq!(bridge.config_option(), &mode_option("code"));
+    }
+
+    #[test]
+    fn set_mode_meta_survives_translation() {
+        let meta = serde_json::json!({ "traceId": "abc" })
+            .as_object()
+            .unwrap()
+            .clone();
+        let request = SetSessionModeRequest::new("s", "code").meta(meta.clone());
+
+        let bridge = SessionModeBridge::from_legacy(legacy());
+        assert_eq!(
+            bridge.translate_set_mode(request.clone()),
+            SessionModeRequest::SetMode(request.clone())
+        );
+
+        let bridge =
+            SessionModeBridge::from_session(Some(&legacy()), Some(&[mode_option("ask")])).unwrap();
+        assert_eq!(
+            bridge.translate_set_mode(request),
+            SessionModeRequest::SetConfigOption(
+                SetSessionConfigOptionRequest::new("s", "agent-mode", "code").meta(meta)
+            )
+        );
+    }
+
+    #[test]
+    fn unrelated_config_requests_are_returned() {
+        let bridge = SessionModeBridge::from_legacy(legacy());
+        let request = SetSessionConfigOptionRequest::new("s", "model", "opus");
+        assert_eq!(
+            bridge.translate_set_config_option(request.clone()),
+            Err(request)
+        );
+    }
+}
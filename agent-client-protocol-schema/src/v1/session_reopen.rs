@@ -0,0 +1,308 @@
+//! Choosing how to reopen an existing session from the agent's capabilities.
+//!
+//! Agents may support any combination of `session/load` (replays history),
+//! `session/resume` (no replay) and `session/fork`. A [`SessionReopener`]
+//! picks the method that satisfies a [`ReopenIntent`] and builds its request.
+
+use std::path::PathBuf;
+
+use derive_more::Display;
+
+#[cfg(feature = "unstable_session_fork")]
+use super::ForkSessionRequest;
+use super::{
+    AGENT_METHOD_NAMES, AgentCapabilities, ClientRequest, CloseSessionRequest, Error,
+    LoadSessionRequest, McpServer, ResumeSessionRequest, SessionCapabilities, SessionId,
+    SessionInfo,
+};
+
+/// What the user wants from reopening a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[non_exhaustive]
+pub enum ReopenIntent {
+    /// Keep working in the session; replayed history is acceptable but not needed.
+    #[display("continue")]
+    Continue,
+    /// Show the session's history again before continuing.
+    #[display("replay")]
+    Replay,
+    /// Start a new session from the existing one's context, leaving it untouched.
+    #[display("branch")]
+    Branch,
+}
+
+/// The request chosen by a [`SessionReopener`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReopenRequest {
+    /// `session/load`: the agent replays the history as `session/update` notifications.
+    Load(LoadSessionRequest),
+    /// `session/resume`: the agent restores the session without replaying it.
+    Resume(ResumeSessionRequest),
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// `session/fork`: the agent creates a new session from the existing one.
+    #[cfg(feature = "unstable_session_fork")]
+    Fork(ForkSessionRequest),
+}
+
+impl ReopenRequest {
+    /// The method this request is sent with.
+    #[must_use]
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Load(_) => AGENT_METHOD_NAMES.session_load,
+            Self::Resume(_) => AGENT_METHOD_NAMES.session_resume,
+            #[cfg(feature = "unstable_session_fork")]
+            Self::Fork(_) => AGENT_METHOD_NAMES.session_fork,
+        }
+    }
+
+    /// Returns whether the agent will replay the session's history before responding.
+    #[must_use]
+    pub fn replays_history(&self) -> bool {
+        matches!(self, Self::Load(_))
+    }
+
+    /// List of MCP servers to connect to for this session.
+    #[must_use]
+    pub fn mcp_servers(mut self, mcp_servers: Vec<McpServer>) -> Self {
+        match &mut self {
+            Self::Load(request) => request.mcp_servers = mcp_servers,
+            Self::Resume(request) => request.mcp_servers = mcp_servers,
+            #[cfg(feature = "unstable_session_fork")]
+            Self::Fork(request) => request.mcp_servers = mcp_servers,
+        }
+        self
+    }
+
+    /// Additional workspace roots to activate for this session. Each path must be absolute.
+    #[must_use]
+    pub fn additional_directories(mut self, additional_directories: Vec<PathBuf>) -> Self {
+        match &mut self {
+            Self::Load(request) => request.additional_directories = additional_directories,
+            Self::Resume(request) => request.additional_directories = additional_directories,
+            #[cfg(feature = "unstable_session_fork")]
+            Self::Fork(request) => request.additional_directories = additional_directories,
+        }
+        self
+    }
+}
+
+impl From<ReopenRequest> for ClientRequest {
+    fn from(request: ReopenRequest) -> Self {
+        match request {
+            ReopenRequest::Load(request) => Self::LoadSessionRequest(request),
+            ReopenRequest::Resume(request) => Self::ResumeSessionRequest(request),
+            #[cfg(feature = "unstable_session_fork")]
+            ReopenRequest::Fork(request) => Self::ForkSessionRequest(request),
+        }
+    }
+}
+
+/// Picks `session/load`, `session/resume` or `session/fork` for a [`ReopenIntent`]
+/// based on what the agent advertised.
+///
+/// | Intent                       | Preferred        | Fallback       |
+/// |------------------------------|------------------|----------------|
+/// | [`ReopenIntent::Continue`]   | `session/resume` | `session/load` |
+/// | [`ReopenIntent::Replay`]     | `session/load`   | —              |
+/// | [`ReopenIntent::Branch`]     | `session/fork`   | —              |
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionReopener {
+    load_session: bool,
+    session: SessionCapabilities,
+}
+
+impl SessionReopener {
+    /// Builds a reopener from the capabilities in the agent's `initialize` response.
+    #[must_use]
+    pub fn new(capabilities: &AgentCapabilities) -> Self {
+        Self {
+            load_session: capabilities.load_session,
+            session: capabilities.session_capabilities.clone(),
+        }
+    }
+
+    fn resume(&self) -> bool {
+        self.session.resume.is_some()
+    }
+
+    /// Returns whether [`Self::plan`] can satisfy `intent`.
+    #[must_use]
+    pub fn supports(&self, intent: ReopenIntent) -> bool {
+        match intent {
+            ReopenIntent::Continue => self.resume() || self.load_session,
+            ReopenIntent::Replay => self.load_session,
+            #[cfg(feature = "unstable_session_fork")]
+            ReopenIntent::Branch => self.session.fork.is_some(),
+            #[cfg(not(feature = "unstable_session_fork"))]
+            ReopenIntent::Branch => false,
+        }
+    }
+
+    /// Builds the request that reopens `session_id` in `cwd` for `intent`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReopenError::Unsupported`] when the agent advertised no
+    /// method satisfying `intent`.
+    pub fn plan(
+        &self,
+        intent: ReopenIntent,
+        session_id: impl Into<SessionId>,
+        cwd: impl Into<PathBuf>,
+    ) -> Result<ReopenRequest, ReopenError> {
+        let (session_id, cwd) = (session_id.into(), cwd.into());
+        match intent {
+            ReopenIntent::Continue if self.resume() => Ok(ReopenRequest::Resume(
+                ResumeSessionRequest::new(session_id, cwd),
+            )),
+            ReopenIntent::Continue | ReopenIntent::Replay if self.load_session => Ok(
+                ReopenRequest::Load(LoadSessionRequest::new(session_id, cwd)),
+            ),
+            #[cfg(feature = "unstable_session_fork")]
+            ReopenIntent::Branch if self.session.fork.is_some() => Ok(ReopenRequest::Fork(
+                ForkSessionRequest::new(session_id, cwd),
+            )),
+            _ => Err(ReopenError::Unsupported(intent)),
+        }
+    }
+
+    /// Builds the request reopening a session returned by `session/list`,
+    /// keeping its additional workspace roots.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReopenError::Unsupported`] when the agent advertised no
+    /// method satisfying `intent`.
+    pub fn plan_listed(
+        &self,
+        intent: ReopenIntent,
+        session: &SessionInfo,
+    ) -> Result<ReopenRequest, ReopenError> {
+        Ok(self
+            .plan(intent, session.session_id.clone(), session.cwd.clone())?
+            .additional_directories(session.additional_directories.clone()))
+    }
+
+    /// Builds a `session/close` request, or `None` when the agent does not
+    /// support closing sessions and the client should simply stop using it.
+    #[must_use]
+    pub fn close(&self, session_id: impl Into<SessionId>) -> Option<CloseSessionRequest> {
+        self.session
+            .close
+            .is_some()
+            .then(|| CloseSessionRequest::new(session_id))
+    }
+}
+
+/// A [`ReopenIntent`] the agent cannot satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum ReopenError {
+    /// The agent advertised no method for this intent.
+    #[display("agent cannot {_0} sessions")]
+    Unsupported(ReopenIntent),
+}
+
+impl std::error::Error for ReopenError {}
+
+impl From<ReopenError> for Error {
+    fn from(error: ReopenError) -> Self {
+        Error::method_not_found().data(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1::{SessionCloseCapabilities, SessionResumeCapabilities};
+
+    fn capabilities(load: bool, resume: bool) -> AgentCapabilities {
+        AgentCapabilities::new()
+            .load_session(load)
+            .session_capabilities(
+                SessionCapabilities::new()
+                    .resume(resume.then(SessionResumeCapabilities::new))
+                    .close(SessionCloseCapabilities::new()),
+            )
+    }
+
+    #[test]
+    fn continue_prefers_resume_and_falls_back_to_load() {
+        let both = SessionReopener::new(&capabilities(true, true));
+        let request = both.plan(ReopenIntent::Continue, "s", "/repo").unwrap();
+        assert_eq!(request.method(), "session/resume");
+        assert!(!request.replays_history());
+
+        let load_only = SessionReopener::new(&capabilities(true, false));
+        let request = load_only
+            .plan(ReopenIntent::Continue, "s", "/repo")
+            .unwrap();
+        assert_eq!(
+            request,
+            ReopenRequest::Load(LoadSessionRequest::new("s", "/repo"))
+        );
+        assert!(request.replays_history());
+    }
+
+    #[test]
+    fn unsupported_intents_are_reported() {
+        let resume_only = SessionReopener::new(&capabilities(false, true));
+        assert!(!resume_only.supports(ReopenIntent::Replay));
+        assert_eq!(
+            resume_only.plan(ReopenIntent::Replay, "s", "/repo"),
+            Err(ReopenError::Unsupported(ReopenIntent::Replay))
+        );
+        assert_eq!(
+            resume_only.plan(ReopenIntent::Branch, "s", "/repo"),
+            Err(ReopenError::Unsupported(ReopenIntent::Branch))
+        );
+
+        let none = SessionReopener::new(&AgentCapabilities::new());
+        assert!(!none.supports(ReopenIntent::Continue));
+        assert_eq!(none.close("s"), None);
+        assert_eq!(resume_only.close("s"), Some(CloseSessionRequest::new("s")));
+    }
+
+    #[test]
+    fn listed_sessions_keep_their_roots() {
+        let reopener = SessionReopener::new(&capabilities(false, true));
+        let session =
+            SessionInfo::new("s", "/repo").additional_directories(vec![PathBuf::from("/lib")]);
+
+        let ClientRequest::ResumeSessionRequest(request) = reopener
+            .plan_listed(ReopenIntent::Continue, &session)
+            .unwrap()
+            .into()
+        else {
+            panic!("expected session/resume");
+        };
+        assert_eq!(
+            request,
+            ResumeSessionRequest::new("s", "/repo")
+                .additional_directories(vec![PathBuf::from("/lib")])
+        );
+    }
+
+    #[cfg(feature = "unstable_session_fork")]
+    #[test]
+    fn branch_uses_fork() {
+        use crate::v1::SessionForkCapabilities;
+
+        let capabilities = capabilities(true, true);
+        let capabilities = capabilities.clone().session_capabilities(
+            capabilities
+                .session_capabilities
+                .fork(SessionForkCapabilities::new()),
+        );
+        let request = SessionReopener::new(&capabilities)
+            .plan(ReopenIntent::Branch, "s", "/repo")
+            .unwrap();
+        assert_eq!(request.method(), "session/fork");
+    }
+}
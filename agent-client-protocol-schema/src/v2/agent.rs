@@ -18,7 +18,7 @@ use super::{
     AbsolutePath, ClientCapabilities, ContentBlock, ExtNotification, ExtRequest, ExtResponse, Meta,
     SessionId,
 };
-use crate::{IntoOption, ProtocolVersion, SkipListener};
+use crate::{IntoOption, ProtocolVersion, SkipListener, Timestamp};
 
 #[cfg(feature = "unstable_mcp_over_acp")]
 use super::mcp::{
@@ -1884,7 +1884,7 @@ pub struct SessionInfo {
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true, "format" = "date-time")))]
     #[serde(default)]
-    pub updated_at: Option<String>,
+    pub updated_at: Option<Timestamp>,
     /// The _meta property is reserved by ACP to allow clients and agents to attach additional
     /// metadata to their interactions. Implementations MUST NOT make assumptions about values at
     /// these keys.
@@ -1931,7 +1931,7 @@ impl SessionInfo {
 
     /// RFC 3339 timestamp of last activity.
     #[must_use]
-    pub fn updated_at(mut self, updated_at: impl IntoOption<String>) -> Self {
+    pub fn updated_at(mut self, updated_at: impl IntoOption<Timestamp>) -> Self {
         self.updated_at = updated_at.into_option();
         self
     }
@@ -0,0 +1,312 @@
+//! Client-side authentication flow around the advertised [`AuthMethod`]s.
+//!
+//! An [`AuthFlow`] starts [`AuthState::Unauthenticated`], moves to
+//! [`AuthState::Choosing`] when a call fails with
+//! [`ErrorCode::AuthRequired`], to [`AuthState::InProgress`] once the user
+//! picks a method, and ends [`AuthState::Authenticated`] or, after `auth/logout`,
+//! [`AuthState::LoggedOut`].
+
+use std::process::Command;
+
+use derive_more::Display;
+
+use super::{
+    AuthMethod, AuthMethodId, ClientCapabilities, Error, ErrorCode, InitializeResponse,
+    LoginAuthRequest, LogoutAuthRequest,
+};
+
+/// Where an [`AuthFlow`] currently stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthState {
+    /// No authentication has been attempted; the agent may not need any.
+    Unauthenticated,
+    /// The agent asked for authentication and the user should pick a method.
+    Choosing,
+    /// The user picked a method and it is being carried out.
+    InProgress(AuthMethodId),
+    /// Authentication with the given method succeeded.
+    Authenticated(AuthMethodId),
+    /// The client logged out with `auth/logout`.
+    LoggedOut,
+}
+
+/// What the client must do to carry out a chosen [`AuthMethod`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthStep {
+    /// Send this request to the agent.
+    Login(LoginAuthRequest),
+    /// Run the agent interactively in a terminal.
+    Terminal(TerminalAuthLaunch),
+}
+
+/// The interactive agent invocation for an [`AuthMethod::Terminal`] method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TerminalAuthLaunch {
+    /// The method being carried out.
+    pub method_id: AuthMethodId,
+    /// Arguments appended to the configured agent invocation.
+    pub args: Vec<String>,
+    /// Environment variables overriding the configured agent invocation, in order.
+    pub env: Vec<(String, String)>,
+}
+
+impl TerminalAuthLaunch {
+    /// Builds the command to run in a terminal from the command used to
+    /// launch the agent, appending [`Self::args`] and applying [`Self::env`].
+    #[must_use]
+    pub fn to_command(&self, agent: &Command) -> Command {
+        let mut command = Command::new(agent.get_program());
+        command.args(agent.get_args()).args(&self.args);
+        for (name, value) in agent.get_envs() {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+        command.envs(self.env.iter().map(|(name, value)| (name, value)));
+        if let Some(dir) = agent.get_current_dir() {
+            command.current_dir(dir);
+        }
+        command
+    }
+}
+
+/// Tracks authentication against one agent connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AuthFlow {
+    methods: Vec<AuthMethod>,
+    logout_supported: bool,
+    state: AuthState,
+}
+
+impl AuthFlow {
+    /// Builds a flow from the agent's `initialize` response, keeping only the
+    /// methods the client advertised support for and understands.
+    #[must_use]
+    pub fn new(response: &InitializeResponse, capabilities: &ClientCapabilities) -> Self {
+        let methods = response
+            .auth_methods
+            .iter()
+            .filter(|method| match method {
+                AuthMethod::Agent(_) => true,
+                AuthMethod::Terminal(_) => capabilities
+                    .auth
+                    .as_ref()
+                    .is_some_and(|auth| auth.terminal.is_some()),
+                AuthMethod::Other(_) => false,
+            })
+            .cloned()
+            .collect();
+        Self {
+            methods,
+            logout_supported: !response.auth_methods.is_empty(),
+            state: AuthState::Unauthenticated,
+        }
+    }
+
+    /// The methods to offer the user.
+    #[must_use]
+    pub fn methods(&self) -> &[AuthMethod] {
+        &self.methods
+    }
+
+    /// The current state.
+    #[must_use]
+    pub fn state(&self) -> &AuthState {
+        &self.state
+    }
+
+    /// Returns whether the last chosen method succeeded.
+    #[must_use]
+    pub fn is_authenticated(&self) -> bool {
+        matches!(self.state, AuthState::Authenticated(_))
+    }
+
+    /// Inspects a failed call and moves to [`AuthState::Choosing`] when the
+    /// agent requires authentication.
+    ///
+    /// Returns `true` when the call should be retried after authenticating.
+    pub fn handle_error(&mut self, error: &Error) -> bool {
+        if error.code != ErrorCode::AuthRequired {
+            return false;
+        }
+        self.state = AuthState::Choosing;
+        true
+    }
+
+    /// Starts authenticating with `method_id` and returns what the client must do.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthFlowError::UnknownMethod`] when the method is not one of
+    /// [`Self::methods`].
+    pub fn choose(&mut self, method_id: &AuthMethodId) -> Result<AuthStep, AuthFlowError> {
+        let method = self
+            .methods
+            .iter()
+            .find(|method| method.method_id() == method_id)
+            .ok_or_else(|| AuthFlowError::UnknownMethod(method_id.clone()))?;
+        let step = match method {
+            AuthMethod::Agent(agent) => {
+                AuthStep::Login(LoginAuthRequest::new(agent.method_id.clone()))
+            }
+            AuthMethod::Terminal(terminal) => AuthStep::Terminal(TerminalAuthLaunch {
+                method_id: terminal.method_id.clone(),
+                args: terminal.args.clone(),
+                env: terminal
+                    .env
+                    .iter()
+                    .map(|variable| (variable.name.clone(), variable.value.clone()))
+                    .collect(),
+            }),
+            AuthMethod::Other(other) => {
+                return Err(AuthFlowError::UnknownMethod(other.method_id.clone()));
+            }
+        };
+        self.state = AuthState::InProgress(method_id.clone());
+        Ok(step)
+    }
+
+    /// Records that the chosen method succeeded: the `auth/login` call
+    /// returned or the terminal process exited successfully.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthFlowError::NotInProgress`] when no method was chosen.
+    pub fn succeeded(&mut self) -> Result<(), AuthFlowError> {
+        let AuthState::InProgress(method_id) = &self.state else {
+            return Err(AuthFlowError::NotInProgress);
+        };
+        self.state = AuthState::Authenticated(method_id.clone());
+        Ok(())
+    }
+
+    /// Records that the chosen method failed, returning to [`AuthState::Choosing`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthFlowError::NotInProgress`] when no method was chosen.
+    pub fn failed(&mut self) -> Result<(), AuthFlowError> {
+        if !matches!(self.state, AuthState::InProgress(_)) {
+            return Err(AuthFlowError::NotInProgress);
+        }
+        self.state = AuthState::Choosing;
+        Ok(())
+    }
+
+    /// Logs out and returns the request to send.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthFlowError::LogoutUnsupported`] when the agent advertised
+    /// no authentication methods, and [`AuthFlowError::NotAuthenticated`] when
+    /// there is no session to end.
+    pub fn logout(&mut self) -> Result<LogoutAuthRequest, AuthFlowError> {
+        if !self.logout_supported {
+            return Err(AuthFlowError::LogoutUnsupported);
+        }
+        if !self.is_authenticated() {
+            return Err(AuthFlowError::NotAuthenticated);
+        }
+        self.state = AuthState::LoggedOut;
+        Ok(LogoutAuthRequest::new())
+    }
+}
+
+/// An [`AuthFlow`] transition that is not allowed.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum AuthFlowError {
+    /// The method was not advertised or the client cannot run it.
+    #[display("unknown auth method: {_0}")]
+    UnknownMethod(AuthMethodId),
+    /// No method is being carried out.
+    #[display("no authentication in progress")]
+    NotInProgress,
+    /// There is no authenticated session.
+    #[display("not authenticated")]
+    NotAuthenticated,
+    /// The agent does not support `auth/logout`.
+    #[display("agent does not support logout")]
+    LogoutUnsupported,
+}
+
+impl std::error::Error for AuthFlowError {}
+
+impl From<AuthFlowError> for Error {
+    fn from(error: AuthFlowError) -> Self {
+        Error::invalid_params().data(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::ProtocolVersion;
+    use crate::v2::{
+        AuthCapabilities, AuthMethodAgent, AuthMethodTerminal, EnvVariable, Implementation,
+        OtherAuthMethod, TerminalAuthCapabilities,
+    };
+
+    fn response() -> InitializeResponse {
+        InitializeResponse::new(ProtocolVersion::V2, Implementation::new("agent", "1.0"))
+            .auth_methods(vec![
+                AuthMethod::Agent(AuthMethodAgent::new("oauth", "Sign in")),
+                AuthMethod::Terminal(
+                    AuthMethodTerminal::new("cli", "Log in from terminal")
+                        .args(vec!["login".to_string()])
+                        .env(vec![EnvVariable::new("MODE", "device")]),
+                ),
+                AuthMethod::Other(OtherAuthMethod::new("_sso", "sso", "SSO", BTreeMap::new())),
+            ])
+    }
+
+    #[test]
+    fn unsupported_and_unknown_methods_are_filtered() {
+        let flow = AuthFlow::new(&response(), &ClientCapabilities::new());
+        assert_eq!(flow.methods().len(), 1);
+
+        let capabilities = ClientCapabilities::new()
+            .auth(AuthCapabilities::new().terminal(TerminalAuthCapabilities::new()));
+        let flow = AuthFlow::new(&response(), &capabilities);
+        assert_eq!(flow.methods().len(), 2);
+    }
+
+    #[test]
+    fn auth_required_error_leads_to_login_and_logout() {
+        let mut flow = AuthFlow::new(&response(), &ClientCapabilities::new());
+        assert!(flow.handle_error(&Error::auth_required()));
+        assert_eq!(
+            flow.choose(&"oauth".into()),
+            Ok(AuthStep::Login(LoginAuthRequest::new("oauth")))
+        );
+        assert_eq!(flow.logout(), Err(AuthFlowError::NotAuthenticated));
+        flow.succeeded().unwrap();
+        assert!(flow.is_authenticated());
+        assert_eq!(flow.logout(), Ok(LogoutAuthRequest::new()));
+        assert_eq!(flow.state(), &AuthState::LoggedOut);
+        assert_eq!(
+            flow.choose(&"sso".into()),
+            Err(AuthFlowError::UnknownMethod("sso".into()))
+        );
+    }
+
+    #[test]
+    fn terminal_step_extends_agent_command() {
+        let capabilities = ClientCapabilities::new()
+            .auth(AuthCapabilities::new().terminal(TerminalAuthCapabilities::new()));
+        let mut flow = AuthFlow::new(&response(), &capabilities);
+        let AuthStep::Terminal(launch) = flow.choose(&"cli".into()).unwrap() else {
+            panic!("expected terminal step");
+        };
+        let command = launch.to_command(Command::new("my-agent").arg("--acp"));
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["--acp", "login"]);
+        assert_eq!(launch.env, [("MODE".to_string(), "device".to_string())]);
+    }
+}
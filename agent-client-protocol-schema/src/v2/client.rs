@@ -24,7 +24,7 @@ use super::{
     CompleteElicitationNotification, CreateElicitationRequest, CreateElicitationResponse,
     ElicitationCapabilities,
 };
-use crate::{IntoMaybeUndefined, IntoOption, MaybeUndefined, SkipListener};
+use crate::{IntoMaybeUndefined, IntoOption, MaybeUndefined, SkipListener, Timestamp};
 
 #[cfg(feature = "unstable_mcp_over_acp")]
 use super::mcp::{
@@ -662,7 +662,7 @@ pub struct SessionInfoUpdate {
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true, "format" = "date-time")))]
     #[serde(default, skip_serializing_if = "MaybeUndefined::is_undefined")]
-    pub updated_at: MaybeUndefined<String>,
+    pub updated_at: MaybeUndefined<Timestamp>,
     /// The _meta property is reserved by ACP to allow clients and agents to attach additional
     /// metadata to their interactions. Omitted means no metadata update; `null` is an
     /// explicit clear signal. Implementations MUST NOT make assumptions about values at these keys.
@@ -694,7 +694,7 @@ impl SessionInfoUpdate {
 
     /// RFC 3339 timestamp of last activity. Set to null to clear.
     #[must_use]
-    pub fn updated_at(mut self, updated_at: impl IntoMaybeUndefined<String>) -> Self {
+    pub fn updated_at(mut self, updated_at: impl IntoMaybeUndefined<Timestamp>) -> Self {
         self.updated_at = updated_at.into_maybe_undefined();
         self
     }
@@ -752,6 +752,12 @@ impl UsageUpdate {
         }
     }
 
+    /// Tokens left in the context window.
+    #[must_use]
+    pub fn remaining(&self) -> u64 {
+        self.size.saturating_sub(self.used)
+    }
+
     /// Cumulative session cost (optional).
     #[must_use]
     pub fn cost(mut self, cost: impl IntoOption<Cost>) -> Self {
@@ -2935,12 +2941,14 @@ mod tests {
         );
         assert_eq!(
             serde_json::from_value::<SessionInfoUpdate>(
-                json!({"title": "title", "updatedAt": "timestamp"})
+                json!({"title": "title", "updatedAt": "2025-01-12T15:00:58Z"})
             )
             .unwrap(),
             SessionInfoUpdate {
                 title: MaybeUndefined::Value("title".to_string()),
-                updated_at: MaybeUndefined::Value("timestamp".to_string()),
+                updated_at: MaybeUndefined::Value(
+                    Timestamp::parse("2025-01-12T15:00:58Z").unwrap()
+                ),
                 meta: MaybeUndefined::Undefined
             }
         );
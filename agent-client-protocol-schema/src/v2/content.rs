@@ -9,7 +9,12 @@
 //!
 //! See: [Content](https://agentclientprotocol.com/protocol/content)
 
-use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use derive_more::{Display, From};
 #[cfg(feature = "schemars")]
@@ -18,7 +23,7 @@ use serde::{Deserialize, Serialize};
 use serde_with::{DefaultOnError, VecSkipError, serde_as, skip_serializing_none};
 
 use super::Meta;
-use crate::{IntoOption, SkipListener};
+use crate::{BinaryData, BinaryDataError, IntoOption, SkipListener, Timestamp, Uri, UriError};
 
 /// An Internet media type identifying the format of protocol content.
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
@@ -273,7 +278,7 @@ pub struct ImageContent {
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
     #[cfg_attr(feature = "schemars", schemars(url))]
     #[serde(default)]
-    pub uri: Option<String>,
+    pub uri: Option<Uri>,
     /// Optional annotations that help clients decide how to display or route this content.
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
@@ -304,6 +309,22 @@ impl ImageContent {
         }
     }
 
+    /// Builds [`ImageContent`] by base64-encoding `data`.
+    #[must_use]
+    pub fn from_bytes(data: impl Into<BinaryData>, mime_type: impl Into<MediaType>) -> Self {
+        Self::new(data.into().encode(), mime_type)
+    }
+
+    /// Decodes `data`, checking it against `mime_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError`] when `data` is not base64 or is
+    /// recognizably not a `mime_type` payload.
+    pub fn decode_data(&self) -> Result<BinaryData, BinaryDataError> {
+        crate::binary::decode_checked(&self.data, Some(self.mime_type.as_ref()))
+    }
+
     /// Sets or clears the optional `annotations` field.
     #[must_use]
     pub fn annotations(mut self, annotations: impl IntoOption<Annotations>) -> Self {
@@ -313,7 +334,7 @@ impl ImageContent {
 
     /// Sets or clears the optional `uri` field.
     #[must_use]
-    pub fn uri(mut self, uri: impl IntoOption<String>) -> Self {
+    pub fn uri(mut self, uri: impl IntoOption<Uri>) -> Self {
         self.uri = uri.into_option();
         self
     }
@@ -372,6 +393,22 @@ impl AudioContent {
         }
     }
 
+    /// Builds [`AudioContent`] by base64-encoding `data`.
+    #[must_use]
+    pub fn from_bytes(data: impl Into<BinaryData>, mime_type: impl Into<MediaType>) -> Self {
+        Self::new(data.into().encode(), mime_type)
+    }
+
+    /// Decodes `data`, checking it against `mime_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError`] when `data` is not base64 or is
+    /// recognizably not a `mime_type` payload.
+    pub fn decode_data(&self) -> Result<BinaryData, BinaryDataError> {
+        crate::binary::decode_checked(&self.data, Some(self.mime_type.as_ref()))
+    }
+
     /// Sets or clears the optional `annotations` field.
     #[must_use]
     pub fn annotations(mut self, annotations: impl IntoOption<Annotations>) -> Self {
@@ -471,7 +508,7 @@ pub struct TextResourceContents {
     pub text: String,
     /// URI associated with this resource or media payload.
     #[cfg_attr(feature = "schemars", schemars(url))]
-    pub uri: String,
+    pub uri: Uri,
     /// MIME type describing the encoded media payload.
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
@@ -492,7 +529,7 @@ pub struct TextResourceContents {
 impl TextResourceContents {
     /// Builds [`TextResourceContents`] with its required content payload; optional annotations and metadata start unset.
     #[must_use]
-    pub fn new(text: impl Into<String>, uri: impl Into<String>) -> Self {
+    pub fn new(text: impl Into<String>, uri: impl Into<Uri>) -> Self {
         Self {
             mime_type: None,
             text: text.into(),
@@ -533,7 +570,7 @@ pub struct BlobResourceContents {
     pub blob: String,
     /// URI associated with this resource or media payload.
     #[cfg_attr(feature = "schemars", schemars(url))]
-    pub uri: String,
+    pub uri: Uri,
     /// MIME type describing the encoded media payload.
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
@@ -554,7 +591,7 @@ pub struct BlobResourceContents {
 impl BlobResourceContents {
     /// Builds [`BlobResourceContents`] with its required content payload; optional annotations and metadata start unset.
     #[must_use]
-    pub fn new(blob: impl Into<String>, uri: impl Into<String>) -> Self {
+    pub fn new(blob: impl Into<String>, uri: impl Into<Uri>) -> Self {
         Self {
             blob: blob.into(),
             mime_type: None,
@@ -563,6 +600,22 @@ impl BlobResourceContents {
         }
     }
 
+    /// Builds [`BlobResourceContents`] by base64-encoding `blob`.
+    #[must_use]
+    pub fn from_bytes(blob: impl Into<BinaryData>, uri: impl Into<Uri>) -> Self {
+        Self::new(blob.into().encode(), uri)
+    }
+
+    /// Decodes `blob`, checking it against `mime_type` when set.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BinaryDataError`] when `blob` is not base64 or is
+    /// recognizably not a `mime_type` payload.
+    pub fn decode_blob(&self) -> Result<BinaryData, BinaryDataError> {
+        crate::binary::decode_checked(&self.blob, self.mime_type.as_ref().map(AsRef::as_ref))
+    }
+
     /// Sets or clears the optional `mimeType` field.
     #[must_use]
     pub fn mime_type(mut self, mime_type: impl IntoOption<MediaType>) -> Self {
@@ -594,7 +647,7 @@ pub struct ResourceLink {
     pub name: String,
     /// URI associated with this resource or media payload.
     #[cfg_attr(feature = "schemars", schemars(url))]
-    pub uri: String,
+    pub uri: Uri,
     /// Optional display title for end-user UI.
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
@@ -640,7 +693,7 @@ pub struct ResourceLink {
 impl ResourceLink {
     /// Builds [`ResourceLink`] with its required content payload; optional annotations and metadata start unset.
     #[must_use]
-    pub fn new(name: impl Into<String>, uri: impl Into<String>) -> Self {
+    pub fn new(name: impl Into<String>, uri: impl Into<Uri>) -> Self {
         Self {
             annotations: None,
             description: None,
@@ -654,6 +707,21 @@ impl ResourceLink {
         }
     }
 
+    /// The local path this link points to, if it is a `file:` URI within
+    /// one of `roots`, usually the session's `cwd` and additional directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UriError`] when the link is not a local file URI or points
+    /// outside every root.
+    pub fn local_path<I, P>(&self, roots: I) -> Result<PathBuf, UriError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.uri.to_file_path_within(roots)
+    }
+
     /// Sets or clears the optional `annotations` field.
     #[must_use]
     pub fn annotations(mut self, annotations: impl IntoOption<Annotations>) -> Self {
@@ -718,7 +786,7 @@ impl ResourceLink {
 pub struct Icon {
     /// A standard URI pointing to an icon resource.
     #[cfg_attr(feature = "schemars", schemars(url))]
-    pub src: String,
+    pub src: Uri,
     /// Optional MIME type override if the source MIME type is missing or generic.
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
@@ -743,7 +811,7 @@ pub struct Icon {
 impl Icon {
     /// Builds [`Icon`] with the required source URI; optional display hints start unset.
     #[must_use]
-    pub fn new(src: impl Into<String>) -> Self {
+    pub fn new(src: impl Into<Uri>) -> Self {
         Self {
             src: src.into(),
             mime_type: None,
@@ -812,7 +880,7 @@ pub struct Annotations {
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true, "format" = "date-time")))]
     #[serde(default)]
-    pub last_modified: Option<String>,
+    pub last_modified: Option<Timestamp>,
     /// Relative importance of this content when clients choose what to surface.
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[cfg_attr(feature = "schemars", schemars(extend("x-deserialize-default-on-error" = true)))]
@@ -847,7 +915,7 @@ impl Annotations {
 
     /// Sets or clears the optional `lastModified` field.
     #[must_use]
-    pub fn last_modified(mut self, last_modified: impl IntoOption<String>) -> Self {
+    pub fn last_modified(mut self, last_modified: impl IntoOption<Timestamp>) -> Self {
         self.last_modified = last_modified.into_option();
         self
     }
@@ -894,6 +962,31 @@ pub enum Role {
 mod tests {
     use super::*;
 
+    #[test]
+    fn binary_payloads_encode_and_check_media_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let image = ImageContent::from_bytes(png.clone(), "image/png");
+        assert_eq!(image.data, "iVBORw0KGgoAAAANSUhEUg==");
+        assert_eq!(image.decode_data().unwrap().as_bytes(), png);
+
+        let mislabeled = ImageContent::new(image.data.clone(), "image/jpeg");
+        assert!(matches!(
+            mislabeled.decode_data(),
+            Err(BinaryDataError::MediaTypeMismatch {
+                detected: "image/png",
+                ..
+            })
+        ));
+        assert!(matches!(
+            AudioContent::new("%%%", "audio/wav").decode_data(),
+            Err(BinaryDataError::InvalidBase64(_))
+        ));
+
+        let blob = BlobResourceContents::from_bytes(png.clone(), "file:///logo.png");
+        assert_eq!(blob.decode_blob().unwrap().as_bytes(), png);
+        assert!(blob.mime_type("audio/mpeg").decode_blob().is_err());
+    }
+
     #[test]
     fn test_text_content_roundtrip() {
         let content = TextContent::new("hello world");
@@ -1093,4 +1186,22 @@ mod tests {
                 .is_none()
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn resource_links_resolve_only_within_roots() {
+        let link = ResourceLink::new("main.rs", "file:///repo/src/main.rs#L1:3");
+        assert_eq!(
+            link.local_path(["/repo"]).unwrap(),
+            PathBuf::from("/repo/src/main.rs")
+        );
+        assert!(matches!(
+            link.local_path(["/other"]),
+            Err(UriError::OutsideRoots(_))
+        ));
+        assert!(matches!(
+            ResourceLink::new("docs", "https://example.com/docs").local_path(["/repo"]),
+            Err(UriError::NotFile(_))
+        ));
+    }
 }
@@ -1,5 +1,5 @@
 //! Extension types and constants for protocol extensibility.
-use derive_more::From;
+use derive_more::{Display, From};
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use std::sync::Arc;
@@ -42,6 +42,20 @@ impl ExtRequest {
             params,
         }
     }
+
+    /// Like [`Self::new`], checking that `method` is an extension method name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::InvalidName`] unless `method` starts with `_`.
+    pub fn try_new(
+        method: impl Into<Arc<str>>,
+        params: Arc<RawValue>,
+    ) -> Result<Self, ExtMethodError> {
+        let method = method.into();
+        check_method_name(&method)?;
+        Ok(Self::new(method, params))
+    }
 }
 
 /// Allows for sending an arbitrary response to an [`ExtRequest`] that is not part of the ACP spec.
@@ -94,4 +108,50 @@ impl ExtNotification {
             params,
         }
     }
+
+    /// Like [`Self::new`], checking that `method` is an extension method name.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExtMethodError::InvalidName`] unless `method` starts with `_`.
+    pub fn try_new(
+        method: impl Into<Arc<str>>,
+        params: Arc<RawValue>,
+    ) -> Result<Self, ExtMethodError> {
+        let method = method.into();
+        check_method_name(&method)?;
+        Ok(Self::new(method, params))
+    }
+}
+
+/// An extension method that cannot be built or registered.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum ExtMethodError {
+    /// The method name does not start with `_`, so it may clash with
+    /// future protocol methods.
+    #[display("extension method names must start with `_`: {_0:?}")]
+    InvalidName(String),
+    /// A handler is already registered for the method.
+    #[display("extension method {_0} is already registered")]
+    AlreadyRegistered(String),
+    /// The parameters or response could not be serialized.
+    #[display("cannot encode extension method {method}: {error}")]
+    Encode {
+        /// The extension method.
+        method: String,
+        /// The serialization error.
+        error: String,
+    },
+}
+
+impl std::error::Error for ExtMethodError {}
+
+/// Checks that `method` is a `_`-prefixed extension method name.
+pub(crate) fn check_method_name(method: &str) -> Result<(), ExtMethodError> {
+    if method.len() > 1 && method.starts_with('_') {
+        Ok(())
+    } else {
+        Err(ExtMethodError::InvalidName(method.to_string()))
+    }
 }
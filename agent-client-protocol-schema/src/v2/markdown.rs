@@ -0,0 +1,615 @@
+//! Rendering session content to Markdown.
+//!
+//! [`MarkdownRenderer`] turns individual plans, tool calls and content blocks
+//! into Markdown, and [`MarkdownTranscript`] accumulates the
+//! [`SessionUpdate`]s of a turn into a summary suitable for posting to chat
+//! or code review tools.
+
+use std::{collections::HashMap, path::Path};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+use super::{
+    ContentBlock, Diff, DiffChangeOperation, EmbeddedResourceResource, MessageId, PlanEntry,
+    PlanEntryStatus, PlanId, PlanUpdateContent, SessionUpdate, Terminal, TerminalId,
+    ToolCallContent, ToolCallId, ToolCallStatus, ToolCallUpdate,
+};
+use crate::{
+    MaybeUndefined,
+    markdown::{code, collapsed, escape_html, fenced, inline, language_hint, link, quoted},
+};
+
+/// How agent thoughts appear in a rendered transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ThoughtStyle {
+    /// Inside a collapsed `<details>` element.
+    #[default]
+    Collapsed,
+    /// As a block quote.
+    Quoted,
+    /// Left out entirely.
+    Hidden,
+}
+
+/// Renders protocol types to Markdown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MarkdownRenderer {
+    /// How agent thoughts are rendered.
+    pub thoughts: ThoughtStyle,
+    terminals: HashMap<TerminalId, TerminalState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TerminalState {
+    command: Option<String>,
+    output: Vec<u8>,
+}
+
+impl MarkdownRenderer {
+    /// Builds a [`MarkdownRenderer`] with collapsed thoughts.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how agent thoughts are rendered.
+    #[must_use]
+    pub fn thoughts(mut self, thoughts: ThoughtStyle) -> Self {
+        self.thoughts = thoughts;
+        self
+    }
+
+    /// Replaces the recorded output of a terminal.
+    ///
+    /// [`MarkdownTranscript`] records terminal output from `terminal_update`
+    /// and `terminal_output_chunk` notifications on its own.
+    pub fn set_terminal_output(
+        &mut self,
+        terminal_id: impl Into<TerminalId>,
+        output: impl Into<String>,
+    ) {
+        self.terminals.entry(terminal_id.into()).or_default().output = output.into().into_bytes();
+    }
+
+    /// Renders plan entries as a checkbox list.
+    #[must_use]
+    pub fn plan_entries(&self, entries: &[PlanEntry]) -> String {
+        entries
+            .iter()
+            .map(|entry| {
+                let content = inline(&entry.content);
+                match &entry.status {
+                    PlanEntryStatus::Completed => format!("- [x] {content}"),
+                    PlanEntryStatus::InProgress => format!("- [ ] {content} _(in progress)_"),
+                    PlanEntryStatus::Pending => format!("- [ ] {content}"),
+                    PlanEntryStatus::Cancelled => format!("- [ ] ~~{content}~~"),
+                    PlanEntryStatus::Other(status) => {
+                        format!("- [ ] {content} _({})_", inline(status))
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Renders plan content of any kind.
+    #[must_use]
+    pub fn plan(&self, plan: &PlanUpdateContent) -> String {
+        match plan {
+            PlanUpdateContent::Items(items) => self.plan_entries(&items.entries),
+            #[cfg(feature = "unstable_plan_operations")]
+            PlanUpdateContent::File(file) => link("Plan", file.uri.as_str()),
+            #[cfg(feature = "unstable_plan_operations")]
+            PlanUpdateContent::Markdown(markdown) => markdown.content.clone(),
+            PlanUpdateContent::Other(other) => {
+                format!("_[unsupported plan: {}]_", inline(&other.type_))
+            }
+        }
+    }
+
+    /// Renders tool call state as its status icon and title followed by its content.
+    ///
+    /// Fields that were never set fall back to client defaults: the tool call
+    /// id stands in for a missing title, and a missing status renders as pending.
+    #[must_use]
+    pub fn tool_call(&self, tool_call: &ToolCallUpdate) -> String {
+        let status = tool_call.status.value().unwrap_or(&ToolCallStatus::Pending);
+        let title = tool_call
+            .title
+            .value()
+            .map_or_else(|| tool_call.tool_call_id.to_string(), Clone::clone);
+        let mut sections = vec![format!("{} **{}**", status_icon(status), inline(&title))];
+        if let Some(content) = tool_call.content.value() {
+            sections.extend(
+                content
+                    .iter()
+                    .map(|content| self.tool_call_content(content)),
+            );
+        }
+        sections.join("\n\n")
+    }
+
+    /// Renders one item of tool call content.
+    #[must_use]
+    pub fn tool_call_content(&self, content: &ToolCallContent) -> String {
+        match content {
+            ToolCallContent::Content(content) => self.content_block(&content.content),
+            ToolCallContent::Diff(diff) => self.diff(diff),
+            ToolCallContent::Terminal(terminal) => self.terminal(terminal),
+            ToolCallContent::Other(other) => {
+                format!(
+                    "_[unsupported tool call content: {}]_",
+                    inline(&other.type_)
+                )
+            }
+        }
+    }
+
+    /// Renders file changes as a fenced `diff` block of the patch text, or as
+    /// a list of changed paths when the agent provided no patch.
+    #[must_use]
+    pub fn diff(&self, diff: &Diff) -> String {
+        if let Some(patch) = &diff.patch {
+            return fenced("diff", &patch.text);
+        }
+        diff.changes
+            .iter()
+            .map(|change| match &change.operation {
+                DiffChangeOperation::Add(change) => format!("- Added {}", display(&change.path)),
+                DiffChangeOperation::Delete(change) => {
+                    format!("- Deleted {}", display(&change.path))
+                }
+                DiffChangeOperation::Modify(change) => {
+                    format!("- Modified {}", display(&change.path))
+                }
+                DiffChangeOperation::Move(change) => format!(
+                    "- Moved {} to {}",
+                    display(&change.old_path),
+                    display(&change.path)
+                ),
+                DiffChangeOperation::Copy(change) => format!(
+                    "- Copied {} to {}",
+                    display(&change.old_path),
+                    display(&change.path)
+                ),
+                DiffChangeOperation::Other(change) => format!("- {}", inline(&change.operation)),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Renders a terminal's command and output as a code block, or a
+    /// placeholder if nothing was recorded for it.
+    #[must_use]
+    pub fn terminal(&self, terminal: &Terminal) -> String {
+        let Some(state) = self.terminals.get(&terminal.terminal_id) else {
+            return format!("_[terminal {}]_", code(&terminal.terminal_id.to_string()));
+        };
+        let output = String::from_utf8_lossy(&state.output);
+        match &state.command {
+            Some(command) => fenced("console", &format!("$ {command}\n{output}")),
+            None => fenced("console", &output),
+        }
+    }
+
+    /// Renders a content block. Binary content is replaced by a placeholder.
+    #[must_use]
+    pub fn content_block(&self, block: &ContentBlock) -> String {
+        match block {
+            ContentBlock::Text(text) => text.text.clone(),
+            ContentBlock::Image(image) => {
+                let label = format!("image: {}", image.mime_type);
+                match &image.uri {
+                    Some(uri) => format!("_{}_", link(&label, uri.as_str())),
+                    None => format!("_[{}]_", inline(&label)),
+                }
+            }
+            ContentBlock::Audio(audio) => {
+                format!("_[audio: {}]_", inline(audio.mime_type.as_ref()))
+            }
+            ContentBlock::ResourceLink(resource) => link(
+                resource.title.as_deref().unwrap_or(&resource.name),
+                resource.uri.as_str(),
+            ),
+            ContentBlock::Resource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(contents) => format!(
+                    "{}\n\n{}",
+                    code(contents.uri.as_str()),
+                    fenced(language_hint(contents.uri.as_str()), &contents.text)
+                ),
+                EmbeddedResourceResource::BlobResourceContents(contents) => {
+                    format!("_[binary resource: {}]_", inline(contents.uri.as_str()))
+                }
+            },
+            ContentBlock::Other(other) => {
+                format!("_[unsupported content: {}]_", inline(&other.type_))
+            }
+        }
+    }
+
+    /// Renders content blocks separated by blank lines.
+    #[must_use]
+    pub fn content_blocks(&self, blocks: &[ContentBlock]) -> String {
+        blocks
+            .iter()
+            .map(|block| self.content_block(block))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Renders an agent thought according to [`MarkdownRenderer::thoughts`].
+    ///
+    /// HTML in the thought is escaped rather than rendered. Returns `None` if
+    /// thoughts are hidden.
+    #[must_use]
+    pub fn thought(&self, blocks: &[ContentBlock]) -> Option<String> {
+        let body = self.content_blocks(blocks);
+        match self.thoughts {
+            ThoughtStyle::Collapsed => Some(collapsed("Thinking", &body)),
+            ThoughtStyle::Quoted => Some(quoted(&escape_html(&body))),
+            ThoughtStyle::Hidden => None,
+        }
+    }
+}
+
+fn status_icon(status: &ToolCallStatus) -> &'static str {
+    match status {
+        ToolCallStatus::Pending => "⏳",
+        ToolCallStatus::InProgress => "🔄",
+        ToolCallStatus::Completed => "✅",
+        ToolCallStatus::Failed => "❌",
+        ToolCallStatus::Cancelled => "🚫",
+        ToolCallStatus::Other(_) => "❔",
+    }
+}
+
+/// A path as an inline code span.
+fn display(path: impl AsRef<std::ffi::OsStr>) -> String {
+    code(&Path::new(&path).display().to_string())
+}
+
+/// Accumulates session updates into a Markdown transcript.
+///
+/// Message chunks and upserts are merged by message id, tool calls are shown
+/// in their latest state at the position they were first reported, plans are
+/// shown with their latest content, and terminal output is recorded for
+/// rendering [`Terminal`] tool call content.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MarkdownTranscript {
+    renderer: MarkdownRenderer,
+    items: Vec<TranscriptItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TranscriptItem {
+    Message(Speaker, MessageId, Vec<ContentBlock>),
+    ToolCall(Box<ToolCallUpdate>),
+    Plan(PlanUpdateContent),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Speaker {
+    User,
+    Agent,
+    Thought,
+}
+
+impl MarkdownTranscript {
+    /// Builds an empty transcript rendered with `renderer`.
+    #[must_use]
+    pub fn new(renderer: MarkdownRenderer) -> Self {
+        Self {
+            renderer,
+            ..Self::default()
+        }
+    }
+
+    /// The renderer, for recording terminal output.
+    pub fn renderer_mut(&mut self) -> &mut MarkdownRenderer {
+        &mut self.renderer
+    }
+
+    /// Adds a session update to the transcript. Updates without renderable
+    /// content are ignored.
+    pub fn push(&mut self, update: &SessionUpdate) {
+        match update {
+            SessionUpdate::UserMessageChunk(chunk) => {
+                self.push_chunk(Speaker::User, &chunk.message_id, &chunk.content);
+            }
+            SessionUpdate::AgentMessageChunk(chunk) => {
+                self.push_chunk(Speaker::Agent, &chunk.message_id, &chunk.content);
+            }
+            SessionUpdate::AgentThoughtChunk(chunk) => {
+                self.push_chunk(Speaker::Thought, &chunk.message_id, &chunk.content);
+            }
+            SessionUpdate::UserMessage(message) => {
+                self.upsert_message(Speaker::User, &message.message_id, &message.content);
+            }
+            SessionUpdate::AgentMessage(message) => {
+                self.upsert_message(Speaker::Agent, &message.message_id, &message.content);
+            }
+            SessionUpdate::AgentThought(message) => {
+                self.upsert_message(Speaker::Thought, &message.message_id, &message.content);
+            }
+            SessionUpdate::ToolCallUpdate(update) => {
+                match self.find_tool_call(&update.tool_call_id) {
+                    Some(existing) => existing.apply_update(update.clone()),
+                    None => self
+                        .items
+                        .push(TranscriptItem::ToolCall(Box::new(update.clone()))),
+                }
+            }
+            SessionUpdate::ToolCallContentChunk(chunk) => {
+                let content = chunk.content.clone();
+                match self.find_tool_call(&chunk.tool_call_id) {
+                    Some(existing) => match &mut existing.content {
+                        MaybeUndefined::Value(items) => items.push(content),
+                        other => *other = MaybeUndefined::Value(vec![content]),
+                    },
+                    None => self.items.push(TranscriptItem::ToolCall(Box::new(
+                        ToolCallUpdate::new(chunk.tool_call_id.clone()).content(vec![content]),
+                    ))),
+                }
+            }
+            SessionUpdate::TerminalUpdate(update) => {
+                let state = self
+                    .renderer
+                    .terminals
+                    .entry(update.terminal_id.clone())
+                    .or_default();
+                update.command.clone().update_to(&mut state.command);
+                match &update.output {
+                    MaybeUndefined::Value(output) => {
+                        state.output = STANDARD.decode(&output.data).unwrap_or_default();
+                    }
+                    MaybeUndefined::Null => state.output.clear(),
+                    MaybeUndefined::Undefined => {}
+                }
+            }
+            SessionUpdate::TerminalOutputChunk(chunk) => {
+                if let Ok(data) = STANDARD.decode(&chunk.data) {
+                    self.renderer
+                        .terminals
+                        .entry(chunk.terminal_id.clone())
+                        .or_default()
+                        .output
+                        .extend(data);
+                }
+            }
+            SessionUpdate::PlanUpdate(update) => {
+                let id = plan_id(&update.plan);
+                match self.items.iter_mut().find_map(|item| match item {
+                    TranscriptItem::Plan(plan) if plan_id(plan) == id => Some(plan),
+                    _ => None,
+                }) {
+                    Some(existing) => *existing = update.plan.clone(),
+                    None => self.items.push(TranscriptItem::Plan(update.plan.clone())),
+                }
+            }
+            #[cfg(feature = "unstable_plan_operations")]
+            SessionUpdate::PlanRemoved(removed) => {
+                self.items.retain(|item| {
+                    !matches!(item, TranscriptItem::Plan(plan) if plan_id(plan) == &removed.plan_id)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders the transcript.
+    #[must_use]
+    pub fn render(&self) -> String {
+        self.items
+            .iter()
+            .filter_map(|item| match item {
+                TranscriptItem::Message(Speaker::User, _, blocks) => Some(format!(
+                    "**User**\n\n{}",
+                    self.renderer.content_blocks(blocks)
+                )),
+                TranscriptItem::Message(Speaker::Agent, _, blocks) => Some(format!(
+                    "**Agent**\n\n{}",
+                    self.renderer.content_blocks(blocks)
+                )),
+                TranscriptItem::Message(Speaker::Thought, _, blocks) => {
+                    self.renderer.thought(blocks)
+                }
+                TranscriptItem::ToolCall(tool_call) => Some(self.renderer.tool_call(tool_call)),
+                TranscriptItem::Plan(plan) => {
+                    Some(format!("**Plan**\n\n{}", self.renderer.plan(plan)))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn push_chunk(&mut self, speaker: Speaker, message_id: &MessageId, block: &ContentBlock) {
+        let Some(blocks) = self.find_message(speaker, message_id) else {
+            self.items.push(TranscriptItem::Message(
+                speaker,
+                message_id.clone(),
+                vec![block.clone()],
+            ));
+            return;
+        };
+        if let (Some(ContentBlock::Text(last)), ContentBlock::Text(text)) =
+            (blocks.last_mut(), block)
+        {
+            last.text.push_str(&text.text);
+        } else {
+            blocks.push(block.clone());
+        }
+    }
+
+    fn upsert_message(
+        &mut self,
+        speaker: Speaker,
+        message_id: &MessageId,
+        content: &MaybeUndefined<Vec<ContentBlock>>,
+    ) {
+        if let Some(blocks) = self.find_message(speaker, message_id) {
+            match content {
+                MaybeUndefined::Value(content) => blocks.clone_from(content),
+                MaybeUndefined::Null => blocks.clear(),
+                MaybeUndefined::Undefined => {}
+            }
+        } else {
+            self.items.push(TranscriptItem::Message(
+                speaker,
+                message_id.clone(),
+                content.value().cloned().unwrap_or_default(),
+            ));
+        }
+    }
+
+    fn find_message(
+        &mut self,
+        speaker: Speaker,
+        message_id: &MessageId,
+    ) -> Option<&mut Vec<ContentBlock>> {
+        self.items.iter_mut().rev().find_map(|item| match item {
+            TranscriptItem::Message(known_speaker, known_id, blocks)
+                if *known_speaker == speaker && known_id == message_id =>
+            {
+                Some(blocks)
+            }
+            _ => None,
+        })
+    }
+
+    fn find_tool_call(&mut self, tool_call_id: &ToolCallId) -> Option<&mut ToolCallUpdate> {
+        self.items.iter_mut().find_map(|item| match item {
+            TranscriptItem::ToolCall(tool_call) if &tool_call.tool_call_id == tool_call_id => {
+                Some(&mut **tool_call)
+            }
+            _ => None,
+        })
+    }
+}
+
+fn plan_id(plan: &PlanUpdateContent) -> &PlanId {
+    match plan {
+        PlanUpdateContent::Items(items) => &items.plan_id,
+        #[cfg(feature = "unstable_plan_operations")]
+        PlanUpdateContent::File(file) => &file.plan_id,
+        #[cfg(feature = "unstable_plan_operations")]
+        PlanUpdateContent::Markdown(markdown) => &markdown.plan_id,
+        PlanUpdateContent::Other(other) => &other.plan_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{
+        AgentMessage, ContentChunk, DiffChange, DiffPathChange, ImageContent, PlanEntryPriority,
+        PlanUpdate, ResourceLink, TerminalOutput, TerminalOutputChunk, TerminalUpdate,
+        ToolCallContentChunk,
+    };
+
+    #[test]
+    fn agent_text_is_escaped() {
+        let renderer = MarkdownRenderer::new();
+        let link = ContentBlock::ResourceLink(
+            ResourceLink::new("a", "file:///a b (1).md").title("see ](x) <b>"),
+        );
+        assert_eq!(
+            renderer.content_block(&link),
+            "[see \\](x) \\<b\\>](<file:///a b (1).md>)"
+        );
+
+        let thought = renderer
+            .thought(&["</details>\n<img src=x>".into()])
+            .unwrap();
+        assert_eq!(
+            thought,
+            "<details>\n<summary>Thinking</summary>\n\n\
+             &lt;/details&gt;\n&lt;img src=x&gt;\n\n</details>"
+        );
+
+        let entries = [PlanEntry::new(
+            "Step\n- [x] forged",
+            PlanEntryPriority::High,
+            PlanEntryStatus::Pending,
+        )];
+        assert_eq!(
+            renderer.plan_entries(&entries),
+            "- [ ] Step - \\[x\\] forged"
+        );
+    }
+
+    #[test]
+    fn renders_diffs_with_and_without_patch() {
+        let renderer = MarkdownRenderer::new();
+        let changes = vec![DiffChange::new(DiffChangeOperation::Modify(
+            DiffPathChange::new("/repo/a.txt"),
+        ))];
+        assert_eq!(
+            renderer.diff(&Diff::new(changes.clone())),
+            "- Modified `/repo/a.txt`"
+        );
+        assert_eq!(
+            renderer.diff(&Diff::patch("-a\n+b\n", changes)),
+            "```diff\n-a\n+b\n```"
+        );
+        assert_eq!(
+            renderer.content_block(&ContentBlock::Image(ImageContent::new("AAAA", "image/png"))),
+            "_[image: image/png]_"
+        );
+    }
+
+    #[test]
+    fn transcript_merges_messages_and_records_terminals() {
+        let mut transcript = MarkdownTranscript::new(MarkdownRenderer::new());
+        transcript.push(&SessionUpdate::AgentThoughtChunk(ContentChunk::new(
+            "Checking".into(),
+            "m1",
+        )));
+        transcript.push(&SessionUpdate::AgentMessageChunk(ContentChunk::new(
+            "Run".into(),
+            "m2",
+        )));
+        transcript.push(&SessionUpdate::ToolCallUpdate(
+            ToolCallUpdate::new("call-1")
+                .title("Run tests")
+                .status(ToolCallStatus::InProgress),
+        ));
+        transcript.push(&SessionUpdate::TerminalUpdate(
+            TerminalUpdate::new("term-1")
+                .command("cargo test")
+                .output(TerminalOutput::new(STANDARD.encode("running\n"))),
+        ));
+        transcript.push(&SessionUpdate::TerminalOutputChunk(
+            TerminalOutputChunk::new("term-1", STANDARD.encode("ok\n")),
+        ));
+        transcript.push(&SessionUpdate::ToolCallContentChunk(
+            ToolCallContentChunk::new("call-1", Terminal::new("term-1")),
+        ));
+        transcript.push(&SessionUpdate::ToolCallUpdate(
+            ToolCallUpdate::new("call-1").status(ToolCallStatus::Completed),
+        ));
+        transcript.push(&SessionUpdate::AgentMessage(
+            AgentMessage::new("m2").content(vec!["All tests pass.".into()]),
+        ));
+        transcript.push(&SessionUpdate::PlanUpdate(PlanUpdate::new(
+            PlanUpdateContent::items(
+                "p",
+                vec![PlanEntry::new(
+                    "Run tests",
+                    PlanEntryPriority::High,
+                    PlanEntryStatus::Cancelled,
+                )],
+            ),
+        )));
+
+        assert_eq!(
+            transcript.render(),
+            "<details>\n<summary>Thinking</summary>\n\nChecking\n\n</details>\n\n\
+             **Agent**\n\nAll tests pass.\n\n\
+             ✅ **Run tests**\n\n```console\n$ cargo test\nrunning\nok\n```\n\n\
+             **Plan**\n\n- [ ] ~~Run tests~~"
+        );
+    }
+}
@@ -10,37 +10,72 @@
 //! [`unstable`]: https://docs.rs/crate/agent-client-protocol-schema/latest/features
 
 mod agent;
+mod auth_flow;
 mod client;
 mod content;
 mod elicitation;
 mod error;
 mod ext;
+mod ext_registry;
+mod file_context;
+mod markdown;
 #[cfg(feature = "unstable_mcp_over_acp")]
 mod mcp;
+#[cfg(feature = "unstable_mcp_over_acp")]
+mod mcp_connection;
+mod mcp_launch;
+#[cfg(feature = "unstable_mcp_payloads")]
+mod mcp_payload;
 #[cfg(feature = "unstable_nes")]
 mod nes;
 mod plan;
+mod prompt_budget;
+mod prompt_downgrade;
 mod protocol_level;
+#[cfg(feature = "unstable_llm_providers")]
+mod provider_manager;
+mod request_cancellation;
+mod response_decode;
 #[cfg(feature = "schemars")]
 pub(crate) mod schema_util;
+mod session_catalog;
+mod session_config;
+mod session_reopen;
 mod terminal;
 mod tool_call;
 
 pub use crate::rpc::{JsonRpcBatch, JsonRpcMessage, Notification, Request, RequestId};
 pub use agent::*;
+pub use auth_flow::*;
 pub use client::*;
 pub use content::*;
 use derive_more::{Display, From};
 pub use elicitation::*;
 pub use error::*;
 pub use ext::*;
+pub use ext_registry::*;
+pub use file_context::*;
+pub use markdown::*;
 #[cfg(feature = "unstable_mcp_over_acp")]
 pub use mcp::*;
+#[cfg(feature = "unstable_mcp_over_acp")]
+pub use mcp_connection::*;
+pub use mcp_launch::*;
+#[cfg(feature = "unstable_mcp_payloads")]
+pub use mcp_payload::*;
 #[cfg(feature = "unstable_nes")]
 pub use nes::*;
 pub use plan::*;
+pub use prompt_budget::*;
+pub use prompt_downgrade::*;
 pub use protocol_level::*;
+#[cfg(feature = "unstable_llm_providers")]
+pub use provider_manager::*;
+pub use request_cancellation::*;
 pub use serde_json::value::RawValue;
+pub use session_catalog::*;
+pub use session_config::*;
+pub use session_reopen::*;
 pub use terminal::*;
 pub use tool_call::*;
 
@@ -0,0 +1,270 @@
+//! Fitting prompt content to the agent's [`PromptCapabilities`].
+//!
+//! Every agent accepts text and resource links; images, audio and embedded
+//! resources need the matching capability. A [`PromptDowngrader`] rewrites a
+//! prompt so the same editor content can be sent to any agent. Custom blocks
+//! are passed through unchanged.
+
+use super::{
+    AgentCapabilities, Annotations, ContentBlock, EmbeddedResource, EmbeddedResourceResource,
+    MediaType, PromptCapabilities, ResourceLink, TextContent,
+};
+use crate::{Uri, binary::decoded_len};
+
+/// What to do with images and audio the agent cannot accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum MediaFallback {
+    /// Replace the block with a short text placeholder such as `[image: image/png]`.
+    #[default]
+    Placeholder,
+    /// Remove the block from the prompt.
+    Drop,
+}
+
+/// A rewrite made by a [`PromptDowngrader`].
+///
+/// Indexes refer to positions in the original prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PromptChange {
+    /// An embedded resource was replaced by a link to its URI.
+    Linked {
+        /// Position of the block in the original prompt.
+        index: usize,
+        /// The URI of the linked resource.
+        uri: Uri,
+    },
+    /// An image or audio block was replaced by a text placeholder.
+    Placeholder {
+        /// Position of the block in the original prompt.
+        index: usize,
+        /// The media type of the replaced content.
+        mime_type: MediaType,
+    },
+    /// An image or audio block was removed.
+    Dropped {
+        /// Position of the block in the original prompt.
+        index: usize,
+        /// The media type of the removed content.
+        mime_type: MediaType,
+    },
+}
+
+impl PromptChange {
+    /// Position of the changed block in the original prompt.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Linked { index, .. }
+            | Self::Placeholder { index, .. }
+            | Self::Dropped { index, .. } => *index,
+        }
+    }
+}
+
+/// A prompt rewritten by a [`PromptDowngrader`], with what was changed.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct DowngradedPrompt {
+    /// The content blocks to send.
+    pub prompt: Vec<ContentBlock>,
+    /// The rewrites made, in prompt order; empty when the prompt was sent as is.
+    pub changes: Vec<PromptChange>,
+}
+
+/// Rewrites prompts to fit what the agent advertised.
+///
+/// | Block                      | Without the capability                    |
+/// |----------------------------|-------------------------------------------|
+/// | [`ContentBlock::Image`]    | placeholder or dropped                    |
+/// | [`ContentBlock::Audio`]    | placeholder or dropped                    |
+/// | [`ContentBlock::Resource`] | [`ContentBlock::ResourceLink`] to its URI |
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PromptDowngrader {
+    capabilities: PromptCapabilities,
+    media_fallback: MediaFallback,
+}
+
+impl PromptDowngrader {
+    /// Builds a downgrader from the capabilities in the agent's `initialize` response.
+    #[must_use]
+    pub fn new(capabilities: &AgentCapabilities) -> Self {
+        Self {
+            capabilities: capabilities
+                .session
+                .as_ref()
+                .and_then(|session| session.prompt.clone())
+                .unwrap_or_default(),
+            media_fallback: MediaFallback::default(),
+        }
+    }
+
+    /// What to do with unsupported images and audio. Defaults to [`MediaFallback::Placeholder`].
+    #[must_use]
+    pub fn media_fallback(mut self, media_fallback: MediaFallback) -> Self {
+        self.media_fallback = media_fallback;
+        self
+    }
+
+    /// Rewrites `prompt` so that every block is supported by the agent.
+    #[must_use]
+    pub fn downgrade(&self, prompt: Vec<ContentBlock>) -> DowngradedPrompt {
+        let mut downgraded = DowngradedPrompt {
+            prompt: Vec::with_capacity(prompt.len()),
+            changes: Vec::new(),
+        };
+        for (index, block) in prompt.into_iter().enumerate() {
+            let (block, change) = match block {
+                ContentBlock::Image(image) if self.capabilities.image.is_none() => {
+                    let label = match &image.uri {
+                        Some(uri) => format!("[image: {}, {uri}]", image.mime_type),
+                        None => format!("[image: {}]", image.mime_type),
+                    };
+                    self.replace_media(index, image.mime_type, label, image.annotations)
+                }
+                ContentBlock::Audio(audio) if self.capabilities.audio.is_none() => {
+                    let label = format!("[audio: {}]", audio.mime_type);
+                    self.replace_media(index, audio.mime_type, label, audio.annotations)
+                }
+                ContentBlock::Resource(resource)
+                    if self.capabilities.embedded_context.is_none() =>
+                {
+                    let link = link_to(resource);
+                    let change = PromptChange::Linked {
+                        index,
+                        uri: link.uri.clone(),
+                    };
+                    (Some(ContentBlock::ResourceLink(link)), Some(change))
+                }
+                block => (Some(block), None),
+            };
+            downgraded.prompt.extend(block);
+            downgraded.changes.extend(change);
+        }
+        downgraded
+    }
+
+    fn replace_media(
+        &self,
+        index: usize,
+        mime_type: MediaType,
+        label: String,
+        annotations: Option<Annotations>,
+    ) -> (Option<ContentBlock>, Option<PromptChange>) {
+        match self.media_fallback {
+            MediaFallback::Placeholder => (
+                Some(ContentBlock::Text(
+                    TextContent::new(label).annotations(annotations),
+                )),
+                Some(PromptChange::Placeholder { index, mime_type }),
+            ),
+            MediaFallback::Drop => (None, Some(PromptChange::Dropped { index, mime_type })),
+        }
+    }
+}
+
+/// A link standing in for an embedded resource, named after the last segment of its URI.
+fn link_to(resource: EmbeddedResource) -> ResourceLink {
+    let (uri, mime_type, size) = match resource.resource {
+        EmbeddedResourceResource::TextResourceContents(text) => {
+            (text.uri, text.mime_type, text.text.len())
+        }
+        EmbeddedResourceResource::BlobResourceContents(blob) => {
+            let size = decoded_len(&blob.blob);
+            (blob.uri, blob.mime_type, size)
+        }
+    };
+    let name = uri
+        .as_str()
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.trim_end_matches('/').rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(uri.as_str())
+        .to_string();
+    ResourceLink::new(name, uri)
+        .mime_type(mime_type)
+        .size(i64::try_from(size).ok())
+        .annotations(resource.annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{
+        AudioContent, ImageContent, PromptAudioCapabilities, PromptEmbeddedContextCapabilities,
+        PromptImageCapabilities, SessionCapabilities, TextResourceContents,
+    };
+
+    fn prompt() -> Vec<ContentBlock> {
+        vec![
+            ContentBlock::Text(TextContent::new("Explain this")),
+            ContentBlock::Image(ImageContent::new("iVBORw0K", "image/png")),
+            ContentBlock::Resource(EmbeddedResource::new(
+                EmbeddedResourceResource::TextResourceContents(
+                    TextResourceContents::new("fn main() {}", "file:///repo/src/main.rs")
+                        .mime_type("text/x-rust"),
+                ),
+            )),
+            ContentBlock::Audio(AudioContent::new("UklGRg==", "audio/wav")),
+        ]
+    }
+
+    #[test]
+    fn capable_agents_get_the_prompt_unchanged() {
+        let capabilities = AgentCapabilities::new().session(
+            SessionCapabilities::new().prompt(
+                PromptCapabilities::new()
+                    .image(PromptImageCapabilities::new())
+                    .audio(PromptAudioCapabilities::new())
+                    .embedded_context(PromptEmbeddedContextCapabilities::new()),
+            ),
+        );
+        let downgraded = PromptDowngrader::new(&capabilities).downgrade(prompt());
+        assert_eq!(downgraded.prompt, prompt());
+        assert!(downgraded.changes.is_empty());
+    }
+
+    #[test]
+    fn resources_become_links_and_media_placeholders() {
+        let downgraded = PromptDowngrader::new(&AgentCapabilities::new()).downgrade(prompt());
+        assert_eq!(
+            downgraded.prompt,
+            vec![
+                ContentBlock::Text(TextContent::new("Explain this")),
+                ContentBlock::Text(TextContent::new("[image: image/png]")),
+                ContentBlock::ResourceLink(
+                    ResourceLink::new("main.rs", "file:///repo/src/main.rs")
+                        .mime_type("text/x-rust")
+                        .size(12)
+                ),
+                ContentBlock::Text(TextContent::new("[audio: audio/wav]")),
+            ]
+        );
+        assert_eq!(
+            downgraded
+                .changes
+                .iter()
+                .map(PromptChange::index)
+                .collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn dropped_media_is_reported() {
+        let downgraded = PromptDowngrader::new(&AgentCapabilities::new())
+            .media_fallback(MediaFallback::Drop)
+            .downgrade(prompt());
+        assert_eq!(downgraded.prompt.len(), 2);
+        assert_eq!(
+            downgraded.changes[0],
+            PromptChange::Dropped {
+                index: 1,
+                mime_type: MediaType::new("image/png")
+            }
+        );
+    }
+}
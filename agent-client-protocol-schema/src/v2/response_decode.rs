@@ -0,0 +1,341 @@
+//! Decoding responses by the method of the request they answer.
+//!
+//! Several responses are empty objects, so deserializing an untagged
+//! [`AgentResponse`] or [`ClientResponse`] yields the first variant that
+//! accepts `{}`. Decoding with the request's method picks the exact variant.
+
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    AGENT_METHOD_NAMES, AgentRequest, AgentResponse, CLIENT_METHOD_NAMES, ClientRequest,
+    ClientResponse, ExtResponse, RawValue,
+};
+use crate::rpc::MethodResponse;
+
+impl AgentResponse {
+    /// Decodes the `result` of a response to a `method` request as that
+    /// method's variant.
+    ///
+    /// Empty results (`null`) decode as the default of empty-shaped
+    /// responses; unknown methods decode as [`Self::ExtMethodResponse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from deserializing `result` as the method's response type.
+    pub fn decode(method: &str, result: &RawValue) -> serde_json::Result<Self> {
+        let names = &AGENT_METHOD_NAMES;
+        Ok(match method {
+            m if m == names.initialize => Self::InitializeResponse(parse(result)?),
+            m if m == names.auth_login => Self::LoginAuthResponse(parse_or_default(result)?),
+            #[cfg(feature = "unstable_llm_providers")]
+            m if m == names.providers_list => Self::ListProvidersResponse(parse(result)?),
+            #[cfg(feature = "unstable_llm_providers")]
+            m if m == names.providers_set => Self::SetProviderResponse(parse_or_default(result)?),
+            #[cfg(feature = "unstable_llm_providers")]
+            m if m == names.providers_disable => {
+                Self::DisableProviderResponse(parse_or_default(result)?)
+            }
+            m if m == names.auth_logout => Self::LogoutAuthResponse(parse_or_default(result)?),
+            m if m == names.session_new => Self::NewSessionResponse(parse(result)?),
+            m if m == names.session_list => Self::ListSessionsResponse(parse(result)?),
+            m if m == names.session_delete => {
+                Self::DeleteSessionResponse(parse_or_default(result)?)
+            }
+            #[cfg(feature = "unstable_session_fork")]
+            m if m == names.session_fork => Self::ForkSessionResponse(parse(result)?),
+            m if m == names.session_resume => {
+                Self::ResumeSessionResponse(parse_or_default(result)?)
+            }
+            m if m == names.session_close => Self::CloseSessionResponse(parse_or_default(result)?),
+            m if m == names.session_set_config_option => {
+                Self::SetSessionConfigOptionResponse(parse(result)?)
+            }
+            m if m == names.session_prompt => Self::PromptResponse(parse(result)?),
+            #[cfg(feature = "unstable_nes")]
+            m if m == names.nes_start => Self::StartNesResponse(parse(result)?),
+            #[cfg(feature = "unstable_nes")]
+            m if m == names.nes_suggest => Self::SuggestNesResponse(parse(result)?),
+            #[cfg(feature = "unstable_nes")]
+            m if m == names.nes_close => Self::CloseNesResponse(parse_or_default(result)?),
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            m if m == names.mcp_message => Self::MessageMcpResponse(parse(result)?),
+            _ => Self::ExtMethodResponse(Box::new(ext(result))),
+        })
+    }
+}
+
+impl MethodResponse for AgentResponse {
+    fn decode_result(method: &str, result: &RawValue) -> serde_json::Result<Self> {
+        Self::decode(method, result)
+    }
+}
+
+impl ClientRequest {
+    /// Decodes the `result` of the agent's response to this request.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from deserializing `result` as this request's response type.
+    pub fn decode_response(&self, result: &RawValue) -> serde_json::Result<AgentResponse> {
+        AgentResponse::decode(self.method(), result)
+    }
+}
+
+impl ClientResponse {
+    /// Decodes the `result` of a response to a `method` request as that
+    /// method's variant.
+    ///
+    /// Empty results (`null`) decode as the default of empty-shaped
+    /// responses; unknown methods decode as [`Self::ExtMethodResponse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from deserializing `result` as the method's response type.
+    pub fn decode(method: &str, result: &RawValue) -> serde_json::Result<Self> {
+        let names = &CLIENT_METHOD_NAMES;
+        Ok(match method {
+            m if m == names.session_request_permission => {
+                Self::RequestPermissionResponse(parse(result)?)
+            }
+            m if m == names.elicitation_create => Self::CreateElicitationResponse(parse(result)?),
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            m if m == names.mcp_connect => Self::ConnectMcpResponse(parse(result)?),
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            m if m == names.mcp_disconnect => {
+                Self::DisconnectMcpResponse(parse_or_default(result)?)
+            }
+            #[cfg(feature = "unstable_mcp_over_acp")]
+            m if m == names.mcp_message => Self::MessageMcpResponse(parse(result)?),
+            _ => Self::ExtMethodResponse(Box::new(ext(result))),
+        })
+    }
+}
+
+impl MethodResponse for ClientResponse {
+    fn decode_result(method: &str, result: &RawValue) -> serde_json::Result<Self> {
+        Self::decode(method, result)
+    }
+}
+
+impl AgentRequest {
+    /// Decodes the `result` of the client's response to this request.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from deserializing `result` as this request's response type.
+    pub fn decode_response(&self, result: &RawValue) -> serde_json::Result<ClientResponse> {
+        ClientResponse::decode(self.method(), result)
+    }
+}
+
+fn parse<T: DeserializeOwned>(result: &RawValue) -> serde_json::Result<T> {
+    serde_json::from_str(result.get())
+}
+
+fn parse_or_default<T: DeserializeOwned + Default>(result: &RawValue) -> serde_json::Result<T> {
+    if result.get().trim() == "null" {
+        Ok(T::default())
+    } else {
+        parse(result)
+    }
+}
+
+fn ext(result: &RawValue) -> ExtResponse {
+    ExtResponse::new(Arc::from(result.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::discriminant;
+
+    use serde_json::value::to_raw_value;
+
+    use crate::{ProtocolVersion, v2::*};
+
+    fn assert_agent_round_trip(method: &str, response: &AgentResponse) {
+        let result = to_raw_value(response).unwrap();
+        let decoded = AgentResponse::decode(method, &result).unwrap();
+        assert_eq!(discriminant(&decoded), discriminant(response), "{method}");
+        assert_eq!(to_raw_value(&decoded).unwrap().get(), result.get());
+    }
+
+    fn assert_client_round_trip(method: &str, response: &ClientResponse) {
+        let result = to_raw_value(response).unwrap();
+        let decoded = ClientResponse::decode(method, &result).unwrap();
+        assert_eq!(discriminant(&decoded), discriminant(response), "{method}");
+        assert_eq!(to_raw_value(&decoded).unwrap().get(), result.get());
+    }
+
+    fn empty_object() -> std::sync::Arc<RawValue> {
+        to_raw_value(&serde_json::json!({})).unwrap().into()
+    }
+
+    #[test]
+    fn agent_responses_round_trip_to_their_own_variant() {
+        let names = &AGENT_METHOD_NAMES;
+        assert_agent_round_trip(
+            names.initialize,
+            &AgentResponse::InitializeResponse(Box::new(InitializeResponse::new(
+                ProtocolVersion::V2,
+                Implementation::new("agent", "1.0.0"),
+            ))),
+        );
+        assert_agent_round_trip(
+            names.auth_login,
+            &AgentResponse::LoginAuthResponse(Box::default()),
+        );
+        assert_agent_round_trip(
+            names.auth_logout,
+            &AgentResponse::LogoutAuthResponse(Box::default()),
+        );
+        assert_agent_round_trip(
+            names.session_new,
+            &AgentResponse::NewSessionResponse(Box::new(NewSessionResponse::new("sess-1"))),
+        );
+        assert_agent_round_trip(
+            names.session_list,
+            &AgentResponse::ListSessionsResponse(Box::new(ListSessionsResponse::new(vec![]))),
+        );
+        assert_agent_round_trip(
+            names.session_delete,
+            &AgentResponse::DeleteSessionResponse(Box::default()),
+        );
+        assert_agent_round_trip(
+            names.session_resume,
+            &AgentResponse::ResumeSessionResponse(Box::default()),
+        );
+        assert_agent_round_trip(
+            names.session_close,
+            &AgentResponse::CloseSessionResponse(Box::default()),
+        );
+        assert_agent_round_trip(
+            names.session_set_config_option,
+            &AgentResponse::SetSessionConfigOptionResponse(Box::new(
+                SetSessionConfigOptionResponse::new(vec![]),
+            )),
+        );
+        assert_agent_round_trip(
+            names.session_prompt,
+            &AgentResponse::PromptResponse(Box::new(PromptResponse::new())),
+        );
+        assert_agent_round_trip(
+            "_zed/index",
+            &AgentResponse::ExtMethodResponse(Box::new(ExtResponse::new(empty_object()))),
+        );
+    }
+
+    #[test]
+    fn unstable_agent_responses_round_trip_to_their_own_variant() {
+        #[cfg(feature = "unstable_llm_providers")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.providers_list,
+            &AgentResponse::ListProvidersResponse(Box::new(ListProvidersResponse::new(vec![]))),
+        );
+        #[cfg(feature = "unstable_llm_providers")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.providers_set,
+            &AgentResponse::SetProviderResponse(Box::default()),
+        );
+        #[cfg(feature = "unstable_llm_providers")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.providers_disable,
+            &AgentResponse::DisableProviderResponse(Box::default()),
+        );
+        #[cfg(feature = "unstable_session_fork")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.session_fork,
+            &AgentResponse::ForkSessionResponse(Box::new(ForkSessionResponse::new("sess-2"))),
+        );
+        #[cfg(feature = "unstable_nes")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.nes_start,
+            &AgentResponse::StartNesResponse(Box::new(StartNesResponse::new("nes-1"))),
+        );
+        #[cfg(feature = "unstable_nes")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.nes_suggest,
+            &AgentResponse::SuggestNesResponse(Box::new(SuggestNesResponse::new(vec![]))),
+        );
+        #[cfg(feature = "unstable_nes")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.nes_close,
+            &AgentResponse::CloseNesResponse(Box::default()),
+        );
+        #[cfg(feature = "unstable_mcp_over_acp")]
+        assert_agent_round_trip(
+            AGENT_METHOD_NAMES.mcp_message,
+            &AgentResponse::MessageMcpResponse(Box::new(MessageMcpResponse::new(
+                to_raw_value(&serde_json::json!({"tools": []}))
+                    .unwrap()
+                    .into(),
+            ))),
+        );
+    }
+
+    #[test]
+    fn client_responses_round_trip_to_their_own_variant() {
+        let names = &CLIENT_METHOD_NAMES;
+        assert_client_round_trip(
+            names.session_request_permission,
+            &ClientResponse::RequestPermissionResponse(Box::new(RequestPermissionResponse::new(
+                RequestPermissionOutcome::Cancelled,
+            ))),
+        );
+        assert_client_round_trip(
+            names.elicitation_create,
+            &ClientResponse::CreateElicitationResponse(Box::new(CreateElicitationResponse::new(
+                ElicitationAction::Decline,
+            ))),
+        );
+        assert_client_round_trip(
+            "_zed/open",
+            &ClientResponse::ExtMethodResponse(Box::new(ExtResponse::new(empty_object()))),
+        );
+    }
+
+    #[test]
+    fn unstable_client_responses_round_trip_to_their_own_variant() {
+        #[cfg(feature = "unstable_mcp_over_acp")]
+        assert_client_round_trip(
+            CLIENT_METHOD_NAMES.mcp_connect,
+            &ClientResponse::ConnectMcpResponse(Box::new(ConnectMcpResponse::new("conn-1"))),
+        );
+        #[cfg(feature = "unstable_mcp_over_acp")]
+        assert_client_round_trip(
+            CLIENT_METHOD_NAMES.mcp_disconnect,
+            &ClientResponse::DisconnectMcpResponse(Box::default()),
+        );
+        #[cfg(feature = "unstable_mcp_over_acp")]
+        assert_client_round_trip(
+            CLIENT_METHOD_NAMES.mcp_message,
+            &ClientResponse::MessageMcpResponse(Box::new(MessageMcpResponse::new(
+                to_raw_value(&serde_json::json!({"content": []}))
+                    .unwrap()
+                    .into(),
+            ))),
+        );
+    }
+
+    #[test]
+    fn empty_results_decode_by_request() {
+        let empty = to_raw_value(&serde_json::json!({})).unwrap();
+        let untagged: AgentResponse = serde_json::from_str(empty.get()).unwrap();
+        assert!(matches!(untagged, AgentResponse::LoginAuthResponse(_)));
+
+        let request =
+            ClientRequest::CloseSessionRequest(Box::new(CloseSessionRequest::new("sess-1")));
+        assert!(matches!(
+            request.decode_response(&empty).unwrap(),
+            AgentResponse::CloseSessionResponse(_)
+        ));
+
+        let null = to_raw_value(&serde_json::Value::Null).unwrap();
+        assert!(matches!(
+            AgentResponse::decode(AGENT_METHOD_NAMES.auth_logout, &null).unwrap(),
+            AgentResponse::LogoutAuthResponse(_)
+        ));
+        assert!(AgentResponse::decode(AGENT_METHOD_NAMES.session_new, &null).is_err());
+    }
+}
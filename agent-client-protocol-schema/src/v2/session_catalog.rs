@@ -0,0 +1,272 @@
+//! Cursor pagination for `session/list`.
+//!
+//! Agents answer [`ListSessionsRequest`]s from any session store with a
+//! [`SessionCatalog`]; clients drain every page with [`SessionPages`].
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+};
+
+use derive_more::Display;
+
+use super::{Error, ListSessionsRequest, ListSessionsResponse, SessionInfo, SessionListCursor};
+use crate::session_catalog::{CursorPosition, decode_cursor, encode_cursor, paginate};
+
+/// Builds paginated [`ListSessionsResponse`]s.
+///
+/// Sessions are listed most recently updated first, by their RFC 3339
+/// `updatedAt`; sessions without a parseable timestamp come last. Cursors
+/// record the last session returned rather than an offset, so sessions added
+/// or removed between calls do not shift later pages. Cursors carry an
+/// HMAC-SHA256 tag keyed by the catalog's secret over the position and the
+/// request's `cwd` filter, so edited or forged cursors and cursors reused with
+/// another filter are rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionCatalog {
+    secret: Vec<u8>,
+    page_size: usize,
+}
+
+impl SessionCatalog {
+    /// Builds a catalog returning up to 50 sessions per page.
+    ///
+    /// Use the same `secret` across agent restarts for cursors to stay valid.
+    #[must_use]
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            page_size: 50,
+        }
+    }
+
+    /// Maximum number of sessions per page; at least one.
+    #[must_use]
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Answers `request` from `sessions`, which may be in any order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SessionCatalogError::InvalidCursor`] when the request's cursor
+    /// was not issued by this catalog for the same `cwd` filter.
+    pub fn list(
+        &self,
+        sessions: impl IntoIterator<Item = SessionInfo>,
+        request: &ListSessionsRequest,
+    ) -> Result<ListSessionsResponse, SessionCatalogError> {
+        let filter = request
+            .cwd
+            .as_ref()
+            .map(|cwd| cwd.0.to_string_lossy())
+            .unwrap_or_default();
+        let after = request
+            .cursor
+            .as_ref()
+            .map(|cursor| {
+                decode_cursor(&self.secret, &filter, cursor.as_ref())
+                    .ok_or_else(|| SessionCatalogError::InvalidCursor(cursor.clone()))
+            })
+            .transpose()?;
+
+        let sessions = sessions
+            .into_iter()
+            .filter(|session| request.cwd.as_ref().is_none_or(|cwd| &session.cwd == cwd))
+            .collect();
+        let (page, next) = paginate(
+            sessions,
+            |session: &SessionInfo| {
+                CursorPosition::new(session.updated_at.as_ref(), &session.session_id.0)
+            },
+            after.as_ref(),
+            self.page_size,
+        );
+        Ok(ListSessionsResponse::new(page).next_cursor(
+            next.map(|next| SessionListCursor::new(encode_cursor(&self.secret, &filter, &next))),
+        ))
+    }
+}
+
+/// A `session/list` cursor that a [`SessionCatalog`] cannot resume from.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum SessionCatalogError {
+    /// The cursor was edited, issued by another catalog, or issued for another `cwd`.
+    #[display("invalid session list cursor: {_0}")]
+    InvalidCursor(SessionListCursor),
+}
+
+impl std::error::Error for SessionCatalogError {}
+
+impl From<SessionCatalogError> for Error {
+    fn from(error: SessionCatalogError) -> Self {
+        Error::invalid_params().data(error.to_string())
+    }
+}
+
+/// Iterates over every session by following `nextCursor`.
+///
+/// `fetch` sends one `session/list` request and returns its response. The
+/// iterator stops after the last page, after the first error, or when the
+/// agent repeats a cursor.
+pub struct SessionPages<F> {
+    fetch: F,
+    next: Option<ListSessionsRequest>,
+    buffer: VecDeque<SessionInfo>,
+    seen: HashSet<SessionListCursor>,
+}
+
+impl<F, E> SessionPages<F>
+where
+    F: FnMut(ListSessionsRequest) -> Result<ListSessionsResponse, E>,
+{
+    /// Starts listing from `request`, usually one without a cursor.
+    pub fn new(request: ListSessionsRequest, fetch: F) -> Self {
+        Self {
+            fetch,
+            next: Some(request),
+            buffer: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl<F, E> Iterator for SessionPages<F>
+where
+    F: FnMut(ListSessionsRequest) -> Result<ListSessionsResponse, E>,
+{
+    type Item = Result<SessionInfo, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(session) = self.buffer.pop_front() {
+                return Some(Ok(session));
+            }
+            let request = self.next.take()?;
+            let response = match (self.fetch)(request.clone()) {
+                Ok(response) => response,
+                Err(error) => return Some(Err(error)),
+            };
+            self.buffer.extend(response.sessions);
+            self.next = response
+                .next_cursor
+                .filter(|cursor| self.seen.insert(cursor.clone()))
+                .map(|cursor| request.cursor(cursor));
+        }
+    }
+}
+
+impl<F> fmt::Debug for SessionPages<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionPages")
+            .field("next", &self.next)
+            .field("buffer", &self.buffer)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+    use crate::{Timestamp, v2::AbsolutePath};
+
+    fn at(timestamp: &str) -> Timestamp {
+        Timestamp::parse(timestamp).unwrap()
+    }
+
+    fn sessions() -> Vec<SessionInfo> {
+        vec![
+            SessionInfo::new("a", "/repo").updated_at(at("2025-01-01T00:00:00Z")),
+            SessionInfo::new("b", "/repo").updated_at(at("2025-01-03T00:00:00+02:00")),
+            SessionInfo::new("c", "/other").updated_at(at("2025-01-02T00:00:00Z")),
+            SessionInfo::new("d", "/repo"),
+            SessionInfo::new("e", "/repo").updated_at(at("2025-01-02T12:00:00Z")),
+        ]
+    }
+
+    fn ids(response: &ListSessionsResponse) -> Vec<&str> {
+        response
+            .sessions
+            .iter()
+            .map(|session| &*session.session_id.0)
+            .collect()
+    }
+
+    #[test]
+    fn pages_follow_recency_and_survive_inserts() {
+        let catalog = SessionCatalog::new("secret").page_size(2);
+        let first = catalog
+            .list(sessions(), &ListSessionsRequest::new())
+            .unwrap();
+        assert_eq!(ids(&first), ["b", "e"]);
+
+        let mut grown = sessions();
+        grown.push(SessionInfo::new("new", "/repo").updated_at(at("2026-01-01T00:00:00Z")));
+        let second = catalog
+            .list(
+                grown,
+                &ListSessionsRequest::new().cursor(first.next_cursor.clone()),
+            )
+            .unwrap();
+        assert_eq!(ids(&second), ["c", "a"]);
+
+        let last = catalog
+            .list(
+                sessions(),
+                &ListSessionsRequest::new().cursor(second.next_cursor),
+            )
+            .unwrap();
+        assert_eq!(ids(&last), ["d"]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn cursors_are_rejected_when_tampered_or_refiltered() {
+        let catalog = SessionCatalog::new("secret").page_size(1);
+        let first = catalog
+            .list(
+                sessions(),
+                &ListSessionsRequest::new().cwd(AbsolutePath::new("/repo")),
+            )
+            .unwrap();
+        assert_eq!(ids(&first), ["b"]);
+        let cursor = first.next_cursor.unwrap();
+
+        let refiltered = ListSessionsRequest::new().cursor(cursor.clone());
+        assert_eq!(
+            catalog.list(sessions(), &refiltered),
+            Err(SessionCatalogError::InvalidCursor(cursor.clone()))
+        );
+        let tampered = ListSessionsRequest::new()
+            .cwd(AbsolutePath::new("/repo"))
+            .cursor(format!("x{}", cursor.as_ref()));
+        assert!(catalog.list(sessions(), &tampered).is_err());
+    }
+
+    #[test]
+    fn client_iterator_drains_all_pages() {
+        let catalog = SessionCatalog::new("secret").page_size(2);
+        let mut calls = 0;
+        let all: Vec<_> = SessionPages::new(ListSessionsRequest::new(), |request| {
+            calls += 1;
+            Ok::<_, Infallible>(catalog.list(sessions(), &request).unwrap())
+        })
+        .map(|session| session.unwrap().session_id.0.to_string())
+        .collect();
+        assert_eq!(all, ["b", "e", "c", "a", "d"]);
+        assert_eq!(calls, 3);
+
+        let looping = SessionPages::new(ListSessionsRequest::new(), |_| {
+            Ok::<_, Infallible>(
+                ListSessionsResponse::new(vec![SessionInfo::new("x", "/repo")]).next_cursor("same"),
+            )
+        });
+        assert_eq!(looping.count(), 2);
+    }
+}
@@ -0,0 +1,471 @@
+//! Client-side tracking of session configuration options.
+//!
+//! Agents always deliver the complete set of [`SessionConfigOption`]s, whether
+//! in a session setup response, a [`SetSessionConfigOptionResponse`], or a
+//! [`ConfigOptionUpdate`]. [`SessionConfigStore`] keeps the latest snapshot,
+//! reports what changed between snapshots, and checks outgoing
+//! [`SetSessionConfigOptionRequest`]s before they are sent.
+//!
+//! See protocol docs: [Session Config Options](https://agentclientprotocol.com/protocol/session-config-options)
+
+use derive_more::Display;
+
+use super::{
+    ConfigOptionUpdate, Error, SessionConfigId, SessionConfigKind, SessionConfigOption,
+    SessionConfigOptionCategory, SessionConfigOptionValue, SessionConfigSelect,
+    SessionConfigSelectOption, SessionConfigSelectOptions, SessionConfigValueId,
+    SetSessionConfigOptionRequest, SetSessionConfigOptionResponse,
+};
+
+/// The latest known configuration options for a single session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionConfigStore {
+    options: Vec<SessionConfigOption>,
+}
+
+impl SessionConfigStore {
+    /// Builds an empty [`SessionConfigStore`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the stored options with a full snapshot and reports what changed.
+    ///
+    /// Pass the `config_options` of a session setup response, a
+    /// [`SetSessionConfigOptionResponse`], or a [`ConfigOptionUpdate`].
+    pub fn apply(&mut self, options: Vec<SessionConfigOption>) -> SessionConfigDiff {
+        let diff = SessionConfigDiff::between(&self.options, &options);
+        self.options = options;
+        diff
+    }
+
+    /// Applies a `config_option_update` session notification.
+    pub fn apply_update(&mut self, update: ConfigOptionUpdate) -> SessionConfigDiff {
+        self.apply(update.config_options)
+    }
+
+    /// Applies the response to a `session/set_config_option` request.
+    pub fn apply_response(
+        &mut self,
+        response: SetSessionConfigOptionResponse,
+    ) -> SessionConfigDiff {
+        self.apply(response.config_options)
+    }
+
+    /// All options in the order the agent sent them.
+    #[must_use]
+    pub fn options(&self) -> &[SessionConfigOption] {
+        &self.options
+    }
+
+    /// Looks up an option by id.
+    #[must_use]
+    pub fn get(&self, id: &SessionConfigId) -> Option<&SessionConfigOption> {
+        self.options.iter().find(|option| &option.config_id == id)
+    }
+
+    /// The current value of an option, in the shape a
+    /// [`SetSessionConfigOptionRequest`] would carry it.
+    ///
+    /// Returns `None` for unknown options and for custom option kinds whose
+    /// value shape is not known.
+    #[must_use]
+    pub fn current_value(&self, id: &SessionConfigId) -> Option<SessionConfigOptionValue> {
+        self.get(id).and_then(current_value)
+    }
+
+    /// Options tagged with the given category, in the order the agent sent them.
+    pub fn by_category<'a>(
+        &'a self,
+        category: &'a SessionConfigOptionCategory,
+    ) -> impl Iterator<Item = &'a SessionConfigOption> + 'a {
+        self.options
+            .iter()
+            .filter(move |option| option.category.as_ref() == Some(category))
+    }
+
+    /// The first session mode selector, if any.
+    #[must_use]
+    pub fn mode(&self) -> Option<&SessionConfigOption> {
+        self.by_category(&SessionConfigOptionCategory::Mode).next()
+    }
+
+    /// The first model selector, if any.
+    #[must_use]
+    pub fn model(&self) -> Option<&SessionConfigOption> {
+        self.by_category(&SessionConfigOptionCategory::Model).next()
+    }
+
+    /// The first thought/reasoning level selector, if any.
+    #[must_use]
+    pub fn thought_level(&self) -> Option<&SessionConfigOption> {
+        self.by_category(&SessionConfigOptionCategory::ThoughtLevel)
+            .next()
+    }
+
+    /// Checks that a request sets a known option to a value its kind accepts.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SessionConfigValueError`] describing why the value would be rejected.
+    pub fn validate(
+        &self,
+        request: &SetSessionConfigOptionRequest,
+    ) -> Result<(), SessionConfigValueError> {
+        let option = self
+            .get(&request.config_id)
+            .ok_or_else(|| SessionConfigValueError::UnknownOption(request.config_id.clone()))?;
+
+        match (&option.kind, &request.value) {
+            (SessionConfigKind::Select(select), SessionConfigOptionValue::Id { value }) => {
+                if select_values(select).any(|option| &option.value == value) {
+                    Ok(())
+                } else {
+                    Err(SessionConfigValueError::UnknownValue {
+                        config_id: request.config_id.clone(),
+                        value: value.clone(),
+                    })
+                }
+            }
+            // Any boolean is valid for a toggle. Custom option kinds define
+            // their own value shapes, which cannot be checked here.
+            (SessionConfigKind::Boolean(_), SessionConfigOptionValue::Boolean { .. })
+            | (SessionConfigKind::Other(_), SessionConfigOptionValue::Other(_)) => Ok(()),
+            (kind, _) => Err(SessionConfigValueError::TypeMismatch {
+                config_id: request.config_id.clone(),
+                expected: kind_name(kind).to_owned(),
+            }),
+        }
+    }
+}
+
+/// Differences between two successive configuration snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionConfigDiff {
+    /// Options present only in the new snapshot.
+    pub added: Vec<SessionConfigId>,
+    /// Options present only in the previous snapshot.
+    pub removed: Vec<SessionConfigOption>,
+    /// Options whose definition (name, description, category, kind or
+    /// choices) changed, ignoring the current value.
+    ///
+    /// Any change to a custom option kind is reported here, because its
+    /// current value cannot be told apart from the rest of its payload.
+    pub changed: Vec<SessionConfigId>,
+    /// Options whose current value changed.
+    pub value_changes: Vec<SessionConfigValueChange>,
+}
+
+impl SessionConfigDiff {
+    /// Computes the differences between two snapshots.
+    #[must_use]
+    pub fn between(previous: &[SessionConfigOption], current: &[SessionConfigOption]) -> Self {
+        let mut diff = Self::default();
+
+        for option in current {
+            let Some(old) = previous
+                .iter()
+                .find(|old| old.config_id == option.config_id)
+            else {
+                diff.added.push(option.config_id.clone());
+                continue;
+            };
+
+            if let (Some(old_value), Some(new_value)) = (current_value(old), current_value(option))
+                && old_value != new_value
+            {
+                diff.value_changes.push(SessionConfigValueChange {
+                    config_id: option.config_id.clone(),
+                    previous: old_value,
+                    current: new_value,
+                });
+            }
+            if without_value(old) != without_value(option) {
+                diff.changed.push(option.config_id.clone());
+            }
+        }
+
+        diff.removed = previous
+            .iter()
+            .filter(|old| {
+                current
+                    .iter()
+                    .all(|option| option.config_id != old.config_id)
+            })
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    /// Returns `true` if the snapshots were equivalent.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.value_changes.is_empty()
+    }
+}
+
+/// A change to the current value of a configuration option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionConfigValueChange {
+    /// The option whose value changed.
+    pub config_id: SessionConfigId,
+    /// The value before the snapshot was applied.
+    pub previous: SessionConfigOptionValue,
+    /// The value after the snapshot was applied.
+    pub current: SessionConfigOptionValue,
+}
+
+/// Reason a [`SetSessionConfigOptionRequest`] does not fit the known options.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum SessionConfigValueError {
+    /// The session has no option with this id.
+    #[display("unknown session config option `{_0}`")]
+    UnknownOption(SessionConfigId),
+    /// The value's type does not match the option's kind.
+    #[display("session config option `{config_id}` expects a {expected} value")]
+    TypeMismatch {
+        /// The option being set.
+        config_id: SessionConfigId,
+        /// The kind of value the option accepts.
+        expected: String,
+    },
+    /// The value is not one of the option's choices.
+    #[display("`{value}` is not a valid value for session config option `{config_id}`")]
+    UnknownValue {
+        /// The option being set.
+        config_id: SessionConfigId,
+        /// The rejected value.
+        value: SessionConfigValueId,
+    },
+}
+
+impl std::error::Error for SessionConfigValueError {}
+
+impl From<SessionConfigValueError> for Error {
+    fn from(error: SessionConfigValueError) -> Self {
+        Error::invalid_params().data(error.to_string())
+    }
+}
+
+/// Every choice of a select option, flattening groups.
+fn select_values(select: &SessionConfigSelect) -> impl Iterator<Item = &SessionConfigSelectOption> {
+    let (ungrouped, grouped) = match &select.options {
+        SessionConfigSelectOptions::Ungrouped(options) => (options.as_slice(), [].as_slice()),
+        SessionConfigSelectOptions::Grouped(groups) => ([].as_slice(), groups.as_slice()),
+    };
+    ungrouped
+        .iter()
+        .chain(grouped.iter().flat_map(|group| &group.options))
+}
+
+fn current_value(option: &SessionConfigOption) -> Option<SessionConfigOptionValue> {
+    match &option.kind {
+        SessionConfigKind::Select(select) => {
+            Some(SessionConfigOptionValue::id(select.current_value.clone()))
+        }
+        SessionConfigKind::Boolean(boolean) => {
+            Some(SessionConfigOptionValue::boolean(boolean.current_value))
+        }
+        SessionConfigKind::Other(_) => None,
+    }
+}
+
+/// A copy of the option with its current value blanked, for comparing definitions.
+fn without_value(option: &SessionConfigOption) -> SessionConfigOption {
+    let mut option = option.clone();
+    match &mut option.kind {
+        SessionConfigKind::Select(select) => select.current_value = SessionConfigValueId::new(""),
+        SessionConfigKind::Boolean(boolean) => boolean.current_value = false,
+        SessionConfigKind::Other(_) => {}
+    }
+    option
+}
+
+fn kind_name(kind: &SessionConfigKind) -> &str {
+    match kind {
+        SessionConfigKind::Select(_) => "select",
+        SessionConfigKind::Boolean(_) => "boolean",
+        SessionConfigKind::Other(other) => &other.type_,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    use crate::v2::{
+        OtherSessionConfigKind, OtherSessionConfigOptionValue, SessionConfigSelectGroup,
+    };
+
+    fn model(current: &'static str) -> SessionConfigOption {
+        SessionConfigOption::select(
+            "model",
+            "Model",
+            current,
+            vec![
+                SessionConfigSelectGroup::new(
+                    "fast",
+                    "Fast",
+                    vec![SessionConfigSelectOption::new("haiku", "Haiku")],
+                ),
+                SessionConfigSelectGroup::new(
+                    "smart",
+                    "Smart",
+                    vec![SessionConfigSelectOption::new("opus", "Opus")],
+                ),
+            ],
+        )
+        .category(SessionConfigOptionCategory::Model)
+    }
+
+    fn mode() -> SessionConfigOption {
+        SessionConfigOption::select(
+            "mode",
+            "Mode",
+            "ask",
+            vec![
+                SessionConfigSelectOption::new("ask", "Ask"),
+                SessionConfigSelectOption::new("code", "Code"),
+            ],
+        )
+        .category(SessionConfigOptionCategory::Mode)
+    }
+
+    #[test]
+    fn apply_reports_added_removed_changed_and_value_changes() {
+        let mut store = SessionConfigStore::new();
+        let diff = store.apply(vec![model("haiku"), mode()]);
+        assert_eq!(
+            diff.added,
+            [SessionConfigId::new("model"), SessionConfigId::new("mode")]
+        );
+        assert!(diff.removed.is_empty());
+
+        let diff = store.apply(vec![
+            model("opus").description("Pick a model"),
+            SessionConfigOption::boolean("web", "Web search", true),
+        ]);
+        assert_eq!(diff.added, [SessionConfigId::new("web")]);
+        assert_eq!(diff.removed, [mode()]);
+        assert_eq!(diff.changed, [SessionConfigId::new("model")]);
+        assert_eq!(
+            diff.value_changes,
+            [SessionConfigValueChange {
+                config_id: "model".into(),
+                previous: SessionConfigOptionValue::id("haiku"),
+                current: SessionConfigOptionValue::id("opus"),
+            }]
+        );
+
+        let diff = store.apply_update(ConfigOptionUpdate::new(store.options().to_vec()));
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn options_are_exposed_by_category() {
+        let mut store = SessionConfigStore::new();
+        store.apply(vec![model("haiku"), mode()]);
+
+        assert_eq!(store.model().map(|o| &o.config_id), Some(&"model".into()));
+        assert_eq!(store.mode().map(|o| &o.config_id), Some(&"mode".into()));
+        assert!(store.thought_level().is_none());
+        assert_eq!(
+            store.current_value(&"model".into()),
+            Some(SessionConfigOptionValue::id("haiku"))
+        );
+    }
+
+    #[test]
+    fn validate_checks_kind_and_allowed_values() {
+        let mut store = SessionConfigStore::new();
+        store.apply(vec![
+            model("haiku"),
+            SessionConfigOption::boolean("web", "Web search", false),
+        ]);
+
+        assert_eq!(
+            store.validate(&SetSessionConfigOptionRequest::new("s", "model", "opus")),
+            Ok(())
+        );
+        assert_eq!(
+            store.validate(&SetSessionConfigOptionRequest::new("s", "web", true)),
+            Ok(())
+        );
+        assert_eq!(
+            store.validate(&SetSessionConfigOptionRequest::new("s", "model", "gpt")),
+            Err(SessionConfigValueError::UnknownValue {
+                config_id: "model".into(),
+                value: "gpt".into(),
+            })
+        );
+
+        let err = store
+            .validate(&SetSessionConfigOptionRequest::new("s", "web", "on"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "session config option `web` expects a boolean value"
+        );
+        assert_eq!(
+            store.validate(&SetSessionConfigOptionRequest::new("s", "nope", true)),
+            Err(SessionConfigValueError::UnknownOption("nope".into()))
+        );
+    }
+
+    #[test]
+    fn custom_kinds_are_diffed_as_definitions_and_accept_custom_values() {
+        let slider = |value: i64| {
+            SessionConfigOption::new(
+                "temperature",
+                "Temperature",
+                SessionConfigKind::Other(OtherSessionConfigKind::new(
+                    "_slider",
+                    BTreeMap::from([("currentValue".to_owned(), value.into())]),
+                )),
+            )
+        };
+
+        let mut store = SessionConfigStore::new();
+        store.apply(vec![slider(1)]);
+        assert_eq!(store.current_value(&"temperature".into()), None);
+
+        let diff = store.apply(vec![slider(2)]);
+        assert_eq!(diff.changed, [SessionConfigId::new("temperature")]);
+        assert!(diff.value_changes.is_empty());
+
+        let custom = SessionConfigOptionValue::Other(OtherSessionConfigOptionValue::new(
+            "_number",
+            3.into(),
+            BTreeMap::new(),
+        ));
+        assert_eq!(
+            store.validate(&SetSessionConfigOptionRequest::new(
+                "s",
+                "temperature",
+                custom
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            store
+                .validate(&SetSessionConfigOptionRequest::new(
+                    "s",
+                    "temperature",
+                    true
+                ))
+                .unwrap_err()
+                .to_string(),
+            "session config option `temperature` expects a _slider value"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Choosing how to reopen an existing session from the agent's capabilities.
+//!
+//! Every agent with session support accepts `session/resume`, which replays
+//! history only when asked to with `replayFrom`; some also support
+//! `session/fork`. A [`SessionReopener`] picks the method that satisfies a
+//! [`ReopenIntent`] and builds its request.
+
+use derive_more::Display;
+
+#[cfg(feature = "unstable_session_fork")]
+use super::ForkSessionRequest;
+use super::{
+    AGENT_METHOD_NAMES, AbsolutePath, AgentCapabilities, ClientRequest, CloseSessionRequest, Error,
+    McpServer, ReplayFrom, ReplayFromStart, ResumeSessionRequest, SessionCapabilities, SessionId,
+    SessionInfo,
+};
+
+/// What the user wants from reopening a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[non_exhaustive]
+pub enum ReopenIntent {
+    /// Keep working in the session without replaying its history.
+    #[display("continue")]
+    Continue,
+    /// Show the session's history again before continuing.
+    #[display("replay")]
+    Replay,
+    /// Start a new session from the existing one's context, leaving it untouched.
+    #[display("branch")]
+    Branch,
+}
+
+/// The request chosen by a [`SessionReopener`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReopenRequest {
+    /// `session/resume`, replaying history when `replayFrom` is set.
+    Resume(ResumeSessionRequest),
+    /// **UNSTABLE**
+    ///
+    /// This capability is not part of the spec yet, and may be removed or changed at any point.
+    ///
+    /// `session/fork`: the agent creates a new session from the existing one.
+    #[cfg(feature = "unstable_session_fork")]
+    Fork(ForkSessionRequest),
+}
+
+impl ReopenRequest {
+    /// The method this request is sent with.
+    #[must_use]
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Resume(_) => AGENT_METHOD_NAMES.session_resume,
+            #[cfg(feature = "unstable_session_fork")]
+            Self::Fork(_) => AGENT_METHOD_NAMES.session_fork,
+        }
+    }
+
+    /// Returns whether the agent will replay the session's history before responding.
+    #[must_use]
+    pub fn replays_history(&self) -> bool {
+        matches!(self, Self::Resume(request) if request.replay_from.is_some())
+    }
+
+    /// List of MCP servers to connect to for this session.
+    #[must_use]
+    pub fn mcp_servers(mut self, mcp_servers: Vec<McpServer>) -> Self {
+        match &mut self {
+            Self::Resume(request) => request.mcp_servers = mcp_servers,
+            #[cfg(feature = "unstable_session_fork")]
+            Self::Fork(request) => request.mcp_servers = mcp_servers,
+        }
+        self
+    }
+
+    /// Additional workspace roots to activate for this session. Each path must be absolute.
+    #[must_use]
+    pub fn additional_directories<I, P>(mut self, additional_directories: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<AbsolutePath>,
+    {
+        let additional_directories = additional_directories.into_iter().map(Into::into).collect();
+        match &mut self {
+            Self::Resume(request) => request.additional_directories = additional_directories,
+            #[cfg(feature = "unstable_session_fork")]
+            Self::Fork(request) => request.additional_directories = additional_directories,
+        }
+        self
+    }
+}
+
+impl From<ReopenRequest> for ClientRequest {
+    fn from(request: ReopenRequest) -> Self {
+        match request {
+            ReopenRequest::Resume(request) => Self::ResumeSessionRequest(Box::new(request)),
+            #[cfg(feature = "unstable_session_fork")]
+            ReopenRequest::Fork(request) => Self::ForkSessionRequest(Box::new(request)),
+        }
+    }
+}
+
+/// Picks `session/resume` or `session/fork` for a [`ReopenIntent`] based on
+/// what the agent advertised.
+///
+/// | Intent                       | Request                                     |
+/// |------------------------------|---------------------------------------------|
+/// | [`ReopenIntent::Continue`]   | `session/resume`                            |
+/// | [`ReopenIntent::Replay`]     | `session/resume` from [`ReplayFrom::Start`] |
+/// | [`ReopenIntent::Branch`]     | `session/fork`                              |
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionReopener {
+    session: Option<SessionCapabilities>,
+}
+
+impl SessionReopener {
+    /// Builds a reopener from the capabilities in the agent's `initialize` response.
+    #[must_use]
+    pub fn new(capabilities: &AgentCapabilities) -> Self {
+        Self {
+            session: capabilities.session.clone(),
+        }
+    }
+
+    /// Returns whether [`Self::plan`] can satisfy `intent`.
+    #[must_use]
+    pub fn supports(&self, intent: ReopenIntent) -> bool {
+        match intent {
+            ReopenIntent::Continue | ReopenIntent::Replay => self.session.is_some(),
+            #[cfg(feature = "unstable_session_fork")]
+            ReopenIntent::Branch => self
+                .session
+                .as_ref()
+                .is_some_and(|session| session.fork.is_some()),
+            #[cfg(not(feature = "unstable_session_fork"))]
+            ReopenIntent::Branch => false,
+        }
+    }
+
+    /// Builds the request that reopens `session_id` in `cwd` for `intent`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReopenError::Unsupported`] when the agent advertised no
+    /// method satisfying `intent`.
+    pub fn plan(
+        &self,
+        intent: ReopenIntent,
+        session_id: impl Into<SessionId>,
+        cwd: impl Into<AbsolutePath>,
+    ) -> Result<ReopenRequest, ReopenError> {
+        if !self.supports(intent) {
+            return Err(ReopenError::Unsupported(intent));
+        }
+        let (session_id, cwd) = (session_id.into(), cwd.into());
+        Ok(match intent {
+            #[cfg(feature = "unstable_session_fork")]
+            ReopenIntent::Branch => ReopenRequest::Fork(ForkSessionRequest::new(session_id, cwd)),
+            ReopenIntent::Replay => ReopenRequest::Resume(
+                ResumeSessionRequest::new(session_id, cwd)
+                    .replay_from(ReplayFrom::Start(ReplayFromStart::new())),
+            ),
+            _ => ReopenRequest::Resume(ResumeSessionRequest::new(session_id, cwd)),
+        })
+    }
+
+    /// Builds the request reopening a session returned by `session/list`,
+    /// keeping its additional workspace roots.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReopenError::Unsupported`] when the agent advertised no
+    /// method satisfying `intent`.
+    pub fn plan_listed(
+        &self,
+        intent: ReopenIntent,
+        session: &SessionInfo,
+    ) -> Result<ReopenRequest, ReopenError> {
+        Ok(self
+            .plan(intent, session.session_id.clone(), session.cwd.clone())?
+            .additional_directories(session.additional_directories.clone()))
+    }
+
+    /// Builds a `session/close` request, or `None` when the agent does not
+    /// support sessions at all.
+    #[must_use]
+    pub fn close(&self, session_id: impl Into<SessionId>) -> Option<CloseSessionRequest> {
+        self.session
+            .is_some()
+            .then(|| CloseSessionRequest::new(session_id))
+    }
+}
+
+/// A [`ReopenIntent`] the agent cannot satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+#[non_exhaustive]
+pub enum ReopenError {
+    /// The agent advertised no method for this intent.
+    #[display("agent cannot {_0} sessions")]
+    Unsupported(ReopenIntent),
+}
+
+impl std::error::Error for ReopenError {}
+
+impl From<ReopenError> for Error {
+    fn from(error: ReopenError) -> Self {
+        Error::method_not_found().data(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reopener() -> SessionReopener {
+        SessionReopener::new(&AgentCapabilities::new().session(SessionCapabilities::new()))
+    }
+
+    #[test]
+    fn continue_and_replay_both_resume() {
+        let request = reopener()
+            .plan(ReopenIntent::Continue, "s", "/repo")
+            .unwrap();
+        assert_eq!(request.method(), "session/resume");
+        assert!(!request.replays_history());
+
+        let request = reopener().plan(ReopenIntent::Replay, "s", "/repo").unwrap();
+        assert_eq!(
+            request,
+            ReopenRequest::Resume(
+                ResumeSessionRequest::new("s", "/repo")
+                    .replay_from(ReplayFrom::Start(ReplayFromStart::new()))
+            )
+        );
+        assert!(request.replays_history());
+    }
+
+    #[test]
+    fn agents_without_sessions_support_nothing() {
+        let none = SessionReopener::new(&AgentCapabilities::new());
+        assert_eq!(
+            none.plan(ReopenIntent::Continue, "s", "/repo"),
+            Err(ReopenError::Unsupported(ReopenIntent::Continue))
+        );
+        assert_eq!(none.close("s"), None);
+        assert_eq!(reopener().close("s"), Some(CloseSessionRequest::new("s")));
+        assert!(!reopener().supports(ReopenIntent::Branch));
+    }
+
+    #[test]
+    fn listed_sessions_keep_their_roots() {
+        let session = SessionInfo::new("s", "/repo").additional_directories(["/lib"]);
+        let ClientRequest::ResumeSessionRequest(request) = reopener()
+            .plan_listed(ReopenIntent::Continue, &session)
+            .unwrap()
+            .into()
+        else {
+            panic!("expected session/resume");
+        };
+        assert_eq!(
+            *request,
+            ResumeSessionRequest::new("s", "/repo").additional_directories(["/lib"])
+        );
+    }
+
+    #[cfg(feature = "unstable_session_fork")]
+    #[test]
+    fn branch_uses_fork() {
+        use crate::v2::SessionForkCapabilities;
+
+        let reopener = SessionReopener::new(
+            &AgentCapabilities::new()
+                .session(SessionCapabilities::new().fork(SessionForkCapabilities::new())),
+        );
+        let request = reopener.plan(ReopenIntent::Branch, "s", "/repo").unwrap();
+        assert_eq!(request.method(), "session/fork");
+    }
+}
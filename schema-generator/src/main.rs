@@ -458,7 +458,13 @@ mod schema_annotation_tests {
     fn generated_v2_schema_references_semantic_string_types() {
         let schema = root_schema_value();
 
-        for definition in ["AbsolutePath", "SessionListCursor", "MediaType"] {
+        for definition in [
+            "AbsolutePath",
+            "SessionListCursor",
+            "MediaType",
+            "Timestamp",
+            "Uri",
+        ] {
             assert_eq!(
                 def_schema(&schema, definition)
                     .get("type")
@@ -496,6 +502,14 @@ mod schema_annotation_tests {
             ("ResourceLink", "mimeType", "MediaType"),
             ("Icon", "mimeType", "MediaType"),
             ("DiffChange", "mimeType", "MediaType"),
+            ("SessionInfo", "updatedAt", "Timestamp"),
+            ("SessionInfoUpdate", "updatedAt", "Timestamp"),
+            ("Annotations", "lastModified", "Timestamp"),
+            ("ImageContent", "uri", "Uri"),
+            ("TextResourceContents", "uri", "Uri"),
+            ("BlobResourceContents", "uri", "Uri"),
+            ("ResourceLink", "uri", "Uri"),
+            ("Icon", "src", "Uri"),
         ] {
             let property = property_schema(&schema, definition, property);
             assert!(